@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use tracing::error;
+
+/// What an audit entry is about.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditKind {
+    Alert,
+    Escalation,
+    DueSoon,
+    Liquidation,
+    Recovered,
+    EndpointFailed,
+    EndpointRecovered,
+}
+
+/// One line of the audit log. Bid fields are left out for endpoint events.
+#[derive(Debug, Serialize)]
+pub struct AuditEvent<'a> {
+    pub timestamp: i64,
+    pub event: AuditKind,
+    pub chain_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bid_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub principal: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<&'a str>,
+    /// Whether the notifier accepted the message
+    pub sent: bool,
+}
+
+impl<'a> AuditEvent<'a> {
+    pub fn bid(event: AuditKind, timestamp: i64, chain_id: i32, bid_id: &'a str, principal: &'a str, sent: bool) -> Self {
+        Self {
+            timestamp,
+            event,
+            chain_id,
+            endpoint: None,
+            bid_id: Some(bid_id),
+            principal: Some(principal),
+            severity: None,
+            sent,
+        }
+    }
+
+    pub fn endpoint(event: AuditKind, timestamp: i64, chain_id: i32, endpoint: &'a str, sent: bool) -> Self {
+        Self {
+            timestamp,
+            event,
+            chain_id,
+            endpoint: Some(endpoint),
+            bid_id: None,
+            principal: None,
+            severity: None,
+            sent,
+        }
+    }
+
+    pub fn with_severity(mut self, severity: Option<&'a str>) -> Self {
+        self.severity = severity;
+        self
+    }
+}
+
+/// Append-only JSONL record of every alert, kept apart from the dedup store for post-incident
+/// review. Writes that fail are logged and never hold up alerting.
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    path: Option<PathBuf>,
+}
+
+impl AuditLog {
+    /// An audit log writing to `path`, or one that records nothing without a path.
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path }
+    }
+
+    pub fn record(&self, event: &AuditEvent) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let line = match serde_json::to_string(event) {
+            Ok(line) => line + "\n",
+            Err(e) => {
+                error!(error = %e, "Failed to serialize audit event");
+                return;
+            }
+        };
+        // A single append per line keeps concurrent endpoint tasks from interleaving entries
+        let appended = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(line.as_bytes()));
+        if let Err(e) = appended {
+            error!(path = %path.display(), error = %e, "Failed to append to audit log");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_appends_json_lines() {
+        let dir = std::env::temp_dir().join(format!("audit_log_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit.jsonl");
+        let _ = fs::remove_file(&path);
+        let audit = AuditLog::new(Some(path.clone()));
+
+        audit.record(&AuditEvent::bid(AuditKind::Alert, 1_000, 137, "42", "1000000", true).with_severity(Some("critical")));
+        audit.record(&AuditEvent::endpoint(AuditKind::EndpointFailed, 1_060, 137, "Polygon", false));
+
+        let lines: Vec<serde_json::Value> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                serde_json::json!({
+                    "timestamp": 1_000, "event": "alert", "chain_id": 137, "bid_id": "42",
+                    "principal": "1000000", "severity": "critical", "sent": true
+                }),
+                serde_json::json!({
+                    "timestamp": 1_060, "event": "endpoint_failed", "chain_id": 137, "endpoint": "Polygon", "sent": false
+                }),
+            ]
+        );
+
+        // Unwritable paths are logged, not fatal
+        AuditLog::new(Some(dir.join("missing").join("audit.jsonl")))
+            .record(&AuditEvent::endpoint(AuditKind::EndpointRecovered, 0, 1, "Mainnet", true));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,57 @@
+use crate::notifier::{Notifier, split_message};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::json;
+
+// Discord rejects message content longer than this
+const MAX_CONTENT_CHARS: usize = 2000;
+
+pub struct DiscordWebhook {
+    client: Client,
+    webhook_url: String,
+}
+
+impl DiscordWebhook {
+    pub fn new(client: Client, webhook_url: String) -> Self {
+        Self {
+            client,
+            webhook_url,
+        }
+    }
+
+    pub async fn send_message(&self, content: &str) -> anyhow::Result<()> {
+        let payload = json!({
+            "content": content
+        });
+
+        let response = self
+            .client
+            .post(&self.webhook_url)
+            .header("Content-Type", "application/json")
+            .json(&payload)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Discord webhook returned HTTP {}: {}", status, body);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notifier for DiscordWebhook {
+    async fn notify(&self, _channel: Option<&str>, msg: &str) -> anyhow::Result<()> {
+        for chunk in split_message(msg, MAX_CONTENT_CHARS) {
+            self.send_message(&chunk).await?;
+        }
+        Ok(())
+    }
+
+    fn max_message_chars(&self) -> usize {
+        MAX_CONTENT_CHARS
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{RetryPolicy, lock_or_recover, make_post_request_with_retry, rpc_label};
+use ethers::abi::{ParamType, Token, decode};
+use ethers::providers::ens::{ADDR_SELECTOR, ENS_ADDRESS, NAME_SELECTOR, namehash, reverse_address};
+use ethers::types::{Address, H256};
+use reqwest::Client;
+use reqwest::header::HeaderMap;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+// How long a looked up name (or the lack of one) is reused, defaults for `ens_cache_ttl_secs`
+pub const DEFAULT_ENS_CACHE_TTL_SECS: u64 = 24 * 3600;
+
+/// The only chain ENS names are looked up for.
+pub const ENS_CHAIN_ID: i32 = 1;
+
+/// resolver(bytes32) on the ENS registry
+const RESOLVER_SELECTOR: [u8; 4] = [0x01, 0x78, 0xb8, 0xbf];
+
+/// Reverse resolves borrower addresses to their primary ENS name over JSON-RPC, caching the
+/// answer for `ttl`.
+pub struct EnsCache {
+    client: Client,
+    rpc_urls: Vec<String>,
+    retry: RetryPolicy,
+    ttl: Duration,
+    entries: Mutex<HashMap<Address, (Option<String>, Instant)>>,
+}
+
+impl EnsCache {
+    pub(crate) fn new(client: Client, rpc_urls: Vec<String>, retry: RetryPolicy, ttl: Duration) -> Self {
+        Self {
+            client,
+            rpc_urls,
+            retry,
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The primary ENS name of `address`, from the cache while fresh. A lookup that fails is
+    /// logged and gives `None` without being cached, so alerts fall back to the raw address.
+    pub async fn name(&self, address: &str) -> Option<String> {
+        let address: Address = address.trim().parse().ok()?;
+        let cached = lock_or_recover(&self.entries, "ENS cache").get(&address).cloned();
+        if let Some((name, looked_up_at)) = cached
+            && looked_up_at.elapsed() < self.ttl
+        {
+            return name;
+        }
+
+        match self.lookup(address).await {
+            Ok(name) => {
+                lock_or_recover(&self.entries, "ENS cache").insert(address, (name.clone(), Instant::now()));
+                name
+            }
+            Err(e) => {
+                warn!(address = ?address, error = %e, "Failed to look up ENS name");
+                None
+            }
+        }
+    }
+
+    /// Reads the reverse record, then checks the name resolves back to `address`, since anyone
+    /// can set a reverse record claiming any name.
+    async fn lookup(&self, address: Address) -> Result<Option<String>, String> {
+        let reverse_node = namehash(&reverse_address(address));
+        let Some(resolver) = self.resolver(reverse_node).await? else {
+            return Ok(None);
+        };
+        let name = decode_string(&self.call(resolver, &[&NAME_SELECTOR[..], &reverse_node.0].concat()).await?)?;
+        if name.is_empty() {
+            return Ok(None);
+        }
+
+        let node = namehash(&name);
+        let Some(resolver) = self.resolver(node).await? else {
+            return Ok(None);
+        };
+        let resolved = decode_address(&self.call(resolver, &[&ADDR_SELECTOR[..], &node.0].concat()).await?)?;
+        if resolved != address {
+            debug!(address = ?address, name, "ENS name does not resolve back to the address");
+            return Ok(None);
+        }
+        Ok(Some(name))
+    }
+
+    /// The resolver set for `node` in the ENS registry, if any.
+    async fn resolver(&self, node: H256) -> Result<Option<Address>, String> {
+        let resolver = decode_address(&self.call(ENS_ADDRESS, &[&RESOLVER_SELECTOR[..], &node.0].concat()).await?)?;
+        Ok((!resolver.is_zero()).then_some(resolver))
+    }
+
+    /// `eth_call` against each RPC URL in order until one answers.
+    async fn call(&self, to: Address, data: &[u8]) -> Result<Vec<u8>, String> {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "eth_call",
+            "params": [{ "to": format!("{:?}", to), "data": format!("0x{}", hex::encode(data)) }, "latest"],
+            "id": 1
+        });
+
+        let mut errors = Vec::new();
+        for rpc_url in &self.rpc_urls {
+            // The URL is left out of errors because it may contain the provider's API key
+            let result = match make_post_request_with_retry(&self.client, rpc_url, body.clone(), None, &HeaderMap::new(), self.retry).await
+            {
+                Ok(response) if response.status.is_success() => {
+                    serde_json::from_str(&response.body).map_err(|e| e.to_string()).and_then(|json| call_result(&json))
+                }
+                Ok(response) => Err(format!("HTTP {}", response.status)),
+                Err(e) => Err(e.without_url().to_string()),
+            };
+            match result {
+                Ok(output) => return Ok(output),
+                Err(e) => errors.push(format!("{}: {}", rpc_label(rpc_url), e)),
+            }
+        }
+        Err(errors.join("; "))
+    }
+}
+
+fn call_result(response: &serde_json::Value) -> Result<Vec<u8>, String> {
+    if let Some(error) = response.get("error") {
+        return Err(format!("RPC error: {}", error));
+    }
+    let result = response
+        .get("result")
+        .and_then(|result| result.as_str())
+        .ok_or_else(|| format!("Invalid eth_call response: {}", response))?;
+    hex::decode(result.trim_start_matches("0x")).map_err(|e| format!("Invalid eth_call result: {}", e))
+}
+
+/// An empty result is a call to an address without code, read as the zero address.
+fn decode_address(output: &[u8]) -> Result<Address, String> {
+    if output.is_empty() {
+        return Ok(Address::zero());
+    }
+    match decode(&[ParamType::Address], output).map_err(|e| e.to_string())?.pop() {
+        Some(Token::Address(address)) => Ok(address),
+        _ => Err("eth_call did not return an address".to_string()),
+    }
+}
+
+fn decode_string(output: &[u8]) -> Result<String, String> {
+    if output.is_empty() {
+        return Ok(String::new());
+    }
+    match decode(&[ParamType::String], output).map_err(|e| e.to_string())?.pop() {
+        Some(Token::String(name)) => Ok(name),
+        _ => Err("eth_call did not return a string".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::encode;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const VITALIK: &str = "0xd8da6bf26964af9d7eed9e03e53415d37aa96045";
+    const RESOLVER: &str = "0x231b0ee14048e9dccd1d247744d114a4eb5e8e63";
+
+    /// JSON-RPC node answering ENS calls as if `VITALIK` had the primary name `name`, which
+    /// resolves to `forward`.
+    async fn serve_ens(name: &'static str, forward: &'static str, calls: Arc<AtomicUsize>) -> String {
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::post(move |axum::Json(body): axum::Json<serde_json::Value>| async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                let data = body["params"][0]["data"].as_str().unwrap().to_string();
+                let output = match &data[2..10] {
+                    "0178b8bf" => encode(&[Token::Address(RESOLVER.parse().unwrap())]),
+                    "691f3431" => encode(&[Token::String(name.to_string())]),
+                    "3b3b57de" => encode(&[Token::Address(forward.parse().unwrap())]),
+                    selector => panic!("unexpected call {}", selector),
+                };
+                axum::Json(serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": format!("0x{}", hex::encode(output)) }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{}/", addr)
+    }
+
+    fn cache(rpc_url: String) -> EnsCache {
+        let retry = RetryPolicy { max_retries: 0, base_backoff_ms: 1 };
+        EnsCache::new(Client::new(), vec![rpc_url], retry, Duration::from_secs(60))
+    }
+
+    #[tokio::test]
+    async fn test_name_is_verified_and_cached() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let ens = cache(serve_ens("vitalik.eth", VITALIK, calls.clone()).await);
+
+        assert_eq!(ens.name("0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045").await.as_deref(), Some("vitalik.eth"));
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_eq!(ens.name(VITALIK).await.as_deref(), Some("vitalik.eth"));
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_eq!(ens.name("not an address").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_name_that_resolves_elsewhere_is_ignored() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let ens = cache(serve_ens("vitalik.eth", RESOLVER, calls).await);
+
+        assert_eq!(ens.name(VITALIK).await, None);
+    }
+
+    #[test]
+    fn test_call_result() {
+        assert_eq!(call_result(&serde_json::json!({ "result": "0x0102" })), Ok(vec![1, 2]));
+        assert!(call_result(&serde_json::json!({ "error": { "message": "execution reverted" } })).is_err());
+        assert_eq!(decode_address(&[]), Ok(Address::zero()));
+        assert_eq!(decode_string(&[]), Ok(String::new()));
+    }
+}
@@ -0,0 +1,165 @@
+use crate::format::{bid_next_due, bid_token_decimals, checksum_address, format_token_amount};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use serde::Serialize;
+
+/// File format written by the `export` subcommand.
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+/// One overdue bid, flattened for a spreadsheet.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ExportRow {
+    pub chain: i32,
+    pub bid_id: String,
+    pub borrower: String,
+    pub token: String,
+    /// Whole tokens at full precision
+    pub principal: String,
+    /// RFC 3339 in the alert timezone, empty when the subgraph has none
+    pub next_due: String,
+    pub status: String,
+}
+
+const CSV_HEADER: &str = "chain,bid_id,borrower,token,principal,next_due,status";
+
+impl ExportRow {
+    pub fn from_bid(chain_id: i32, bid: &serde_json::Value, timezone: Tz) -> Self {
+        let field = |name: &str| bid.get(name).and_then(|v| v.as_str()).unwrap_or("").to_string();
+        let decimals = bid_token_decimals(bid);
+
+        Self {
+            chain: chain_id,
+            bid_id: field("bidId"),
+            borrower: checksum_address(&field("borrowerAddress")),
+            token: bid
+                .get("lendingToken")
+                .and_then(|v| v.get("symbol"))
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string(),
+            principal: format_token_amount(&field("principal"), decimals, decimals),
+            next_due: bid_next_due(bid)
+                .and_then(|due| DateTime::from_timestamp(due, 0))
+                .map(|due| due.with_timezone(&timezone).to_rfc3339())
+                .unwrap_or_default(),
+            status: field("status"),
+        }
+    }
+
+    fn csv_line(&self) -> String {
+        [
+            &self.chain.to_string(),
+            &self.bid_id,
+            &self.borrower,
+            &self.token,
+            &self.principal,
+            &self.next_due,
+            &self.status,
+        ]
+        .map(|value| csv_field(value))
+        .join(",")
+    }
+}
+
+// Leading characters a spreadsheet reads as the start of a formula
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Quotes a CSV field when it contains a delimiter, quote or line break. Fields such as token
+/// symbols come from the subgraph, where anyone can deploy a token, so one that would start a
+/// formula gets a leading `'` to keep a spreadsheet from running it.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(FORMULA_PREFIXES) { format!("'{}", value) } else { value.to_string() };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Renders the rows in `format`, with a header line for CSV.
+pub fn render(rows: &[ExportRow], format: ExportFormat) -> anyhow::Result<String> {
+    Ok(match format {
+        ExportFormat::Csv => std::iter::once(CSV_HEADER.to_string())
+            .chain(rows.iter().map(ExportRow::csv_line))
+            .map(|line| line + "\n")
+            .collect(),
+        ExportFormat::Json => serde_json::to_string_pretty(rows)? + "\n",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row() -> ExportRow {
+        let bid = serde_json::json!({
+            "bidId": "12345",
+            "borrowerAddress": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+            "principal": "1500000",
+            "lendingToken": { "symbol": "USDC", "decimals": 6 },
+            "nextDueDate": "1704067200",
+            "status": "Accepted"
+        });
+        ExportRow::from_bid(1, &bid, chrono_tz::US::Eastern)
+    }
+
+    #[test]
+    fn test_export_row_from_bid() {
+        assert_eq!(
+            row(),
+            ExportRow {
+                chain: 1,
+                bid_id: "12345".to_string(),
+                borrower: "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2".to_string(),
+                token: "USDC".to_string(),
+                principal: "1.500000".to_string(),
+                next_due: "2023-12-31T19:00:00-05:00".to_string(),
+                status: "Accepted".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_render_csv_quotes_fields() {
+        let mut quoted = row();
+        quoted.token = "USD \"Coin\", bridged".to_string();
+
+        let csv = render(&[row(), quoted], ExportFormat::Csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            "1,12345,0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2,USDC,1.500000,2023-12-31T19:00:00-05:00,Accepted"
+        );
+        assert!(lines[2].contains(",\"USD \"\"Coin\"\", bridged\","));
+    }
+
+    #[test]
+    fn test_render_csv_escapes_formulas() {
+        let mut formula = row();
+        formula.token = "=HYPERLINK(\"https://evil.example\",\"USDC\")".to_string();
+        formula.status = "@SUM(A1)".to_string();
+
+        let csv = render(&[formula], ExportFormat::Csv).unwrap();
+        let line = csv.lines().nth(1).unwrap();
+
+        assert!(line.contains(",\"'=HYPERLINK(\"\"https://evil.example\"\",\"\"USDC\"\")\","));
+        assert!(line.ends_with(",'@SUM(A1)"));
+        assert_eq!(csv_field("-1"), "'-1");
+        assert_eq!(csv_field("\tUSDC"), "'\tUSDC");
+    }
+
+    #[test]
+    fn test_render_json() {
+        let json: serde_json::Value = serde_json::from_str(&render(&[row()], ExportFormat::Json).unwrap()).unwrap();
+
+        assert_eq!(json[0]["bid_id"], "12345");
+        assert_eq!(json[0]["chain"], 1);
+    }
+}
@@ -0,0 +1,1721 @@
+use crate::tokens::parse_decimals;
+use crate::{ONE_DAY, ONE_HOUR};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use ethers::types::{Address, U256};
+use ethers::utils::to_checksum;
+use std::collections::{BTreeMap, HashMap};
+
+// Decimal places shown for token amounts unless configured otherwise
+pub const DEFAULT_DISPLAY_DECIMALS: u32 = 2;
+
+// Timezone alert timestamps are shown in unless `ALERT_TIMEZONE` says otherwise
+pub const DEFAULT_TIMEZONE: Tz = chrono_tz::US::Eastern;
+
+// Block explorers for the chains we monitor, overridable and extendable via config
+const DEFAULT_EXPLORERS: &[(i32, &str)] = &[
+    (1, "https://etherscan.io"),
+    (137, "https://polygonscan.com"),
+    (999, "https://hyperevmscan.io"),
+    (8453, "https://basescan.org"),
+    (42161, "https://arbiscan.io"),
+    (747474, "https://katanascan.com"),
+];
+
+pub fn default_explorers() -> HashMap<i32, String> {
+    DEFAULT_EXPLORERS
+        .iter()
+        .map(|(chain_id, url)| (*chain_id, url.to_string()))
+        .collect()
+}
+
+// Names shown next to chain ids in alerts, overridable and extendable via config
+const DEFAULT_CHAIN_NAMES: &[(i32, &str)] = &[
+    (1, "Ethereum"),
+    (10, "Optimism"),
+    (137, "Polygon"),
+    (999, "HyperEVM"),
+    (8453, "Base"),
+    (42161, "Arbitrum"),
+    (747474, "Katana"),
+];
+
+pub fn default_chain_names() -> HashMap<i32, String> {
+    DEFAULT_CHAIN_NAMES
+        .iter()
+        .map(|(chain_id, name)| (*chain_id, name.to_string()))
+        .collect()
+}
+
+/// How links are written into messages, depends on what the notifier renders.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LinkStyle {
+    /// `text (url)`, for backends that only auto-link bare URLs
+    #[default]
+    Plain,
+    /// Slack mrkdwn `<url|text>`
+    Slack,
+}
+
+impl LinkStyle {
+    pub fn link(&self, url: &str, text: &str) -> String {
+        match self {
+            LinkStyle::Plain => format!("{} ({})", text, url),
+            LinkStyle::Slack => format!("<{}|{}>", url, text),
+        }
+    }
+}
+
+/// Settings that shape how alert messages are rendered.
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    pub display_decimals: u32,
+    /// Uppercase token symbol to the decimal places shown for it, overriding `display_decimals`
+    pub token_display_decimals: HashMap<String, u32>,
+    /// Chain id to block explorer base URL
+    pub explorers: HashMap<i32, String>,
+    /// Chain id to human readable chain name
+    pub chain_names: HashMap<i32, String>,
+    pub link_style: LinkStyle,
+    pub timezone: Tz,
+    /// Lowercase token address to the price of one whole token in `fiat_currency`
+    pub fiat_prices: HashMap<String, f64>,
+    /// Lowercase borrower address to its verified ENS name
+    pub ens_names: HashMap<String, String>,
+    pub fiat_currency: String,
+    /// Custom layout for overdue alerts, see `render_alert_template`
+    pub alert_template: Option<String>,
+    /// Adds a button snoozing the bid for this many seconds to Block Kit alerts
+    pub snooze_secs: Option<u64>,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            display_decimals: DEFAULT_DISPLAY_DECIMALS,
+            token_display_decimals: HashMap::new(),
+            explorers: default_explorers(),
+            chain_names: default_chain_names(),
+            link_style: LinkStyle::default(),
+            timezone: DEFAULT_TIMEZONE,
+            fiat_prices: HashMap::new(),
+            ens_names: HashMap::new(),
+            fiat_currency: "usd".to_string(),
+            alert_template: None,
+            snooze_secs: None,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Decimal places shown for amounts of the token with `symbol`.
+    pub fn display_decimals_for(&self, symbol: &str) -> u32 {
+        self.token_display_decimals.get(&symbol.to_uppercase()).copied().unwrap_or(self.display_decimals)
+    }
+
+    /// `Polygon (137)`, or just the id for chains without a known name.
+    pub fn chain_label(&self, chain_id: i32) -> String {
+        match self.chain_names.get(&chain_id) {
+            Some(name) => format!("{} ({})", name, chain_id),
+            None => chain_id.to_string(),
+        }
+    }
+
+    /// Links `address` to its explorer page on `chain_id`, or returns it unchanged for chains
+    /// without a known explorer.
+    pub fn address_link(&self, chain_id: i32, address: &str) -> String {
+        self.labelled_address_link(chain_id, address, address)
+    }
+
+    fn labelled_address_link(&self, chain_id: i32, address: &str, label: &str) -> String {
+        match self.explorers.get(&chain_id) {
+            Some(base) => self
+                .link_style
+                .link(&format!("{}/address/{}", base.trim_end_matches('/'), address), label),
+            None => label.to_string(),
+        }
+    }
+
+    /// The borrower's checksummed address linked to the explorer, or with an ENS name in
+    /// `ens_names` the name followed by the shortened address, e.g. `vitalik.eth (0xd8dA…6045)`.
+    pub fn borrower_label(&self, chain_id: i32, borrower: &str) -> String {
+        let address = checksum_address(borrower);
+        match self.ens_names.get(&borrower.to_lowercase()) {
+            Some(name) => format!("{} ({})", name, self.labelled_address_link(chain_id, &address, &short_address(&address))),
+            None => self.address_link(chain_id, &address),
+        }
+    }
+
+    pub fn tx_link(&self, chain_id: i32, tx_hash: &str) -> String {
+        match self.explorers.get(&chain_id) {
+            Some(base) => self
+                .link_style
+                .link(&format!("{}/tx/{}", base.trim_end_matches('/'), tx_hash), tx_hash),
+            None => tx_hash.to_string(),
+        }
+    }
+}
+
+/// `0xd8dA…6045`, or the address unchanged if it is too short to shorten.
+fn short_address(address: &str) -> String {
+    match (address.get(..6), address.get(address.len().saturating_sub(4)..)) {
+        (Some(start), Some(end)) if address.len() > 12 => format!("{}…{}", start, end),
+        _ => address.to_string(),
+    }
+}
+
+/// Renders a raw integer token amount (as returned by the subgraph) in whole tokens, rounded
+/// half-up to `display_decimals` places. The math stays in `U256` so large 18-decimal
+/// balances are exact. Unparseable amounts are shown as zero.
+pub fn format_token_amount(raw: &str, decimals: u32, display_decimals: u32) -> String {
+    // 10^77 is the largest power of ten that fits in a U256
+    let decimals = decimals.min(77);
+    let display_decimals = display_decimals.min(77);
+
+    let amount = U256::from_dec_str(raw.trim()).unwrap_or_default();
+
+    let scaled = if display_decimals >= decimals {
+        amount.saturating_mul(U256::exp10((display_decimals - decimals) as usize))
+    } else {
+        let divisor = U256::exp10((decimals - display_decimals) as usize);
+        let (quotient, remainder) = amount.div_mod(divisor);
+        if remainder.saturating_mul(U256::from(2)) >= divisor {
+            quotient + 1
+        } else {
+            quotient
+        }
+    };
+
+    if display_decimals == 0 {
+        return scaled.to_string();
+    }
+
+    let (whole, fraction) = scaled.div_mod(U256::exp10(display_decimals as usize));
+    format!("{}.{:0>width$}", whole, fraction.to_string(), width = display_decimals as usize)
+}
+
+/// `format_token_amount` with thousands separators, e.g. `5,000,000.00`, for showing in
+/// alerts. Exports and the audit log keep the ungrouped or raw amount.
+pub fn display_token_amount(raw: &str, decimals: u32, display_decimals: u32) -> String {
+    group_thousands(&format_token_amount(raw, decimals, display_decimals))
+}
+
+/// Puts a comma between each group of three digits in the whole part of a decimal number.
+fn group_thousands(number: &str) -> String {
+    let (whole, fraction) = match number.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (number, None),
+    };
+    let mut grouped = String::with_capacity(number.len() + whole.len() / 3);
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        grouped.push('.');
+        grouped.push_str(fraction);
+    }
+    grouped
+}
+
+/// Renders a fiat amount with thousands separators and two decimals, e.g. `$12,345.00`.
+/// Currencies without a known symbol are shown by code, e.g. `12,345.00 CHF`.
+pub fn format_fiat(amount: f64, currency: &str) -> String {
+    let cents = (amount.max(0.0) * 100.0).round() as u128;
+    let number = group_thousands(&format!("{}.{:02}", cents / 100, cents % 100));
+
+    match currency.to_lowercase().as_str() {
+        "usd" => format!("${}", number),
+        "eur" => format!("€{}", number),
+        "gbp" => format!("£{}", number),
+        other => format!("{} {}", number, other.to_uppercase()),
+    }
+}
+
+/// The bid's principal valued in `options.fiat_currency`, if a price for its token is known.
+fn bid_fiat_value(bid: &serde_json::Value, options: &FormatOptions) -> Option<f64> {
+    let token = bid.get("lendingToken")?.get("id")?.as_str()?.to_lowercase();
+    let price = options.fiat_prices.get(&token)?;
+    let raw: f64 = bid.get("principal")?.as_str()?.trim().parse().ok()?;
+    Some(raw / 10f64.powi(bid_token_decimals(bid).min(77) as i32) * price)
+}
+
+/// The bid's collateral, e.g. `1.50 WETH, 2 BAYC`. `None` when the subgraph returned no
+/// collateral data and `"none"` for a loan without collateral.
+pub fn format_collateral(bid: &serde_json::Value, options: &FormatOptions) -> Option<String> {
+    let entries = bid.get("collateral")?.as_array()?;
+    if entries.is_empty() {
+        return Some("none".to_string());
+    }
+
+    let formatted: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            let amount = entry.get("amount").and_then(|v| v.as_str()).unwrap_or("0");
+            let token = entry.get("token");
+            let symbol = token.and_then(|t| t.get("symbol")).and_then(|v| v.as_str()).unwrap_or("unknown");
+            let decimals = token.and_then(|t| t.get("decimals")).and_then(parse_decimals).unwrap_or(0);
+            format!("{} {}", display_token_amount(amount, decimals, options.display_decimals_for(symbol)), symbol)
+        })
+        .collect();
+    Some(formatted.join(", "))
+}
+
+/// Value of the bid's collateral over the value of its principal, when it has collateral
+/// and `options.fiat_prices` has a price for every token involved.
+pub fn collateral_ratio(bid: &serde_json::Value, options: &FormatOptions) -> Option<f64> {
+    let principal = bid_fiat_value(bid, options).filter(|value| *value > 0.0)?;
+    let entries = bid.get("collateral")?.as_array().filter(|entries| !entries.is_empty())?;
+
+    let mut collateral = 0.0;
+    for entry in entries {
+        let token = entry.get("token")?;
+        let price = options.fiat_prices.get(&token.get("id")?.as_str()?.to_lowercase())?;
+        let raw: f64 = entry.get("amount")?.as_str()?.trim().parse().ok()?;
+        let decimals = token.get("decimals").and_then(parse_decimals).unwrap_or(0);
+        collateral += raw / 10f64.powi(decimals.min(77) as i32) * price;
+    }
+    Some(collateral / principal)
+}
+
+/// Parses a decimal amount in whole tokens (e.g. `"10.5"`) into raw integer units. Digits
+/// beyond `decimals` are dropped.
+pub fn parse_token_amount(amount: &str, decimals: u32) -> Option<U256> {
+    let decimals = decimals.min(77) as usize;
+    let (whole, fraction) = amount.trim().split_once('.').unwrap_or((amount.trim(), ""));
+
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    if !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let whole = if whole.is_empty() { U256::zero() } else { U256::from_dec_str(whole).ok()? };
+    let fraction: String = fraction.chars().take(decimals).collect();
+    let fraction_value = if fraction.is_empty() { U256::zero() } else { U256::from_dec_str(&fraction).ok()? };
+
+    let scaled_fraction = fraction_value.checked_mul(U256::exp10(decimals - fraction.len()))?;
+    whole.checked_mul(U256::exp10(decimals))?.checked_add(scaled_fraction)
+}
+
+/// EIP-55 checksum form of `address` whatever its casing. Values that aren't a `0x`-prefixed
+/// 20-byte hex address are returned unchanged.
+pub fn checksum_address(address: &str) -> String {
+    let is_address = address.len() == 42
+        && address.starts_with("0x")
+        && address[2..].chars().all(|c| c.is_ascii_hexdigit());
+    match address.parse::<Address>() {
+        Ok(parsed) if is_address => to_checksum(&parsed, None),
+        _ => address.to_string(),
+    }
+}
+
+/// Decimals of the bid's lending token, which subgraphs return as either a number or a string.
+pub fn bid_token_decimals(bid: &serde_json::Value) -> u32 {
+    bid.get("lendingToken")
+        .and_then(|v| v.get("decimals"))
+        .and_then(parse_decimals)
+        .unwrap_or(0)
+}
+
+pub fn format_timestamp(time: DateTime<Utc>, timezone: Tz) -> String {
+    time.with_timezone(&timezone).format("%Y-%m-%d %H:%M:%S %Z").to_string()
+}
+
+/// Reads `nextDueDate`, which the subgraph returns as a string of unix seconds.
+pub fn bid_next_due(bid: &serde_json::Value) -> Option<i64> {
+    let value = bid.get("nextDueDate")?;
+    value.as_i64().or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+}
+
+/// Renders `nextDueDate` as a readable date plus how long ago (or until) it is, e.g.
+/// `2024-01-01 07:00:00 EST (overdue by 3d 4h)`. Missing values are `unknown` and values
+/// that don't parse are shown as-is.
+pub fn format_next_due(bid: &serde_json::Value, now: i64, timezone: Tz) -> String {
+    let Some(raw) = bid.get("nextDueDate") else {
+        return "unknown".to_string();
+    };
+
+    let parsed = bid_next_due(bid).and_then(|due| DateTime::from_timestamp(due, 0).map(|dt| (due, dt)));
+    let Some((due, due_utc)) = parsed else {
+        return raw.as_str().map(|s| s.to_string()).unwrap_or_else(|| raw.to_string());
+    };
+
+    let date = format_timestamp(due_utc, timezone);
+    if due <= now {
+        format!("{} (overdue by {})", date, format_duration(now - due))
+    } else {
+        format!("{} (due in {})", date, format_duration(due - now))
+    }
+}
+
+/// The values shown for an overdue bid, shared by the plain text and Block Kit alerts.
+struct BidAlertFields {
+    chain: String,
+    bid_id: String,
+    borrower: String,
+    lending_token: String,
+    principal: String,
+    next_due: String,
+    status: String,
+    tx: Option<String>,
+    collateral: Option<String>,
+    collateral_ratio: Option<String>,
+}
+
+fn bid_alert_fields(bid: &serde_json::Value, chain_id: i32, now: i64, options: &FormatOptions) -> BidAlertFields {
+    let bid_id = bid.get("bidId").and_then(|v| v.as_str()).unwrap_or("unknown");
+    let borrower = bid.get("borrowerAddress").and_then(|v| v.as_str()).unwrap_or("unknown");
+    let principal_raw = bid.get("principal").and_then(|v| v.as_str()).unwrap_or("0");
+    let lending_token = bid
+        .get("lendingToken")
+        .and_then(|v| v.get("symbol"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+    let decimals = bid_token_decimals(bid);
+    let status = bid.get("status").and_then(|v| v.as_str()).unwrap_or("unknown");
+
+    BidAlertFields {
+        chain: options.chain_label(chain_id),
+        bid_id: bid_id.to_string(),
+        borrower: if borrower == "unknown" { borrower.to_string() } else { options.borrower_label(chain_id, borrower) },
+        lending_token: lending_token.to_string(),
+        principal: match bid_fiat_value(bid, options) {
+            Some(value) => format!(
+                "{} (≈ {})",
+                display_token_amount(principal_raw, decimals, options.display_decimals_for(lending_token)),
+                format_fiat(value, &options.fiat_currency)
+            ),
+            None => display_token_amount(principal_raw, decimals, options.display_decimals_for(lending_token)),
+        },
+        next_due: format_next_due(bid, now, options.timezone),
+        status: status.to_string(),
+        tx: bid.get("transactionHash").and_then(|v| v.as_str()).map(|tx_hash| options.tx_link(chain_id, tx_hash)),
+        collateral: format_collateral(bid, options),
+        collateral_ratio: collateral_ratio(bid, options).map(|ratio| format!("{:.0}%", ratio * 100.0)),
+    }
+}
+
+/// How urgent an overdue bid is, picked from the configured severity thresholds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Severity<'a> {
+    pub name: &'a str,
+    pub emoji: &'a str,
+}
+
+/// How long a bid has been overdue, tagged on alert headers so the longest overdue stand out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverdueBucket {
+    UnderOneDay,
+    OneToSevenDays,
+    OverSevenDays,
+}
+
+impl OverdueBucket {
+    pub fn from_overdue_secs(overdue_secs: i64) -> Self {
+        if overdue_secs < ONE_DAY as i64 {
+            Self::UnderOneDay
+        } else if overdue_secs <= 7 * ONE_DAY as i64 {
+            Self::OneToSevenDays
+        } else {
+            Self::OverSevenDays
+        }
+    }
+
+    /// The bucket of a bid `now`, `None` when its `nextDueDate` is missing or doesn't parse.
+    pub fn for_bid(bid: &serde_json::Value, now: i64) -> Option<Self> {
+        bid_next_due(bid).map(|due| Self::from_overdue_secs(now - due))
+    }
+
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Self::UnderOneDay => "[🟡 <1d]",
+            Self::OneToSevenDays => "[🟠 1-7d]",
+            Self::OverSevenDays => "[🔴 >7d]",
+        }
+    }
+}
+
+/// `title` followed by the bucket's tag, when known.
+fn with_bucket(title: String, bucket: Option<OverdueBucket>) -> String {
+    match bucket {
+        Some(bucket) => format!("{} {}", title, bucket.tag()),
+        None => title,
+    }
+}
+
+fn alert_header(severity: Option<&Severity>, bucket: Option<OverdueBucket>) -> String {
+    let title = match severity {
+        Some(severity) => format!("{} Overdue Loan Alert! ({})", severity.emoji, severity.name),
+        None => "🚨 Overdue Loan Alert!".to_string(),
+    };
+    with_bucket(title, bucket)
+}
+
+pub fn format_bid_alert(
+    bid: &serde_json::Value,
+    chain_id: i32,
+    timestamp: &str,
+    now: i64,
+    options: &FormatOptions,
+    severity: Option<&Severity>,
+    bucket: Option<OverdueBucket>,
+) -> String {
+    let header = alert_header(severity, bucket);
+    let fields = bid_alert_fields(bid, chain_id, now, options);
+    match &options.alert_template {
+        Some(template) => render_alert_template(template, &header, timestamp, chain_id, &fields),
+        None => bid_alert_text(&header, timestamp, &fields),
+    }
+}
+
+// Placeholders an alert template may use
+pub const ALERT_TEMPLATE_FIELDS: &[&str] = &[
+    "header",
+    "chain",
+    "chain_id",
+    "bid_id",
+    "borrower",
+    "principal",
+    "token",
+    "next_due",
+    "status",
+    "timestamp",
+    "tx",
+    "collateral",
+    "collateral_ratio",
+];
+
+/// Names of the `{field}` placeholders in `template`, in order.
+pub fn template_placeholders(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start + 1..].find('}') else {
+            break;
+        };
+        names.push(&rest[start + 1..start + 1 + len]);
+        rest = &rest[start + len + 2..];
+    }
+    names
+}
+
+/// Fills each `{field}` in `template` (one of `ALERT_TEMPLATE_FIELDS`) with the bid's value.
+/// `header` is the default first line with the severity, and `tx`, `collateral` and
+/// `collateral_ratio` are empty when unknown.
+/// Unknown placeholders are left as written.
+fn render_alert_template(template: &str, header: &str, timestamp: &str, chain_id: i32, fields: &BidAlertFields) -> String {
+    let chain_id = chain_id.to_string();
+    let value = |name: &str| -> Option<&str> {
+        Some(match name {
+            "header" => header,
+            "chain" => &fields.chain,
+            "chain_id" => &chain_id,
+            "bid_id" => &fields.bid_id,
+            "borrower" => &fields.borrower,
+            "principal" => &fields.principal,
+            "token" => &fields.lending_token,
+            "next_due" => &fields.next_due,
+            "status" => &fields.status,
+            "timestamp" => timestamp,
+            "tx" => fields.tx.as_deref().unwrap_or(""),
+            "collateral" => fields.collateral.as_deref().unwrap_or(""),
+            "collateral_ratio" => fields.collateral_ratio.as_deref().unwrap_or(""),
+            _ => return None,
+        })
+    };
+
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start + 1..].find('}') else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        match value(&rest[start + 1..start + 1 + len]) {
+            Some(value) => rendered.push_str(value),
+            None => rendered.push_str(&rest[start..start + len + 2]),
+        }
+        rest = &rest[start + len + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn bid_alert_text(header: &str, timestamp: &str, fields: &BidAlertFields) -> String {
+    let mut message = format!(
+        "{}\nTimestamp: {}\nChain: {}\nBid ID: {}\nBorrower: {}\nPrincipal Token: {}\nPrincipal Amount: {}\nNext Due Date: {}\nStatus: {}",
+        header, timestamp, fields.chain, fields.bid_id, fields.borrower, fields.lending_token, fields.principal, fields.next_due, fields.status
+    );
+
+    if let Some(collateral) = &fields.collateral {
+        message.push_str(&format!("\nCollateral: {}", collateral));
+    }
+    if let Some(ratio) = &fields.collateral_ratio {
+        message.push_str(&format!("\nCollateral Ratio: {}", ratio));
+    }
+    if let Some(tx) = &fields.tx {
+        message.push_str(&format!("\nLoan Tx: {}", tx));
+    }
+
+    message
+}
+
+/// Pre-warning for a bid that is not overdue yet but will be soon.
+pub fn format_bid_due_soon(bid: &serde_json::Value, chain_id: i32, timestamp: &str, now: i64, options: &FormatOptions) -> String {
+    bid_alert_text("⏰ Loan Due Soon!", timestamp, &bid_alert_fields(bid, chain_id, now, options))
+}
+
+/// Alert for a bid that can be liquidated, showing its collateral and ratio when known.
+pub fn format_liquidation_opportunity(bid: &serde_json::Value, chain_id: i32, timestamp: &str, now: i64, options: &FormatOptions) -> String {
+    bid_alert_text("🔨 Liquidation opportunity", timestamp, &bid_alert_fields(bid, chain_id, now, options))
+}
+
+fn bid_alert_blocks(title: &str, bid: &serde_json::Value, chain_id: i32, timestamp: &str, now: i64, options: &FormatOptions) -> serde_json::Value {
+    let fields = bid_alert_fields(bid, chain_id, now, options);
+    let field = |label: &str, value: &str| serde_json::json!({ "type": "mrkdwn", "text": format!("*{}:*\n{}", label, value) });
+
+    let mut section_fields = vec![
+        field("Chain", &fields.chain),
+        field("Bid", &fields.bid_id),
+        field("Borrower", &fields.borrower),
+        field("Principal", &format!("{} {}", fields.principal, fields.lending_token)),
+        field("Next Due Date", &fields.next_due),
+        field("Status", &fields.status),
+    ];
+    if let Some(collateral) = &fields.collateral {
+        let value = match &fields.collateral_ratio {
+            Some(ratio) => format!("{} ({})", collateral, ratio),
+            None => collateral.clone(),
+        };
+        section_fields.push(field("Collateral", &value));
+    }
+    if let Some(tx) = &fields.tx {
+        section_fields.push(field("Loan Tx", tx));
+    }
+
+    let mut blocks = vec![
+        serde_json::json!({ "type": "header", "text": { "type": "plain_text", "text": title, "emoji": true } }),
+        serde_json::json!({ "type": "section", "fields": section_fields }),
+        serde_json::json!({ "type": "context", "elements": [{ "type": "mrkdwn", "text": timestamp }] }),
+    ];
+    if let Some(snooze_secs) = options.snooze_secs {
+        let target = serde_json::json!({ "chain_id": chain_id, "bid_id": fields.bid_id });
+        blocks.push(serde_json::json!({
+            "type": "actions",
+            "elements": [{
+                "type": "button",
+                "action_id": SNOOZE_ACTION_ID,
+                "text": { "type": "plain_text", "text": format!("😴 Snooze for {}", format_duration(snooze_secs as i64)), "emoji": true },
+                "value": target.to_string(),
+            }]
+        }));
+    }
+    serde_json::Value::Array(blocks)
+}
+
+// `action_id` of the snooze button on Block Kit alerts
+pub const SNOOZE_ACTION_ID: &str = "snooze_bid";
+
+/// The blocks of a snoozed alert: the snooze button is swapped for a note of who snoozed it.
+pub fn format_snoozed_blocks(blocks: &serde_json::Value, note: &str) -> serde_json::Value {
+    let mut blocks: Vec<serde_json::Value> = blocks
+        .as_array()
+        .map(|blocks| blocks.iter().filter(|block| block["type"] != "actions").cloned().collect())
+        .unwrap_or_default();
+    blocks.push(serde_json::json!({ "type": "context", "elements": [{ "type": "mrkdwn", "text": note }] }));
+    serde_json::Value::Array(blocks)
+}
+
+/// Block Kit version of `format_bid_alert`, for Slack channels where scannable fields beat plain text.
+pub fn format_bid_alert_blocks(
+    bid: &serde_json::Value,
+    chain_id: i32,
+    timestamp: &str,
+    now: i64,
+    options: &FormatOptions,
+    severity: Option<&Severity>,
+    bucket: Option<OverdueBucket>,
+) -> serde_json::Value {
+    let title = match severity {
+        Some(severity) => format!("{} Overdue Loan Alert ({})", severity.emoji, severity.name),
+        None => "🚨 Overdue Loan Alert".to_string(),
+    };
+    bid_alert_blocks(&with_bucket(title, bucket), bid, chain_id, timestamp, now, options)
+}
+
+/// Block Kit version of `format_bid_escalation`.
+pub fn format_bid_escalation_blocks(
+    bid: &serde_json::Value,
+    chain_id: i32,
+    timestamp: &str,
+    now: i64,
+    options: &FormatOptions,
+    severity: Option<&Severity>,
+    bucket: Option<OverdueBucket>,
+) -> serde_json::Value {
+    let title = match severity {
+        Some(severity) => format!("🔁 {} Escalation: Loan Still Overdue ({})", severity.emoji, severity.name),
+        None => "🔁 Escalation: Loan Still Overdue".to_string(),
+    };
+    bid_alert_blocks(&with_bucket(title, bucket), bid, chain_id, timestamp, now, options)
+}
+
+pub fn format_bid_recovered(bid: &serde_json::Value, chain_id: i32, timestamp: &str, now: i64, options: &FormatOptions) -> String {
+    let bid_id = bid.get("bidId").and_then(|v| v.as_str()).unwrap_or("unknown");
+    let borrower = match bid.get("borrowerAddress").and_then(|v| v.as_str()) {
+        Some(borrower) => options.borrower_label(chain_id, borrower),
+        None => "unknown".to_string(),
+    };
+    let next_due = format_next_due(bid, now, options.timezone);
+    let status = bid.get("status").and_then(|v| v.as_str()).unwrap_or("unknown");
+
+    format!(
+        "✅ Loan Recovered!\nTimestamp: {}\nChain: {}\nBid ID: {}\nBorrower: {}\nNext Due Date: {}\nStatus: {}",
+        timestamp, options.chain_label(chain_id), bid_id, borrower, next_due, status
+    )
+}
+
+pub fn format_duration(secs: i64) -> String {
+    let secs = secs.max(0);
+    let days = secs / ONE_DAY as i64;
+    let hours = (secs % ONE_DAY as i64) / ONE_HOUR as i64;
+    let minutes = (secs % ONE_HOUR as i64) / 60;
+
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+pub fn format_bid_escalation(
+    bid: &serde_json::Value,
+    chain_id: i32,
+    timestamp: &str,
+    now: i64,
+    options: &FormatOptions,
+    severity: Option<&Severity>,
+    bucket: Option<OverdueBucket>,
+) -> String {
+    let overdue_for = bid_next_due(bid)
+        .map(|due| format_duration(now - due))
+        .unwrap_or_else(|| "unknown".to_string());
+
+    format!(
+        "🔁 Escalation: loan still overdue (overdue for {})\n{}",
+        overdue_for,
+        format_bid_alert(bid, chain_id, timestamp, now, options, severity, bucket)
+    )
+}
+
+fn batch_header(count: usize, chain_id: i32, part: usize, parts: usize) -> String {
+    let mut header = format!("📦 {} Overdue Loan Alert(s) on Chain {}", count, chain_id);
+    if parts > 1 {
+        header.push_str(&format!(" (part {}/{})", part, parts));
+    }
+    header
+}
+
+/// Combines the alerts for one chain into as few messages as fit in `max_chars`, each
+/// starting with a header line. Alerts are never split across messages unless a single
+/// alert is longer than the limit on its own.
+pub fn format_alert_batch(chain_id: i32, alerts: &[String], max_chars: usize) -> Vec<String> {
+    // Reserve room for the longest header any part could get
+    let header_chars = batch_header(alerts.len(), chain_id, alerts.len(), alerts.len()).chars().count();
+    let budget = max_chars.saturating_sub(header_chars);
+
+    let mut groups: Vec<Vec<&str>> = Vec::new();
+    let mut group_chars = 0;
+
+    for alert in alerts {
+        let alert_chars = alert.chars().count() + 2;
+        match groups.last_mut() {
+            Some(group) if group_chars + alert_chars <= budget => {
+                group.push(alert);
+                group_chars += alert_chars;
+            }
+            _ => {
+                groups.push(vec![alert]);
+                group_chars = alert_chars;
+            }
+        }
+    }
+
+    let parts = groups.len();
+    groups
+        .iter()
+        .enumerate()
+        .map(|(index, group)| format!("{}\n\n{}", batch_header(alerts.len(), chain_id, index + 1, parts), group.join("\n\n")))
+        .collect()
+}
+
+/// One endpoint's overdue bids for the daily summary, or why they couldn't be fetched.
+pub struct ChainSummary {
+    pub name: String,
+    pub chain_id: i32,
+    /// The endpoint's `group`, totalled together in the summary's group section
+    pub group: Option<String>,
+    pub bids: Result<Vec<serde_json::Value>, String>,
+}
+
+/// Group name endpoints without a `group` are totalled under.
+pub const DEFAULT_SUMMARY_GROUP: &str = "Ungrouped";
+
+/// Sums raw principal per token symbol, keeping each token's decimals for display.
+fn principal_totals(bids: &[serde_json::Value]) -> BTreeMap<String, (u32, U256)> {
+    let mut totals: BTreeMap<String, (u32, U256)> = BTreeMap::new();
+
+    for bid in bids {
+        let symbol = bid
+            .get("lendingToken")
+            .and_then(|v| v.get("symbol"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown");
+        let decimals = bid_token_decimals(bid);
+        let principal = bid
+            .get("principal")
+            .and_then(|v| v.as_str())
+            .and_then(|raw| U256::from_dec_str(raw).ok())
+            .unwrap_or_default();
+
+        let entry = totals.entry(symbol.to_string()).or_insert((decimals, U256::zero()));
+        entry.1 = entry.1.saturating_add(principal);
+    }
+
+    totals
+}
+
+/// `N overdue` followed by the summed principal per token.
+fn overdue_totals(bids: &[serde_json::Value], options: &FormatOptions) -> String {
+    if bids.is_empty() {
+        return "0 overdue".to_string();
+    }
+    let totals: Vec<String> = principal_totals(bids)
+        .iter()
+        .map(|(symbol, (decimals, raw))| {
+            format!("{} {}", display_token_amount(&raw.to_string(), *decimals, options.display_decimals_for(symbol)), symbol)
+        })
+        .collect();
+    format!("{} overdue, {}", bids.len(), totals.join(", "))
+}
+
+/// The digest, one line per chain. Once any endpoint has a `group` the chains are also totalled
+/// per group, with ungrouped ones under `DEFAULT_SUMMARY_GROUP`.
+pub fn format_daily_summary(timestamp: &str, chains: &[ChainSummary], options: &FormatOptions) -> String {
+    let total: usize = chains.iter().filter_map(|chain| chain.bids.as_ref().ok()).map(|bids| bids.len()).sum();
+    let mut message = format!("📊 Daily Overdue Loan Summary\nTimestamp: {}\nTotal Overdue: {}", timestamp, total);
+
+    for chain in chains {
+        let line = match &chain.bids {
+            Err(e) => format!("query failed: {}", e),
+            Ok(bids) => overdue_totals(bids, options),
+        };
+        message.push_str(&format!("\n{} (Chain ID {}): {}", chain.name, chain.chain_id, line));
+    }
+
+    if chains.iter().all(|chain| chain.group.is_none()) {
+        return message;
+    }
+
+    // Named groups in order, then the default bucket
+    let mut groups: BTreeMap<Option<&str>, (Vec<serde_json::Value>, usize)> = BTreeMap::new();
+    for chain in chains {
+        let (bids, failed) = groups.entry(chain.group.as_deref()).or_default();
+        match &chain.bids {
+            Ok(chain_bids) => bids.extend(chain_bids.iter().cloned()),
+            Err(_) => *failed += 1,
+        }
+    }
+    message.push_str("\n\nBy Group:");
+    let named = groups.iter().filter(|(group, _)| group.is_some());
+    for (group, (bids, failed)) in named.chain(groups.iter().filter(|(group, _)| group.is_none())) {
+        message.push_str(&format!("\n{}: {}", group.unwrap_or(DEFAULT_SUMMARY_GROUP), overdue_totals(bids, options)));
+        if *failed > 0 {
+            message.push_str(&format!(" ({} query failed)", failed));
+        }
+    }
+
+    message
+}
+
+/// Cuts `error` down to `max_chars` characters, marking the cut with `…(truncated)`, so a
+/// multi-kilobyte error page doesn't flood the channel or get the message rejected.
+pub fn truncate_error(error: &str, max_chars: usize) -> String {
+    const SUFFIX: &str = "…(truncated)";
+    if error.chars().count() <= max_chars {
+        return error.to_string();
+    }
+    let kept: String = error.chars().take(max_chars.saturating_sub(SUFFIX.chars().count())).collect();
+    format!("{}{}", kept.trim_end(), SUFFIX)
+}
+
+pub fn format_endpoint_failed(name: &str, url: &str, timestamp: &str, error: &str, down_for_secs: i64) -> String {
+    let mut message = format!(
+        "⚠️ GraphQL Endpoint Failed!\nTimestamp: {}\nEndpoint: {} {}\nError: {}",
+        timestamp, name, url, error
+    );
+
+    // Repeat alerts for an ongoing outage say how long it has lasted
+    if down_for_secs > 0 {
+        message.push_str(&format!("\nDown for: {}", format_duration(down_for_secs)));
+    }
+
+    message
+}
+
+pub fn format_endpoint_recovered(name: &str, url: &str, timestamp: &str, down_for_secs: i64) -> String {
+    format!(
+        "✅ GraphQL Endpoint Recovered!\nTimestamp: {}\nEndpoint: {} {}\nDown for: {}",
+        timestamp, name, url, format_duration(down_for_secs)
+    )
+}
+
+/// Posted to the critical channel once an endpoint has failed `escalate_after` polls in a row.
+pub fn format_endpoint_escalated(name: &str, url: &str, timestamp: &str, consecutive_failures: u64, error: &str) -> String {
+    format!(
+        "🚨 GraphQL Endpoint Down!\nTimestamp: {}\nEndpoint: {} {}\nConsecutive Failures: {}\nError: {}",
+        timestamp, name, url, consecutive_failures, error
+    )
+}
+
+pub fn format_endpoint_escalation_resolved(name: &str, url: &str, timestamp: &str, escalated_for_secs: i64) -> String {
+    format!(
+        "✅ GraphQL Endpoint Back Up!\nTimestamp: {}\nEndpoint: {} {}\nEscalated for: {}",
+        timestamp, name, url, format_duration(escalated_for_secs)
+    )
+}
+
+pub fn format_rpc_failed(chain_id: i32, timestamp: &str, error: &str, down_for_secs: i64) -> String {
+    let mut message = format!(
+        "⚠️ Chain Head Unavailable!\nTimestamp: {}\nChain ID: {}\nError: {}",
+        timestamp, chain_id, error
+    );
+
+    if down_for_secs > 0 {
+        message.push_str(&format!("\nDown for: {}", format_duration(down_for_secs)));
+    }
+
+    message
+}
+
+pub fn format_rpc_recovered(chain_id: i32, timestamp: &str, down_for_secs: i64) -> String {
+    format!(
+        "✅ Chain Head Available Again!\nTimestamp: {}\nChain ID: {}\nDown for: {}",
+        timestamp, chain_id, format_duration(down_for_secs)
+    )
+}
+
+pub fn format_sync_lag(
+    name: &str,
+    url: &str,
+    timestamp: &str,
+    indexed_block: u64,
+    chain_head: u64,
+    lagging_for_secs: i64,
+) -> String {
+    let mut message = format!(
+        "🐢 Subgraph Lagging!\nTimestamp: {}\nEndpoint: {} {}\nIndexed Block: {}\nChain Head: {}\nBlocks Behind: {}",
+        timestamp, name, url, indexed_block, chain_head, chain_head.saturating_sub(indexed_block)
+    );
+
+    if lagging_for_secs > 0 {
+        message.push_str(&format!("\nLagging for: {}", format_duration(lagging_for_secs)));
+    }
+
+    message
+}
+
+pub fn format_sync_caught_up(
+    name: &str,
+    url: &str,
+    timestamp: &str,
+    indexed_block: u64,
+    chain_head: u64,
+    lagged_for_secs: i64,
+) -> String {
+    format!(
+        "✅ Subgraph Caught Up!\nTimestamp: {}\nEndpoint: {} {}\nIndexed Block: {}\nChain Head: {}\nLagged for: {}",
+        timestamp, name, url, indexed_block, chain_head, format_duration(lagged_for_secs)
+    )
+}
+
+pub fn format_endpoint_slow(
+    name: &str,
+    url: &str,
+    timestamp: &str,
+    latency_ms: u64,
+    latency_warn_ms: u64,
+    slow_for_secs: i64,
+) -> String {
+    let mut message = format!(
+        "⏱️ Endpoint Slow!\nTimestamp: {}\nEndpoint: {} {}\nLatency: {}ms (limit {}ms)",
+        timestamp, name, url, latency_ms, latency_warn_ms
+    );
+
+    if slow_for_secs > 0 {
+        message.push_str(&format!("\nSlow for: {}", format_duration(slow_for_secs)));
+    }
+
+    message
+}
+
+pub fn format_endpoint_fast(name: &str, url: &str, timestamp: &str, latency_ms: u64, slow_for_secs: i64) -> String {
+    format!(
+        "✅ Endpoint Responsive Again!\nTimestamp: {}\nEndpoint: {} {}\nLatency: {}ms\nSlow for: {}",
+        timestamp, name, url, latency_ms, format_duration(slow_for_secs)
+    )
+}
+
+pub fn format_results_unchanged(name: &str, url: &str, timestamp: &str, unchanged_polls: u32, bids: usize) -> String {
+    format!(
+        "🧊 Subgraph May Be Stalled!\nTimestamp: {}\nEndpoint: {} {}\nOverdue Bids: {}\nUnchanged for: {} polls",
+        timestamp, name, url, bids, unchanged_polls
+    )
+}
+
+pub fn format_results_changed(name: &str, url: &str, timestamp: &str, unchanged_for_secs: i64) -> String {
+    format!(
+        "✅ Subgraph Results Changing Again\nTimestamp: {}\nEndpoint: {} {}\nUnchanged for: {}",
+        timestamp, name, url, format_duration(unchanged_for_secs)
+    )
+}
+
+pub fn format_panic(timestamp: &str, message: &str, location: Option<&str>, thread: Option<&str>) -> String {
+    let mut text = format!("💥 Monitor Panicked!\nTimestamp: {}\nMessage: {}", timestamp, message);
+    if let Some(location) = location {
+        text.push_str(&format!("\nLocation: {}", location));
+    }
+    if let Some(thread) = thread {
+        text.push_str(&format!("\nThread: {}", thread));
+    }
+    text
+}
+
+pub fn format_alert_cap(name: &str, chain: &str, timestamp: &str, alerts_due: usize, max_alerts: usize) -> String {
+    format!(
+        "🚧 Alert Cap Reached!\nTimestamp: {}\nChain: {}\nEndpoint: {}\n{} overdue loans detected, exceeding the alert cap of {}\n\
+         Held Back: {} (alerted on in later polls once under the cap)",
+        timestamp,
+        chain,
+        name,
+        alerts_due,
+        max_alerts,
+        alerts_due.saturating_sub(max_alerts)
+    )
+}
+
+pub fn format_all_clear(chain: &str, timestamp: &str) -> String {
+    format!("✅ No overdue loans on {}\nTimestamp: {}", chain, timestamp)
+}
+
+pub fn format_heartbeat(timestamp: &str, endpoints: usize, failing: &[&str]) -> String {
+    let mut message = format!(
+        "💓 Monitor Alive\nTimestamp: {}\nHealthy Endpoints: {}/{}",
+        timestamp,
+        endpoints.saturating_sub(failing.len()),
+        endpoints
+    );
+    if !failing.is_empty() {
+        message.push_str(&format!("\nFailing: {}", failing.join(", ")));
+    }
+    message
+}
+
+// Largest loans listed in a value at risk alert
+const VALUE_AT_RISK_TOP_LOANS: usize = 5;
+
+/// Overdue principal on a chain valued in `options.fiat_currency`.
+#[derive(Debug, PartialEq)]
+pub struct ValueAtRisk<'a> {
+    pub total: f64,
+    /// Priced bids with their value, largest first
+    pub loans: Vec<(&'a serde_json::Value, f64)>,
+    /// Bids left out of `total` for lack of a price
+    pub unpriced: usize,
+}
+
+pub fn value_at_risk<'a>(bids: &'a [serde_json::Value], options: &FormatOptions) -> ValueAtRisk<'a> {
+    let mut loans: Vec<_> = bids.iter().filter_map(|bid| Some((bid, bid_fiat_value(bid, options)?))).collect();
+    loans.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    ValueAtRisk {
+        total: loans.iter().map(|(_, value)| value).sum(),
+        unpriced: bids.len() - loans.len(),
+        loans,
+    }
+}
+
+/// Alert for a chain whose overdue principal has passed its `value_at_risk_thresholds` entry.
+pub fn format_value_at_risk(chain_id: i32, timestamp: &str, threshold: f64, risk: &ValueAtRisk, options: &FormatOptions) -> String {
+    let currency = &options.fiat_currency;
+    let mut message = format!(
+        "📊 VaR threshold exceeded\nTimestamp: {}\nChain: {}\nOverdue Principal: {} (threshold {})\nOverdue Loans: {}",
+        timestamp,
+        options.chain_label(chain_id),
+        format_fiat(risk.total, currency),
+        format_fiat(threshold, currency),
+        risk.loans.len() + risk.unpriced
+    );
+
+    message.push_str("\nTop Loans:");
+    for (bid, value) in risk.loans.iter().take(VALUE_AT_RISK_TOP_LOANS) {
+        let bid_id = bid.get("bidId").and_then(|v| v.as_str()).unwrap_or("unknown");
+        let principal = bid.get("principal").and_then(|v| v.as_str()).unwrap_or("0");
+        let symbol = bid
+            .get("lendingToken")
+            .and_then(|v| v.get("symbol"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown");
+        message.push_str(&format!(
+            "\n• Bid {}: {} {} ({})",
+            bid_id,
+            display_token_amount(principal, bid_token_decimals(bid), options.display_decimals_for(symbol)),
+            symbol,
+            format_fiat(*value, currency)
+        ));
+    }
+    if risk.unpriced > 0 {
+        message.push_str(&format!("\n{} loan(s) without a price are not counted", risk.unpriced));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_address() {
+        let checksummed = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2";
+
+        assert_eq!(checksum_address("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"), checksummed);
+        assert_eq!(checksum_address("0xC02AAA39B223FE8D0A0E5C4F27EAD9083C756CC2"), checksummed);
+        assert_eq!(checksum_address(checksummed), checksummed);
+        // Values that aren't addresses pass through as-is
+        assert_eq!(checksum_address("0xabc123def456"), "0xabc123def456");
+        assert_eq!(checksum_address("unknown"), "unknown");
+        assert_eq!(checksum_address("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"), "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+    }
+
+    #[test]
+    fn test_format_bid_alert_with_usdc() {
+        let bid = serde_json::json!({
+            "bidId": "12345",
+            "borrowerAddress": "0xabc123def456",
+            "principal": "1000000",
+            "lendingToken": {
+                "id": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+                "symbol": "USDC",
+                "decimals": 6
+            },
+            "nextDueDate": "1704067200",
+            "status": "Accepted"
+        });
+
+        let message = format_bid_alert(&bid, 1, "2024-01-01 12:00:00 EST", 1704110400, &FormatOptions::default(), None, None);
+
+        assert!(message.contains("🚨 Overdue Loan Alert!"));
+        assert!(message.contains("Chain: Ethereum (1)"));
+        assert!(message.contains("Bid ID: 12345"));
+        assert!(message.contains("Borrower: 0xabc123def456"));
+        assert!(message.contains("Principal Token: USDC"));
+        assert!(message.contains("Principal Amount: 1.00"));
+        assert!(message.contains("Status: Accepted"));
+    }
+
+    #[test]
+    fn test_format_bid_alert_appends_fiat_value_when_priced() {
+        let bid = serde_json::json!({
+            "principal": "12345000000",
+            "lendingToken": { "id": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", "symbol": "USDC", "decimals": 6 }
+        });
+        let options = FormatOptions {
+            fiat_prices: HashMap::from([("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".to_string(), 1.0)]),
+            ..FormatOptions::default()
+        };
+
+        let message = format_bid_alert(&bid, 1, "ts", 0, &options, None, None);
+        assert!(message.contains("Principal Amount: 12,345.00 (≈ $12,345.00)"));
+
+        // Without a price the amount is shown alone
+        let message = format_bid_alert(&bid, 1, "ts", 0, &FormatOptions::default(), None, None);
+        assert!(message.contains("Principal Amount: 12,345.00\n"));
+    }
+
+    #[test]
+    fn test_format_fiat() {
+        assert_eq!(format_fiat(12345.0, "usd"), "$12,345.00");
+        assert_eq!(format_fiat(1234567.891, "USD"), "$1,234,567.89");
+        assert_eq!(format_fiat(0.5, "eur"), "€0.50");
+        assert_eq!(format_fiat(999.999, "chf"), "1,000.00 CHF");
+        assert_eq!(format_fiat(12.0, "usd"), "$12.00");
+        assert_eq!(format_fiat(1_234_567_890.0, "gbp"), "£1,234,567,890.00");
+    }
+
+    #[test]
+    fn test_format_bid_alert_with_18_decimals() {
+        let bid = serde_json::json!({
+            "bidId": "99999",
+            "borrowerAddress": "0xdeadbeef",
+            "principal": "5000000000000000000",
+            "lendingToken": {
+                "id": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+                "symbol": "WETH",
+                "decimals": 18
+            },
+            "nextDueDate": "1704153600",
+            "status": "Accepted"
+        });
+
+        let message = format_bid_alert(&bid, 137, "2024-01-02 12:00:00 EST", 1704110400, &FormatOptions::default(), None, None);
+
+        assert!(message.contains("Chain: Polygon (137)"));
+        assert!(message.contains("Principal Token: WETH"));
+        assert!(message.contains("Principal Amount: 5.00"));
+    }
+
+    #[test]
+    fn test_format_bid_alert_with_missing_fields() {
+        let bid = serde_json::json!({});
+
+        let message = format_bid_alert(&bid, 1, "2024-01-01 12:00:00 EST", 1704110400, &FormatOptions::default(), None, None);
+
+        assert!(message.contains("Bid ID: unknown"));
+        assert!(message.contains("Borrower: unknown"));
+        assert!(message.contains("Principal Token: unknown"));
+        assert!(message.contains("Principal Amount: 0.00"));
+    }
+
+    #[test]
+    fn test_format_bid_escalation_includes_overdue_duration() {
+        let bid = serde_json::json!({
+            "bidId": "12345",
+            "nextDueDate": "1704067200"
+        });
+
+        let now = 1704067200 + 3 * ONE_DAY as i64 + 4 * ONE_HOUR as i64;
+        let message = format_bid_escalation(&bid, 1, "2024-01-04 16:00:00 EST", now, &FormatOptions::default(), None, None);
+
+        assert!(message.starts_with("🔁 Escalation: loan still overdue (overdue for 3d 4h)"));
+        assert!(message.contains("Bid ID: 12345"));
+    }
+
+    #[test]
+    fn test_format_bid_recovered() {
+        let bid = serde_json::json!({
+            "bidId": "12345",
+            "borrowerAddress": "0xabc123def456",
+            "nextDueDate": "1706745600",
+            "status": "Repaid"
+        });
+
+        let message = format_bid_recovered(&bid, 1, "2024-01-15 12:00:00 EST", 1705338000, &FormatOptions::default());
+
+        assert!(message.contains("✅ Loan Recovered!"));
+        assert!(message.contains("Bid ID: 12345"));
+        assert!(message.contains("Status: Repaid"));
+    }
+
+    #[test]
+    fn test_format_token_amount_is_exact_for_large_18_decimal_values() {
+        // 1234567890123456789 wei is 1.234567890123456789 WETH
+        assert_eq!(format_token_amount("1234567890123456789", 18, 2), "1.23");
+        assert_eq!(format_token_amount("1234567890123456789", 18, 18), "1.234567890123456789");
+        // Whole-token amounts well beyond f64's 53 bits of precision
+        assert_eq!(
+            format_token_amount("123456789012345678901234567890123456789", 18, 4),
+            "123456789012345678901.2346"
+        );
+    }
+
+    #[test]
+    fn test_format_token_amount_rounding_and_padding() {
+        assert_eq!(format_token_amount("1005000", 6, 2), "1.01");
+        assert_eq!(format_token_amount("1004999", 6, 2), "1.00");
+        assert_eq!(format_token_amount("5", 0, 2), "5.00");
+        assert_eq!(format_token_amount("1500000", 6, 0), "2");
+        assert_eq!(format_token_amount("not a number", 6, 2), "0.00");
+    }
+
+    #[test]
+    fn test_format_bid_alert_uses_token_display_decimals() {
+        // 0.12345678 WBTC, which two decimals would show as 0.12
+        let bid = serde_json::json!({
+            "bidId": "12345",
+            "principal": "12345678",
+            "lendingToken": { "symbol": "WBTC", "decimals": 8 },
+            "collateral": [{ "amount": "2500000", "token": { "symbol": "USDC", "decimals": 6 } }]
+        });
+        let options = FormatOptions {
+            token_display_decimals: HashMap::from([("WBTC".to_string(), 6)]),
+            ..FormatOptions::default()
+        };
+
+        let message = format_bid_alert(&bid, 137, "2024-01-01 12:00:00 EST", 1704110400, &options, None, None);
+
+        assert!(message.contains("Principal Amount: 0.123457\n"));
+        assert!(message.contains("Collateral: 2.50 USDC"));
+        assert_eq!(options.display_decimals_for("wbtc"), 6);
+    }
+
+    #[test]
+    fn test_display_token_amount_groups_thousands() {
+        assert_eq!(display_token_amount("5000000", 6, 2), "5.00");
+        assert_eq!(display_token_amount("999990000", 6, 2), "999.99");
+        assert_eq!(display_token_amount("1000000000", 6, 2), "1,000.00");
+        assert_eq!(display_token_amount("5000000000000", 6, 2), "5,000,000.00");
+        assert_eq!(display_token_amount("123456789012345678901234567890", 18, 4), "123,456,789,012.3457");
+        assert_eq!(display_token_amount("1234567000000", 6, 0), "1,234,567");
+        // Exports keep the plain amount
+        assert_eq!(format_token_amount("5000000000000", 6, 2), "5000000.00");
+    }
+
+    #[test]
+    fn test_format_bid_alert_shows_ens_name() {
+        let bid = serde_json::json!({ "bidId": "1", "borrowerAddress": "0xd8da6bf26964af9d7eed9e03e53415d37aa96045" });
+        let mut options = FormatOptions {
+            ens_names: HashMap::from([("0xd8da6bf26964af9d7eed9e03e53415d37aa96045".to_string(), "vitalik.eth".to_string())]),
+            ..FormatOptions::default()
+        };
+
+        let message = format_bid_alert(&bid, 1, "2024-01-01 12:00:00 EST", 1704110400, &options, None, None);
+        assert!(message.contains("Borrower: vitalik.eth (0xd8dA…6045 (https://etherscan.io/address/0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045))\n"));
+
+        options.link_style = LinkStyle::Slack;
+        let message = format_bid_alert(&bid, 1, "2024-01-01 12:00:00 EST", 1704110400, &options, None, None);
+        assert!(message.contains("Borrower: vitalik.eth (<https://etherscan.io/address/0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045|0xd8dA…6045>)\n"));
+
+        options.ens_names.clear();
+        let message = format_bid_alert(&bid, 1, "2024-01-01 12:00:00 EST", 1704110400, &options, None, None);
+        assert!(message.contains("|0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045>\n"));
+    }
+
+    #[test]
+    fn test_format_bid_alert_links_borrower_and_loan_for_slack() {
+        let bid = serde_json::json!({
+            "bidId": "12345",
+            "borrowerAddress": "0xabc123def456",
+            "transactionHash": "0xfeedbeef"
+        });
+        let options = FormatOptions { link_style: LinkStyle::Slack, ..FormatOptions::default() };
+
+        let message = format_bid_alert(&bid, 137, "2024-01-01 12:00:00 EST", 1704110400, &options, None, None);
+
+        assert!(message.contains("Borrower: <https://polygonscan.com/address/0xabc123def456|0xabc123def456>"));
+        assert!(message.contains("Loan Tx: <https://polygonscan.com/tx/0xfeedbeef|0xfeedbeef>"));
+    }
+
+    #[test]
+    fn test_format_bid_alert_without_explorer_leaves_address_plain() {
+        let bid = serde_json::json!({ "borrowerAddress": "0xabc123def456" });
+
+        let message = format_bid_alert(&bid, 31337, "2024-01-01 12:00:00 EST", 1704110400, &FormatOptions::default(), None, None);
+
+        assert!(message.contains("Borrower: 0xabc123def456\n"));
+    }
+
+    #[test]
+    fn test_format_next_due_numeric_string() {
+        let bid = serde_json::json!({ "nextDueDate": "1704067200" });
+        let now = 1704067200 + 3 * ONE_DAY as i64 + 4 * ONE_HOUR as i64;
+
+        assert_eq!(format_next_due(&bid, now, DEFAULT_TIMEZONE), "2023-12-31 19:00:00 EST (overdue by 3d 4h)");
+        assert_eq!(
+            format_next_due(&bid, 1704067200 - 2 * ONE_HOUR as i64, DEFAULT_TIMEZONE),
+            "2023-12-31 19:00:00 EST (due in 2h 0m)"
+        );
+    }
+
+    #[test]
+    fn test_format_next_due_missing_field() {
+        assert_eq!(format_next_due(&serde_json::json!({}), 1704067200, DEFAULT_TIMEZONE), "unknown");
+    }
+
+    #[test]
+    fn test_format_next_due_malformed_value() {
+        let bid = serde_json::json!({ "nextDueDate": "next tuesday" });
+
+        assert_eq!(format_next_due(&bid, 1704067200, DEFAULT_TIMEZONE), "next tuesday");
+    }
+
+    #[test]
+    fn test_format_timestamp_in_configured_timezone() {
+        let time = DateTime::from_timestamp(1704067200, 0).unwrap();
+
+        assert_eq!(format_timestamp(time, DEFAULT_TIMEZONE), "2023-12-31 19:00:00 EST");
+        assert_eq!(format_timestamp(time, chrono_tz::UTC), "2024-01-01 00:00:00 UTC");
+        assert_eq!(format_timestamp(time, chrono_tz::Europe::Berlin), "2024-01-01 01:00:00 CET");
+    }
+
+    #[test]
+    fn test_format_endpoint_failure_and_recovery() {
+        let first = format_endpoint_failed("Mainnet", "https://example.com", "2024-01-01 00:00:00 EST", "timed out", 0);
+        assert!(!first.contains("Down for"));
+
+        let repeat = format_endpoint_failed("Mainnet", "https://example.com", "2024-01-01 06:00:00 EST", "timed out", 6 * ONE_HOUR as i64);
+        assert!(repeat.ends_with("Error: timed out\nDown for: 6h 0m"));
+
+        let recovered = format_endpoint_recovered("Mainnet", "https://example.com", "2024-01-01 07:00:00 EST", 7 * ONE_HOUR as i64);
+        assert!(recovered.starts_with("✅ GraphQL Endpoint Recovered!"));
+        assert!(recovered.contains("Endpoint: Mainnet https://example.com"));
+        assert!(recovered.ends_with("Down for: 7h 0m"));
+    }
+
+    #[test]
+    fn test_format_alert_batch_fits_in_one_message() {
+        let alerts = vec!["alert one".to_string(), "alert two".to_string()];
+
+        assert_eq!(
+            format_alert_batch(1, &alerts, 4000),
+            vec!["📦 2 Overdue Loan Alert(s) on Chain 1\n\nalert one\n\nalert two"]
+        );
+    }
+
+    #[test]
+    fn test_format_alert_batch_splits_between_alerts() {
+        let alerts = vec!["a".repeat(30), "b".repeat(30), "c".repeat(30)];
+        let messages = format_alert_batch(8453, &alerts, 120);
+
+        assert_eq!(messages.len(), 2);
+        assert!(messages.iter().all(|message| message.chars().count() <= 120));
+        assert!(messages[0].starts_with("📦 3 Overdue Loan Alert(s) on Chain 8453 (part 1/2)"));
+        assert!(messages[0].ends_with(&format!("{}\n\n{}", "a".repeat(30), "b".repeat(30))));
+        assert!(messages[1].starts_with("📦 3 Overdue Loan Alert(s) on Chain 8453 (part 2/2)"));
+        assert!(messages[1].ends_with(&"c".repeat(30)));
+    }
+
+    #[test]
+    fn test_format_bid_alert_blocks() {
+        let bid = serde_json::json!({
+            "bidId": "12345",
+            "borrowerAddress": "0xabc",
+            "principal": "1000000",
+            "lendingToken": { "symbol": "USDC", "decimals": 6 },
+            "nextDueDate": "1704067200",
+            "status": "Accepted"
+        });
+        let options = FormatOptions { link_style: LinkStyle::Slack, ..FormatOptions::default() };
+
+        let blocks = format_bid_alert_blocks(&bid, 1, "2024-01-01 07:00:00 EST", 1704110400, &options, None, None);
+
+        assert_eq!(blocks[0]["type"], "header");
+        assert_eq!(blocks[0]["text"]["text"], "🚨 Overdue Loan Alert");
+
+        let fields: Vec<&str> = blocks[1]["fields"].as_array().unwrap().iter().map(|f| f["text"].as_str().unwrap()).collect();
+        assert_eq!(fields[0], "*Chain:*\nEthereum (1)");
+        assert_eq!(fields[1], "*Bid:*\n12345");
+        assert_eq!(fields[2], "*Borrower:*\n<https://etherscan.io/address/0xabc|0xabc>");
+        assert_eq!(fields[3], "*Principal:*\n1.00 USDC");
+        assert_eq!(fields.len(), 6);
+
+        assert_eq!(blocks[2]["type"], "context");
+        assert_eq!(blocks[2]["elements"][0]["text"], "2024-01-01 07:00:00 EST");
+    }
+
+    #[test]
+    fn test_snooze_button_round_trip() {
+        let bid = serde_json::json!({ "bidId": "12345", "status": "Accepted" });
+        let options = FormatOptions { link_style: LinkStyle::Slack, snooze_secs: Some(4 * ONE_HOUR), ..FormatOptions::default() };
+
+        let blocks = format_bid_alert_blocks(&bid, 137, "2024-01-01 07:00:00 EST", 1704110400, &options, None, None);
+        let button = &blocks[3]["elements"][0];
+        assert_eq!(button["action_id"], SNOOZE_ACTION_ID);
+        assert_eq!(button["text"]["text"], "😴 Snooze for 4h 0m");
+        let target: serde_json::Value = serde_json::from_str(button["value"].as_str().unwrap()).unwrap();
+        assert_eq!(target, serde_json::json!({ "chain_id": 137, "bid_id": "12345" }));
+
+        let snoozed = format_snoozed_blocks(&blocks, "😴 Snoozed by <@U123> until 2024-01-01 11:00:00 EST");
+        let types: Vec<&str> = snoozed.as_array().unwrap().iter().map(|block| block["type"].as_str().unwrap()).collect();
+        assert_eq!(types, ["header", "section", "context", "context"]);
+        assert_eq!(snoozed[3]["elements"][0]["text"], "😴 Snoozed by <@U123> until 2024-01-01 11:00:00 EST");
+
+        // No button unless snoozing is enabled
+        assert_eq!(format_bid_alert_blocks(&bid, 137, "", 0, &FormatOptions::default(), None, None).as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_format_bid_alert_with_severity() {
+        let bid = serde_json::json!({ "bidId": "12345", "status": "Accepted" });
+        let severity = Severity { name: "critical", emoji: "🔥" };
+
+        let message = format_bid_alert(&bid, 1, "2024-01-01 12:00:00 EST", 1704110400, &FormatOptions::default(), Some(&severity), None);
+        assert!(message.starts_with("🔥 Overdue Loan Alert! (critical)\nTimestamp:"));
+
+        let blocks = format_bid_alert_blocks(&bid, 1, "2024-01-01 12:00:00 EST", 1704110400, &FormatOptions::default(), Some(&severity), None);
+        assert_eq!(blocks[0]["text"]["text"], "🔥 Overdue Loan Alert (critical)");
+    }
+
+    #[test]
+    fn test_overdue_bucket_boundaries() {
+        let day = ONE_DAY as i64;
+        assert_eq!(OverdueBucket::from_overdue_secs(0), OverdueBucket::UnderOneDay);
+        assert_eq!(OverdueBucket::from_overdue_secs(day - 1), OverdueBucket::UnderOneDay);
+        assert_eq!(OverdueBucket::from_overdue_secs(day), OverdueBucket::OneToSevenDays);
+        assert_eq!(OverdueBucket::from_overdue_secs(7 * day), OverdueBucket::OneToSevenDays);
+        assert_eq!(OverdueBucket::from_overdue_secs(7 * day + 1), OverdueBucket::OverSevenDays);
+
+        let now = 1704067200 + 3 * day;
+        assert_eq!(OverdueBucket::for_bid(&serde_json::json!({ "nextDueDate": " 1704067200 " }), now), Some(OverdueBucket::OneToSevenDays));
+        assert_eq!(OverdueBucket::for_bid(&serde_json::json!({ "nextDueDate": 1704067200 }), now), Some(OverdueBucket::OneToSevenDays));
+        assert_eq!(OverdueBucket::for_bid(&serde_json::json!({ "nextDueDate": "soon" }), now), None);
+        assert_eq!(OverdueBucket::for_bid(&serde_json::json!({}), now), None);
+
+        let bid = serde_json::json!({ "bidId": "12345", "nextDueDate": "1704067200" });
+        let severity = Severity { name: "critical", emoji: "🔥" };
+        let bucket = OverdueBucket::for_bid(&bid, 1704067200 + 8 * day);
+        let message = format_bid_alert(&bid, 1, "ts", 0, &FormatOptions::default(), Some(&severity), bucket);
+        assert!(message.starts_with("🔥 Overdue Loan Alert! (critical) [🔴 >7d]\nTimestamp:"));
+        let blocks = format_bid_escalation_blocks(&bid, 1, "ts", 0, &FormatOptions::default(), None, bucket);
+        assert_eq!(blocks[0]["text"]["text"], "🔁 Escalation: Loan Still Overdue [🔴 >7d]");
+    }
+
+    #[test]
+    fn test_chain_label() {
+        let mut options = FormatOptions::default();
+        options.chain_names.insert(10, "OP Mainnet".to_string());
+
+        assert_eq!(options.chain_label(137), "Polygon (137)");
+        assert_eq!(options.chain_label(10), "OP Mainnet (10)");
+        // Unknown chains fall back to the bare id
+        assert_eq!(options.chain_label(5), "5");
+    }
+
+    #[test]
+    fn test_format_bid_alert_with_custom_template() {
+        let bid = serde_json::json!({
+            "bidId": "12345",
+            "borrowerAddress": "0xabc123def456",
+            "principal": "1000000",
+            "lendingToken": { "symbol": "USDC", "decimals": 6 },
+            "status": "Accepted"
+        });
+        let options = FormatOptions {
+            alert_template: Some("{header} #{bid_id} on {chain_id}: {principal} {token} from {borrower} ({status}) {unknown}".to_string()),
+            ..FormatOptions::default()
+        };
+        let severity = Severity { name: "critical", emoji: "🔥" };
+
+        let message = format_bid_alert(&bid, 5, "2024-01-01 00:00:00 EST", 0, &options, Some(&severity), None);
+
+        assert_eq!(
+            message,
+            "🔥 Overdue Loan Alert! (critical) #12345 on 5: 1.00 USDC from 0xabc123def456 (Accepted) {unknown}"
+        );
+        assert_eq!(template_placeholders("{a} x {b}{c"), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_format_bid_alert_with_collateral() {
+        let mut bid = serde_json::json!({
+            "bidId": "12345",
+            "principal": "1000000000",
+            "lendingToken": { "id": "0xA0b8", "symbol": "USDC", "decimals": 6 },
+            "collateral": [
+                { "amount": "500000000000000000", "token": { "id": "0xC02a", "symbol": "WETH", "decimals": "18" } }
+            ]
+        });
+        let mut options = FormatOptions::default();
+
+        // Without prices the collateral is listed but no ratio is known
+        let message = format_bid_alert(&bid, 5, "2024-01-01 00:00:00 EST", 0, &options, None, None);
+        assert!(message.contains("\nCollateral: 0.50 WETH"));
+        assert!(!message.contains("Collateral Ratio"));
+
+        options.fiat_prices = HashMap::from([("0xa0b8".to_string(), 1.0), ("0xc02a".to_string(), 3000.0)]);
+        assert_eq!(collateral_ratio(&bid, &options), Some(1.5));
+        let message = format_bid_alert(&bid, 5, "2024-01-01 00:00:00 EST", 0, &options, None, None);
+        assert!(message.contains("\nCollateral Ratio: 150%"));
+
+        bid["collateral"] = serde_json::json!([]);
+        assert_eq!(format_collateral(&bid, &options).as_deref(), Some("none"));
+        assert_eq!(collateral_ratio(&bid, &options), None);
+
+        bid.as_object_mut().unwrap().remove("collateral");
+        assert_eq!(format_collateral(&bid, &options), None);
+        assert!(!format_bid_alert(&bid, 5, "2024-01-01 00:00:00 EST", 0, &options, None, None).contains("Collateral"));
+    }
+
+    #[test]
+    fn test_format_endpoint_slow() {
+        let message = format_endpoint_slow("Mainnet", "https://example.com", "2024-01-01 00:00:00 EST", 4_250, 2_000, 0);
+        assert!(message.contains("Endpoint: Mainnet https://example.com"));
+        assert!(message.contains("Latency: 4250ms (limit 2000ms)"));
+        assert!(!message.contains("Slow for"));
+
+        let fast = format_endpoint_fast("Mainnet", "https://example.com", "2024-01-01 01:00:00 EST", 300, ONE_HOUR as i64);
+        assert!(fast.contains("Latency: 300ms\nSlow for: 1h"));
+    }
+
+    #[test]
+    fn test_format_sync_lag() {
+        let message = format_sync_lag("Mainnet", "https://example.com", "2024-01-01 00:00:00 EST", 19_000_000, 19_000_500, 0);
+
+        assert!(message.starts_with("🐢 Subgraph Lagging!"));
+        assert!(message.ends_with("Indexed Block: 19000000\nChain Head: 19000500\nBlocks Behind: 500"));
+
+        let repeat = format_sync_lag("Mainnet", "https://example.com", "2024-01-01 06:00:00 EST", 19_000_000, 19_001_800, 6 * ONE_HOUR as i64);
+        assert!(repeat.ends_with("Blocks Behind: 1800\nLagging for: 6h 0m"));
+    }
+
+    #[test]
+    fn test_format_panic() {
+        let message = format_panic("2024-01-01 00:00:00 EST", "Failed to open alerted bids file", Some("src/store.rs:42:10"), Some("tokio-runtime-worker"));
+        assert_eq!(
+            message,
+            "💥 Monitor Panicked!\nTimestamp: 2024-01-01 00:00:00 EST\nMessage: Failed to open alerted bids file\n\
+             Location: src/store.rs:42:10\nThread: tokio-runtime-worker"
+        );
+        assert!(format_panic("2024-01-01 00:00:00 EST", "boom", None, None).ends_with("Message: boom"));
+    }
+
+    #[test]
+    fn test_format_results_unchanged() {
+        let message = format_results_unchanged("Mainnet", "https://example.com", "2024-01-01 00:00:00 EST", 12, 3);
+        assert!(message.starts_with("🧊 Subgraph May Be Stalled!"));
+        assert!(message.ends_with("Endpoint: Mainnet https://example.com\nOverdue Bids: 3\nUnchanged for: 12 polls"));
+
+        let changed = format_results_changed("Mainnet", "https://example.com", "2024-01-01 06:00:00 EST", 6 * ONE_HOUR as i64);
+        assert!(changed.ends_with("Unchanged for: 6h 0m"));
+    }
+
+    #[test]
+    fn test_format_value_at_risk_lists_largest_loans() {
+        let bid = |bid_id: &str, principal: &str, token: &str, symbol: &str| {
+            serde_json::json!({
+                "bidId": bid_id,
+                "principal": principal,
+                "lendingToken": { "id": token, "symbol": symbol, "decimals": 6 }
+            })
+        };
+        let bids = [
+            bid("1", "250000000000", "0xa0b8", "USDC"),
+            bid("2", "900000000000", "0xa0b8", "USDC"),
+            bid("3", "5000000", "0xdead", "MYST"),
+        ];
+        let options = FormatOptions {
+            fiat_prices: HashMap::from([("0xa0b8".to_string(), 1.0)]),
+            ..FormatOptions::default()
+        };
+
+        let risk = value_at_risk(&bids, &options);
+        assert_eq!(risk.total, 1_150_000.0);
+        assert_eq!(risk.unpriced, 1);
+        assert_eq!(risk.loans.iter().map(|(_, value)| *value).collect::<Vec<_>>(), [900_000.0, 250_000.0]);
+
+        let message = format_value_at_risk(137, "2024-01-01 12:00:00 EST", 1_000_000.0, &risk, &options);
+        assert_eq!(
+            message,
+            "📊 VaR threshold exceeded\nTimestamp: 2024-01-01 12:00:00 EST\nChain: Polygon (137)\n\
+             Overdue Principal: $1,150,000.00 (threshold $1,000,000.00)\nOverdue Loans: 3\nTop Loans:\n\
+             • Bid 2: 900,000.00 USDC ($900,000.00)\n• Bid 1: 250,000.00 USDC ($250,000.00)\n\
+             1 loan(s) without a price are not counted"
+        );
+    }
+
+    #[test]
+    fn test_truncate_error_cuts_oversized_bodies() {
+        let page = format!("HTTP 502 Bad Gateway: <html>{}</html>", "é".repeat(5000));
+
+        let truncated = truncate_error(&page, 1500);
+        assert_eq!(truncated.chars().count(), 1500);
+        assert!(truncated.starts_with("HTTP 502 Bad Gateway: <html>éé"));
+        assert!(truncated.ends_with("é…(truncated)"));
+
+        assert_eq!(truncate_error("HTTP 503", 1500), "HTTP 503");
+    }
+
+    #[test]
+    fn test_format_heartbeat() {
+        assert_eq!(
+            format_heartbeat("2024-01-01 00:00:00 EST", 3, &[]),
+            "💓 Monitor Alive\nTimestamp: 2024-01-01 00:00:00 EST\nHealthy Endpoints: 3/3"
+        );
+        assert!(format_heartbeat("2024-01-01 00:00:00 EST", 3, &["Base", "Polygon"])
+            .ends_with("Healthy Endpoints: 1/3\nFailing: Base, Polygon"));
+    }
+
+    #[test]
+    fn test_format_bid_due_soon() {
+        let bid = serde_json::json!({
+            "bidId": "12345",
+            "principal": "1000000",
+            "lendingToken": { "symbol": "USDC", "decimals": 6 },
+            "nextDueDate": "1704067200",
+            "status": "Accepted"
+        });
+
+        let message = format_bid_due_soon(&bid, 1, "2023-12-31 12:00:00 EST", 1704067200 - 7 * ONE_HOUR as i64, &FormatOptions::default());
+
+        assert!(message.starts_with("⏰ Loan Due Soon!\nTimestamp: 2023-12-31 12:00:00 EST"));
+        assert!(message.contains("Next Due Date: 2023-12-31 19:00:00 EST (due in 7h 0m)"));
+        assert!(message.contains("Principal Amount: 1.00"));
+    }
+
+    #[test]
+    fn test_format_daily_summary_totals_per_token() {
+        let usdc = |principal: &str| {
+            serde_json::json!({ "principal": principal, "lendingToken": { "symbol": "USDC", "decimals": 6 } })
+        };
+        let weth = serde_json::json!({ "principal": "2500000000000000000", "lendingToken": { "symbol": "WETH", "decimals": "18" } });
+        let chains = vec![
+            ChainSummary { name: "Mainnet".to_string(), chain_id: 1, group: None, bids: Ok(vec![usdc("1000000"), usdc("500000"), weth]) },
+            ChainSummary { name: "Base".to_string(), chain_id: 8453, group: None, bids: Ok(vec![]) },
+            ChainSummary { name: "Arbitrum".to_string(), chain_id: 42161, group: None, bids: Err("timed out".to_string()) },
+        ];
+
+        let message = format_daily_summary("2024-01-01 09:00:00 EST", &chains, &FormatOptions::default());
+
+        assert_eq!(
+            message,
+            "📊 Daily Overdue Loan Summary\nTimestamp: 2024-01-01 09:00:00 EST\nTotal Overdue: 3\n\
+             Mainnet (Chain ID 1): 3 overdue, 1.50 USDC, 2.50 WETH\n\
+             Base (Chain ID 8453): 0 overdue\n\
+             Arbitrum (Chain ID 42161): query failed: timed out"
+        );
+    }
+
+    #[test]
+    fn test_format_daily_summary_totals_per_group() {
+        let usdc = |principal: &str| {
+            serde_json::json!({ "principal": principal, "lendingToken": { "symbol": "USDC", "decimals": 6 } })
+        };
+        let chain = |name: &str, chain_id: i32, group: Option<&str>, bids| ChainSummary {
+            name: name.to_string(),
+            chain_id,
+            group: group.map(str::to_string),
+            bids,
+        };
+        let chains = vec![
+            chain("Mainnet", 1, None, Ok(vec![usdc("1000000")])),
+            chain("Base", 8453, Some("L2s"), Ok(vec![usdc("2000000000")])),
+            chain("Arbitrum", 42161, Some("L2s"), Ok(vec![usdc("500000000")])),
+            chain("Optimism", 10, Some("L2s"), Err("timed out".to_string())),
+            chain("Polygon", 137, Some("Sidechains"), Ok(vec![])),
+        ];
+
+        let message = format_daily_summary("2024-01-01 09:00:00 EST", &chains, &FormatOptions::default());
+
+        assert!(message.ends_with(
+            "Polygon (Chain ID 137): 0 overdue\n\n\
+             By Group:\nL2s: 2 overdue, 2,500.00 USDC (1 query failed)\nSidechains: 0 overdue\nUngrouped: 1 overdue, 1.00 USDC"
+        ));
+    }
+
+    #[test]
+    fn test_parse_token_amount() {
+        assert_eq!(parse_token_amount("10", 6), Some(U256::from(10_000_000u64)));
+        assert_eq!(parse_token_amount("10.5", 6), Some(U256::from(10_500_000u64)));
+        assert_eq!(parse_token_amount(".25", 2), Some(U256::from(25u64)));
+        assert_eq!(parse_token_amount("0.123456789", 6), Some(U256::from(123_456u64)));
+        assert_eq!(parse_token_amount("1", 18), Some(U256::exp10(18)));
+        assert_eq!(parse_token_amount("", 6), None);
+        assert_eq!(parse_token_amount("1e6", 6), None);
+        assert_eq!(parse_token_amount("-1", 6), None);
+    }
+}
@@ -0,0 +1,171 @@
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashSet};
+
+/// How a poll's overdue query went, for the per-endpoint stats.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PollResult {
+    Success,
+    /// The endpoint answered, but with GraphQL errors or a response that couldn't be used
+    GraphQlError,
+    /// The request failed or timed out, or got a non-2xx status
+    TransportError,
+}
+
+impl PollResult {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PollResult::Success => "success",
+            PollResult::GraphQlError => "graphql_error",
+            PollResult::TransportError => "transport_error",
+        }
+    }
+}
+
+/// Running counts of an endpoint's polls since startup.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EndpointStats {
+    pub polls: u64,
+    pub successes: u64,
+    pub graphql_errors: u64,
+    pub transport_errors: u64,
+    /// Failed polls since the last success
+    pub consecutive_failures: u64,
+}
+
+impl EndpointStats {
+    /// Share of polls that succeeded, as a percentage. `None` before the first poll.
+    pub fn uptime_percent(&self) -> Option<f64> {
+        (self.polls > 0).then(|| self.successes as f64 * 100.0 / self.polls as f64)
+    }
+}
+
+/// How recent the monitor's polls are, reported on `/healthz`.
+#[derive(Debug)]
+pub struct HealthState {
+    started_at: i64,
+    last_run: Option<i64>,
+    last_success: Option<i64>,
+    endpoints_polled: HashSet<String>,
+    last_error: Option<String>,
+    endpoint_stats: BTreeMap<String, EndpointStats>,
+}
+
+impl HealthState {
+    pub fn new(started_at: i64) -> Self {
+        Self {
+            started_at,
+            last_run: None,
+            last_success: None,
+            endpoints_polled: HashSet::new(),
+            last_error: None,
+            endpoint_stats: BTreeMap::new(),
+        }
+    }
+
+    /// Counts a poll of `endpoint`, returning its updated stats. A success resets the
+    /// consecutive failures.
+    pub fn record_poll(&mut self, endpoint: &str, result: PollResult) -> &EndpointStats {
+        let stats = self.endpoint_stats.entry(endpoint.to_string()).or_default();
+        stats.polls += 1;
+        match result {
+            PollResult::Success => {
+                stats.successes += 1;
+                stats.consecutive_failures = 0;
+            }
+            PollResult::GraphQlError => {
+                stats.graphql_errors += 1;
+                stats.consecutive_failures += 1;
+            }
+            PollResult::TransportError => {
+                stats.transport_errors += 1;
+                stats.consecutive_failures += 1;
+            }
+        }
+        stats
+    }
+
+    /// Each polled endpoint's stats, by name.
+    pub fn endpoint_stats(&self) -> &BTreeMap<String, EndpointStats> {
+        &self.endpoint_stats
+    }
+
+    /// Notes a poll of `endpoint` whose overdue query was answered.
+    pub fn record_success(&mut self, endpoint: &str, now: i64) {
+        self.last_run = Some(now);
+        self.last_success = Some(now);
+        self.endpoints_polled.insert(endpoint.to_string());
+    }
+
+    /// Notes an error from a poll of `endpoint`, which may or may not also have succeeded.
+    pub fn record_error(&mut self, endpoint: &str, now: i64, error: &str) {
+        self.last_run = Some(now);
+        self.endpoints_polled.insert(endpoint.to_string());
+        self.last_error = Some(format!("{}: {}", endpoint, error));
+    }
+
+    /// Whether no poll has succeeded for more than `stale_after_secs`. Counts from startup
+    /// until the first success so a fresh process isn't reported unhealthy.
+    pub fn is_stale(&self, now: i64, stale_after_secs: u64) -> bool {
+        now - self.last_success.unwrap_or(self.started_at) > stale_after_secs as i64
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "last_run_unix": self.last_run,
+            "last_success_unix": self.last_success,
+            "endpoints_polled": self.endpoints_polled.len(),
+            "last_error": self.last_error,
+            "endpoints": self.endpoint_stats,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_goes_stale_without_successful_polls() {
+        let mut health = HealthState::new(1_000);
+        assert!(!health.is_stale(1_500, 600));
+        assert!(health.is_stale(1_601, 600));
+
+        health.record_success("mainnet", 1_700);
+        assert!(!health.is_stale(2_000, 600));
+
+        // Failed polls count as runs but not successes
+        health.record_error("polygon", 2_400, "timed out");
+        assert!(health.is_stale(2_301, 600));
+        assert_eq!(
+            health.to_json(),
+            json!({
+                "last_run_unix": 2_400,
+                "last_success_unix": 1_700,
+                "endpoints_polled": 2,
+                "last_error": "polygon: timed out",
+                "endpoints": {},
+            })
+        );
+    }
+
+    #[test]
+    fn test_record_poll_counts_and_resets_consecutive_failures() {
+        let mut health = HealthState::new(1_000);
+        health.record_poll("polygon", PollResult::TransportError);
+        health.record_poll("polygon", PollResult::GraphQlError);
+        assert_eq!(health.endpoint_stats()["polygon"].consecutive_failures, 2);
+
+        let stats = health.record_poll("polygon", PollResult::Success).clone();
+        assert_eq!(
+            stats,
+            EndpointStats { polls: 3, successes: 1, graphql_errors: 1, transport_errors: 1, consecutive_failures: 0 }
+        );
+        assert_eq!(stats.uptime_percent().map(|percent| percent.round()), Some(33.0));
+        assert_eq!(EndpointStats::default().uptime_percent(), None);
+        assert_eq!(
+            health.to_json()["endpoints"]["polygon"],
+            json!({ "polls": 3, "successes": 1, "graphql_errors": 1, "transport_errors": 1, "consecutive_failures": 0 })
+        );
+    }
+}
@@ -1,501 +1,5886 @@
-use crate::slack::SlackBot;
-use std::time::Duration;
+use crate::audit::{AuditEvent, AuditKind, AuditLog};
+use crate::discord::DiscordWebhook;
+use crate::export::{ExportFormat, ExportRow};
+use crate::format::{
+    ALERT_TEMPLATE_FIELDS, ChainSummary, DEFAULT_TIMEZONE, FormatOptions, LinkStyle, OverdueBucket, SNOOZE_ACTION_ID, Severity, bid_next_due, bid_token_decimals, checksum_address,
+    collateral_ratio,
+    default_chain_names, default_explorers,
+    format_alert_batch, format_alert_cap, format_all_clear, format_bid_alert, format_bid_alert_blocks, format_bid_due_soon, format_bid_escalation,
+    format_bid_escalation_blocks, format_bid_recovered, format_daily_summary, format_endpoint_escalated, format_endpoint_escalation_resolved, format_endpoint_failed,
+    format_endpoint_fast, format_endpoint_recovered, format_endpoint_slow, format_heartbeat, format_liquidation_opportunity, format_panic, format_rpc_failed, format_rpc_recovered, format_snoozed_blocks, format_value_at_risk, format_results_changed, format_results_unchanged, format_sync_caught_up, format_sync_lag,
+    format_timestamp, parse_token_amount, template_placeholders, truncate_error, value_at_risk,
+};
+use crate::ens::{DEFAULT_ENS_CACHE_TTL_SECS, ENS_CHAIN_ID, EnsCache};
+use crate::health::{HealthState, PollResult};
+use crate::matrix::MatrixNotifier;
+use crate::metrics::metrics;
+use crate::notifier::{AlertSubject, DryRunNotifier, MultiNotifier, Notifier, SeverityFilter, failed_backends};
+use crate::pagerduty::{EVENTS_URL as PAGERDUTY_EVENTS_URL, PagerDuty};
+use crate::price::{PriceCache, PriceFeedConfig};
+use crate::queue::{QueuedAlert, QueuedBid, RetryQueue};
+use crate::slack::{SlackBot, SlashCommand};
+use crate::teams::TeamsNotifier;
+use crate::telegram::TelegramNotifier;
+use crate::store::{AlertRecord, AlertStore, EndpointFailure, make_bid_key, make_due_soon_key, make_liquidation_key};
+use crate::tokens::TokenCache;
+use crate::webhook::WebhookNotifier;
+use std::time::{Duration, Instant};
 use std::env;
-use tokio::time;
-use chrono::{DateTime, Utc};
-use chrono_tz::US::Eastern;
+use tokio::{signal, task, time};
+use tokio::task::JoinSet;
+use chrono::{DateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
 use serde::Deserialize;
+use serde::de::DeserializeOwned;
 use std::fs;
-use std::sync::{Arc, Mutex};
-use std::collections::HashSet;
-use std::io::{BufRead, BufReader, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::{HashMap, HashSet};
+use std::borrow::Cow;
+use std::hash::{Hash, Hasher};
+use tokio::sync::{Semaphore, watch};
+use futures::future::join_all;
+use clap::Parser;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::path::{Path, PathBuf};
+use ethers::types::U256;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::EnvFilter;
 
+pub mod audit;
+pub mod discord;
+pub mod ens;
+pub mod export;
+pub mod format;
+pub mod health;
+pub mod matrix;
+pub mod metrics;
+pub mod notifier;
+pub mod pagerduty;
+pub mod price;
+pub mod queue;
 pub mod slack;
+pub mod store;
+pub mod teams;
+pub mod telegram;
+pub mod tokens;
+pub mod webhook;
+
+#[derive(Debug, Parser)]
+#[command(about = "Monitors TellerV2 subgraphs and alerts on overdue loans")]
+struct Args {
+    /// Path to the endpoints config file
+    #[arg(long, default_value = "src/endpoints.ron")]
+    config: PathBuf,
+
+    /// Poll interval for endpoints that don't set their own `poll_interval_secs`
+    #[arg(long, default_value_t = ONE_HOUR)]
+    interval_secs: u64,
+
+    /// File that records which bids have been alerted on
+    #[arg(long, env = "ALERTED_BIDS_FILE", default_value = ALERTED_BIDS_FILE)]
+    alerted_bids_file: PathBuf,
+
+    /// Alerts that couldn't be sent are kept here and retried on the endpoint's next poll
+    #[arg(long, env = "RETRY_QUEUE_FILE", default_value = RETRY_QUEUE_FILE)]
+    retry_queue_file: PathBuf,
+
+    /// Append a JSON line per alert and endpoint failure or recovery to this file
+    #[arg(long, env = "AUDIT_LOG_FILE")]
+    audit_log_file: Option<PathBuf>,
+
+    /// Log alerts instead of sending them to Slack
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Serve Prometheus metrics on `/metrics` and a health check on `/healthz` at this port
+    #[arg(long, env = "METRICS_PORT")]
+    metrics_port: Option<u16>,
+
+    /// Signing secret of the Slack app, enables the `/overdue` slash command on the metrics port
+    #[arg(long, env = "SLACK_SIGNING_SECRET", hide_env_values = true)]
+    slack_signing_secret: Option<String>,
+
+    /// Poll every endpoint once and exit, non-zero if any endpoint failed
+    #[arg(long, env = "RUN_ONCE", value_parser = clap::builder::BoolishValueParser::new())]
+    once: bool,
+
+    /// Exit at startup if any endpoint URL fails the preflight query
+    #[arg(long, env = "STRICT_PREFLIGHT", value_parser = clap::builder::BoolishValueParser::new())]
+    strict_preflight: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Write the current overdue bids of every enabled endpoint to a file, without alerting
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// File to write
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Print the bids recorded in the alerted bids file
+    ListAlerts,
+    /// Remove bids from the alerted bids file so they are alerted on again
+    ClearAlerts {
+        /// Only clear bids on this chain
+        #[arg(long)]
+        chain: Option<i32>,
+        /// Only clear this bid, needs `--chain`
+        #[arg(long, requires = "chain")]
+        bid: Option<String>,
+        /// Confirm clearing every chain when neither `--chain` nor `--bid` is given
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Check the config file and exit, without polling or sending anything
+    Validate {
+        /// Fail on unset `auth_key` variables and notifier credentials instead of warning,
+        /// for checking a deploy that has its secrets set
+        #[arg(long)]
+        strict: bool,
+    },
+}
 
 #[derive(Debug, Deserialize)]
 struct EndpointConfig {
     endpoints: Vec<Endpoint>,
+    /// Directory endpoints' `query_template` files are read from, defaults to `DEFAULT_QUERIES_DIR`
+    #[serde(default)]
+    queries_dir: Option<PathBuf>,
+    /// Re-alert on bids that are still overdue once this many seconds have passed since the last alert
+    #[serde(default)]
+    realert_after_secs: Option<u64>,
+    /// Send each chain's new alerts from a poll as one combined message instead of one per bid
+    #[serde(default)]
+    batch_alerts: bool,
+    /// Most bid alerts an endpoint sends per poll, the rest are held back and summed up in one
+    /// message. Off when unset
+    #[serde(default)]
+    max_alerts_per_cycle: Option<usize>,
+    /// Post a short all clear when a poll finds no overdue loans, at most once per poll interval per chain
+    #[serde(default)]
+    post_all_clear: bool,
+    /// Slack channel for all clear messages, defaults to the endpoint's channel
+    #[serde(default)]
+    all_clear_channel: Option<String>,
+    /// Send bid alerts to Slack as Block Kit layouts, with the plain text as fallback
+    #[serde(default)]
+    slack_blocks: bool,
+    /// Severity levels by how long a bid has been overdue, each with its own emoji and optional channel
+    #[serde(default)]
+    severity_thresholds: Vec<SeverityThreshold>,
+    /// Backends every alert is sent to, instead of the first one with credentials set
+    #[serde(default)]
+    notifiers: Vec<NotifierRoute>,
+    /// Extra Slack workspaces by name, each with its own token, for endpoints and severities to post to
+    #[serde(default)]
+    slack_workspaces: HashMap<String, SlackWorkspace>,
+    /// Minimum gap between repeat alerts for an endpoint that keeps failing, defaults to `DEFAULT_FAILURE_REALERT_SECS`
+    #[serde(default)]
+    failure_realert_secs: Option<u64>,
+    /// Escalate an endpoint outage to `critical_channel` (and PagerDuty) after this many failed polls in a row
+    #[serde(default)]
+    escalate_after: Option<u32>,
+    /// Slack channel for escalated outages, defaults to the endpoint's channel
+    #[serde(default)]
+    critical_channel: Option<String>,
+    /// Longest error shown in an endpoint failure alert, defaults to `DEFAULT_MAX_ERROR_CHARS`
+    #[serde(default)]
+    max_error_chars: Option<usize>,
+    /// Most undelivered alerts kept for retry, defaults to `DEFAULT_MAX_QUEUED_ALERTS`
+    #[serde(default)]
+    max_queued_alerts: Option<usize>,
+    /// Upper bound on endpoints polled at the same time, defaults to `DEFAULT_MAX_CONCURRENT_POLLS`
+    #[serde(default)]
+    max_concurrent_polls: Option<usize>,
+    /// Spread the first poll of each endpoint across its interval instead of starting them all at once
+    #[serde(default)]
+    stagger_polls: bool,
+    /// Shifts each scheduled poll by a random amount of up to this many seconds either way, defaults to 0
+    #[serde(default)]
+    jitter_secs: Option<u64>,
+    /// Per-request timeout for GraphQL queries and notifier calls, defaults to `HTTP_REQUEST_TIMEOUT_SECS`
+    #[serde(default)]
+    request_timeout_secs: Option<u64>,
+    /// How many times a timed out or 5xx query is retried, defaults to `DEFAULT_MAX_RETRIES`
+    #[serde(default)]
+    max_retries: Option<u32>,
+    /// Backoff before the first retry, doubled on each further attempt
+    #[serde(default)]
+    base_backoff_ms: Option<u64>,
+    /// Bids requested per page of the due queries, defaults to `DEFAULT_PAGE_SIZE` and capped at `MAX_PAGE_SIZE`
+    #[serde(default)]
+    page_size: Option<usize>,
+    /// Most pages of `page_size` bids fetched per poll, defaults to `DEFAULT_MAX_PAGES`
+    #[serde(default)]
+    max_pages: Option<usize>,
+    /// Bid field the due queries are sorted by, defaults to `DEFAULT_ORDER_BY`
+    #[serde(default)]
+    order_by: Option<String>,
+    #[serde(default)]
+    order_direction: OrderDirection,
+    /// How far back to look for overdue bids, defaults to `DEFAULT_LOOKBACK_SECS`
+    #[serde(default)]
+    lookback_secs: Option<u64>,
+    /// How long past its due date a bid has to be before it is alerted on, defaults to 0
+    #[serde(default)]
+    grace_secs: Option<u64>,
+    /// Send a "due soon" pre-warning for bids coming due within this many seconds
+    #[serde(default)]
+    warn_before_secs: Option<u64>,
+    /// Post a digest of all overdue loans once a day
+    #[serde(default)]
+    daily_summary: Option<DailySummary>,
+    /// Daily periods in the alert timezone during which failure alerts are dropped
+    #[serde(default)]
+    maintenance_windows: Vec<MaintenanceWindow>,
+    /// Post a periodic "monitor alive" message, so silence itself is a signal
+    #[serde(default)]
+    heartbeat: Option<Heartbeat>,
+    /// Post panic details to `SLACK_CHANNEL` before the process dies, defaults to on
+    #[serde(default)]
+    panic_alerts: Option<bool>,
+    /// Alert when an endpoint's subgraph is more than this many blocks behind the chain head
+    #[serde(default)]
+    max_block_lag: Option<u64>,
+    /// Warn when an endpoint's overdue query takes longer than this many milliseconds
+    #[serde(default)]
+    latency_warn_ms: Option<u64>,
+    /// Warn when an endpoint returns the same overdue bids for more than this many polls in a row
+    #[serde(default)]
+    stale_cycles: Option<u32>,
+    /// Length of a snooze from the button on Block Kit alerts, defaults to `DEFAULT_SNOOZE_SECS`
+    #[serde(default)]
+    snooze_secs: Option<u64>,
+    /// JSON-RPC URLs per chain ID, tried in order to read the chain head for `max_block_lag`
+    #[serde(default)]
+    rpc_urls: HashMap<i32, Vec<String>>,
+    /// Smallest principal worth alerting on, in whole tokens, keyed by token address or symbol
+    #[serde(default)]
+    min_principal: HashMap<String, String>,
+    /// Flag alerts as under-collateralized when collateral value over principal value is below this, e.g. `1.2`
+    #[serde(default)]
+    min_collateral_ratio: Option<f64>,
+    /// Alert when a chain's summed overdue principal, valued with `price_feed`, is over this, keyed by chain ID
+    #[serde(default)]
+    value_at_risk_thresholds: HashMap<i32, f64>,
+    /// Minimum gap between repeat value at risk alerts for a chain, defaults to `DEFAULT_VALUE_AT_RISK_REALERT_SECS`
+    #[serde(default)]
+    value_at_risk_realert_secs: Option<u64>,
+    /// Borrower addresses whose bids are never alerted on
+    #[serde(default)]
+    ignore_borrowers: Vec<String>,
+    /// Borrower addresses always alerted on at critical severity, regardless of `min_principal`
+    #[serde(default)]
+    priority_borrowers: Vec<String>,
+    /// Layout for overdue alerts with `{field}` placeholders, defaults to the built-in layout
+    #[serde(default)]
+    alert_template: Option<String>,
+    /// Decimal places shown for principal amounts, defaults to `DEFAULT_DISPLAY_DECIMALS`
+    #[serde(default)]
+    display_decimals: Option<u32>,
+    /// Decimal places shown for amounts of specific tokens by symbol, e.g. more for WBTC
+    #[serde(default)]
+    token_display_decimals: HashMap<String, u32>,
+    /// Block explorer base URLs by chain id, merged over the built-in defaults
+    #[serde(default)]
+    explorers: HashMap<i32, String>,
+    /// Chain names shown in alerts by chain id, merged over the built-in defaults
+    #[serde(default)]
+    chain_names: HashMap<i32, String>,
+    /// Seconds without a successful poll before `/healthz` reports unhealthy, defaults to
+    /// twice the longest poll interval
+    #[serde(default)]
+    health_stale_secs: Option<u64>,
+    /// How often each endpoint's poll stats are logged, defaults to `DEFAULT_STATS_LOG_SECS`
+    #[serde(default)]
+    stats_log_secs: Option<u64>,
+    /// Where to look up token prices for showing principal in fiat, off when unset
+    #[serde(default)]
+    price_feed: Option<PriceFeedConfig>,
+    /// Show mainnet borrowers by their ENS name, looked up through `rpc_urls` for chain 1
+    #[serde(default)]
+    resolve_ens: bool,
+    /// How long ENS lookups are cached, defaults to `DEFAULT_ENS_CACHE_TTL_SECS`
+    #[serde(default)]
+    ens_cache_ttl_secs: Option<u64>,
+}
+
+impl EndpointConfig {
+    fn format_options(&self, link_style: LinkStyle, timezone: Tz) -> FormatOptions {
+        let mut explorers = default_explorers();
+        explorers.extend(self.explorers.clone());
+
+        let mut chain_names = default_chain_names();
+        chain_names.extend(self.chain_names.clone());
+
+        let mut options = FormatOptions {
+            explorers,
+            chain_names,
+            link_style,
+            timezone,
+            alert_template: self.alert_template.clone(),
+            ..FormatOptions::default()
+        };
+        if let Some(display_decimals) = self.display_decimals {
+            options.display_decimals = display_decimals;
+        }
+        options.token_display_decimals = self
+            .token_display_decimals
+            .iter()
+            .map(|(symbol, decimals)| (symbol.to_uppercase(), *decimals))
+            .collect();
+        options
+    }
+
+    fn bid_order(&self) -> BidOrder<'_> {
+        BidOrder {
+            by: self.order_by.as_deref().unwrap_or(DEFAULT_ORDER_BY),
+            direction: self.order_direction,
+        }
+    }
+
+    fn paging(&self) -> Paging {
+        Paging {
+            page_size: self.page_size.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE),
+            max_pages: self.max_pages.unwrap_or(DEFAULT_MAX_PAGES),
+        }
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+            base_backoff_ms: self.base_backoff_ms.unwrap_or(DEFAULT_BASE_BACKOFF_MS),
+        }
+    }
+}
+
+/// How the due queries page through bids.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Paging {
+    page_size: usize,
+    max_pages: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+enum OrderDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl OrderDirection {
+    fn as_str(self) -> &'static str {
+        match self {
+            OrderDirection::Asc => "asc",
+            OrderDirection::Desc => "desc",
+        }
+    }
+}
+
+/// Sort order of the due queries, so paging walks the bids in a deterministic order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BidOrder<'a> {
+    by: &'a str,
+    direction: OrderDirection,
+}
+
+impl Default for BidOrder<'_> {
+    fn default() -> Self {
+        Self { by: DEFAULT_ORDER_BY, direction: OrderDirection::default() }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    max_retries: u32,
+    base_backoff_ms: u64,
+}
+
+impl RetryPolicy {
+    /// Exponential backoff for the given retry (0-based), with up to half of it randomised
+    /// so endpoints retrying together don't stay in lockstep.
+    fn backoff(&self, attempt: u32, jitter: f64) -> Duration {
+        let exponential_ms = self.base_backoff_ms.saturating_mul(1 << attempt.min(16));
+        let jittered_ms = exponential_ms / 2 + (exponential_ms as f64 / 2.0 * jitter.clamp(0.0, 1.0)) as u64;
+        Duration::from_millis(jittered_ms)
+    }
+}
+
+/// When and where the daily overdue summary is posted.
+#[derive(Debug, Deserialize)]
+struct DailySummary {
+    /// Time of day as `HH:MM` in the alert timezone
+    time: String,
+    /// Slack channel for the summary, defaults to `SLACK_CHANNEL`
+    #[serde(default)]
+    channel: Option<String>,
+}
+
+impl DailySummary {
+    fn send_at(&self) -> Result<NaiveTime, String> {
+        NaiveTime::parse_from_str(&self.time, "%H:%M")
+            .map_err(|e| format!("Invalid daily_summary time '{}', expected HH:MM: {}", self.time, e))
+    }
+}
+
+/// A daily period, e.g. planned subgraph maintenance, during which failure alerts are
+/// dropped and optionally overdue loan alerts held back.
+#[derive(Debug, Deserialize)]
+struct MaintenanceWindow {
+    /// Start time of day as `HH:MM` in the alert timezone
+    start: String,
+    /// End time of day as `HH:MM` in the alert timezone, before `start` for windows spanning midnight
+    end: String,
+    /// Also hold back overdue loan and due soon alerts until the window ends
+    #[serde(default)]
+    suppress_loan_alerts: bool,
+}
+
+impl MaintenanceWindow {
+    fn times(&self) -> Result<(NaiveTime, NaiveTime), String> {
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time, "%H:%M")
+                .map_err(|e| format!("Invalid maintenance window time '{}', expected HH:MM: {}", time, e))
+        };
+        Ok((parse(&self.start)?, parse(&self.end)?))
+    }
+
+    fn contains(&self, time: NaiveTime) -> bool {
+        match self.times() {
+            Ok((start, end)) if start <= end => start <= time && time < end,
+            Ok((start, end)) => time >= start || time < end,
+            Err(_) => false,
+        }
+    }
+}
+
+/// The maintenance windows covering `now` in `timezone`.
+fn active_maintenance_windows(
+    windows: &[MaintenanceWindow],
+    now: DateTime<Utc>,
+    timezone: Tz,
+) -> impl Iterator<Item = &MaintenanceWindow> {
+    let local_time = now.with_timezone(&timezone).time();
+    windows.iter().filter(move |window| window.contains(local_time))
+}
+
+/// How often and where the heartbeat is posted.
+#[derive(Debug, Deserialize)]
+struct Heartbeat {
+    interval_secs: u64,
+    /// Slack channel for the heartbeat, defaults to `SLACK_CHANNEL`
+    #[serde(default)]
+    channel: Option<String>,
+    /// URL fetched on every heartbeat, e.g. a Healthchecks.io or Cronitor ping URL
+    #[serde(default)]
+    ping_url: Option<String>,
+}
+
+/// A Slack workspace alerts can be sent to besides the one `SLACK_OAUTH_TOKEN` is for.
+#[derive(Debug, Deserialize)]
+struct SlackWorkspace {
+    /// Environment variable holding the workspace's bot token
+    token_env: String,
+    /// Channel for alerts without one of their own, defaults to `#webserver-alerts`
+    #[serde(default)]
+    channel: Option<String>,
+}
+
+/// An alert backend, configured through its environment variables.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+enum Backend {
+    Slack,
+    Discord,
+    Telegram,
+    Teams,
+    Matrix,
+    Webhook,
+}
+
+impl Backend {
+    /// Order backends are tried in when `notifiers` isn't configured.
+    const ALL: [Backend; 6] = [Backend::Slack, Backend::Discord, Backend::Telegram, Backend::Teams, Backend::Matrix, Backend::Webhook];
+
+    fn credentials(self) -> &'static str {
+        match self {
+            Backend::Slack => "SLACK_OAUTH_TOKEN",
+            Backend::Discord => "DISCORD_WEBHOOK_URL",
+            Backend::Telegram => "TELEGRAM_BOT_TOKEN and TELEGRAM_CHAT_ID",
+            Backend::Teams => "TEAMS_WEBHOOK_URL",
+            Backend::Matrix => "MATRIX_HOMESERVER_URL, MATRIX_ACCESS_TOKEN and MATRIX_ROOM_ID",
+            Backend::Webhook => "WEBHOOK_URL",
+        }
+    }
+}
+
+/// One entry of `notifiers`.
+#[derive(Debug, Deserialize)]
+struct NotifierRoute {
+    backend: Backend,
+    /// Only send bid alerts at these severities to the backend, everything when empty
+    #[serde(default)]
+    severities: Vec<String>,
+}
+
+/// A severity level that applies once a bid has been overdue for `min_overdue_secs`.
+#[derive(Debug, Deserialize)]
+struct SeverityThreshold {
+    name: String,
+    min_overdue_secs: u64,
+    emoji: String,
+    /// Slack channel for alerts at this severity, defaults to the endpoint's channel
+    #[serde(default)]
+    channel: Option<String>,
+    /// `slack_workspaces` entry for alerts at this severity, defaults to the endpoint's workspace
+    #[serde(default)]
+    slack_workspace: Option<String>,
+}
+
+impl SeverityThreshold {
+    fn severity(&self) -> Severity<'_> {
+        Severity { name: &self.name, emoji: &self.emoji }
+    }
+}
+
+/// Severity of bids whose collateral ratio is under `min_collateral_ratio`.
+const UNDER_COLLATERALIZED_SEVERITY: Severity<'static> = Severity { name: "under-collateralized", emoji: "⚠️" };
+
+/// Severity used for `priority_borrowers` when no thresholds are configured.
+const PRIORITY_SEVERITY: Severity<'static> = Severity { name: "critical", emoji: "🔥" };
+
+/// Whether bid alerts can come with the severity `name`.
+fn is_known_severity(config: &EndpointConfig, name: &str) -> bool {
+    name == UNDER_COLLATERALIZED_SEVERITY.name
+        || name == PRIORITY_SEVERITY.name
+        || config.severity_thresholds.iter().any(|threshold| threshold.name == name)
+}
+
+/// Picks the highest threshold the bid has passed, if any.
+fn severity_for(thresholds: &[SeverityThreshold], overdue_secs: i64) -> Option<&SeverityThreshold> {
+    thresholds
+        .iter()
+        .filter(|threshold| overdue_secs >= threshold.min_overdue_secs as i64)
+        .max_by_key(|threshold| threshold.min_overdue_secs)
+}
+
+/// The severity threshold an overdue bid has passed, with priority borrowers always at the highest.
+fn bid_threshold<'a>(config: &'a EndpointConfig, bid: &serde_json::Value, now: i64) -> Option<&'a SeverityThreshold> {
+    if is_listed_borrower(bid, &config.priority_borrowers) {
+        config.severity_thresholds.iter().max_by_key(|threshold| threshold.min_overdue_secs)
+    } else {
+        bid_next_due(bid).and_then(|due| severity_for(&config.severity_thresholds, now - due))
+    }
+}
+
+/// Severity an overdue bid is alerted at: under-collateralized first, then its `threshold`,
+/// with priority borrowers critical when no thresholds are configured.
+fn bid_severity<'a>(
+    config: &EndpointConfig,
+    bid: &serde_json::Value,
+    threshold: Option<&'a SeverityThreshold>,
+    options: &FormatOptions,
+) -> Option<Severity<'a>> {
+    let priority = is_listed_borrower(bid, &config.priority_borrowers);
+    let under_collateralized = !priority
+        && config
+            .min_collateral_ratio
+            .is_some_and(|min_ratio| collateral_ratio(bid, options).is_some_and(|ratio| ratio < min_ratio));
+
+    if under_collateralized {
+        Some(UNDER_COLLATERALIZED_SEVERITY)
+    } else {
+        threshold.map(|threshold| threshold.severity()).or(priority.then_some(PRIORITY_SEVERITY))
+    }
+}
+
+/// Whether a bid passes `ignore_borrowers` and `min_principal`, the latter waived for priority borrowers.
+fn is_alertable(config: &EndpointConfig, bid: &serde_json::Value) -> bool {
+    !is_listed_borrower(bid, &config.ignore_borrowers)
+        && (is_listed_borrower(bid, &config.priority_borrowers) || !below_min_principal(bid, &config.min_principal))
+}
+
+/// State shared by every endpoint task.
+struct MonitorContext {
+    client: reqwest::Client,
+    config: EndpointConfig,
+    store: Mutex<AlertStore>,
+    /// Bid alerts the notifier failed to deliver, resent before each poll of their endpoint
+    retry_queue: Mutex<RetryQueue>,
+    audit: AuditLog,
+    poll_permits: Semaphore,
+    notifier: Box<dyn Notifier>,
+    default_interval_secs: u64,
+    timezone: Tz,
+    /// Token prices for `price_feed`, if configured
+    prices: Option<PriceCache>,
+    /// Borrower ENS names for `resolve_ens`, if enabled
+    ens: Option<EnsCache>,
+    /// Paged on escalated outages when `PAGERDUTY_ROUTING_KEY` is set
+    pagerduty: Option<PagerDuty>,
+    /// When each endpoint's ongoing outage was escalated, for `escalate_after`
+    escalated: Mutex<HashMap<String, i64>>,
+    /// Lending token metadata seen so far, across all endpoints
+    tokens: Mutex<TokenCache>,
+    /// Last poll results, served on `/healthz`
+    health: Mutex<HealthState>,
+    /// Fingerprint of each endpoint's last overdue bids, for `stale_cycles`
+    unchanged_results: Mutex<HashMap<String, UnchangedResults>>,
+    /// When each chain's last all clear went out, for `post_all_clear`
+    all_clear_sent: Mutex<HashMap<i32, i64>>,
+    /// The same backends as `notifier`, with Slack posting to each of `slack_workspaces`
+    workspace_notifiers: HashMap<String, Box<dyn Notifier>>,
+    /// Verifies requests to the Slack slash command endpoint, which is only served when set
+    slack_signing_secret: Option<String>,
+    /// Flipped to `true` once a shutdown signal arrives.
+    shutdown: watch::Sender<bool>,
+}
+
+impl MonitorContext {
+    fn format_options(&self) -> FormatOptions {
+        let mut options = self.config.format_options(self.notifier.link_style(), self.timezone);
+        // The button only works while the interactivity endpoint is served
+        if self.slack_signing_secret.is_some() {
+            options.snooze_secs = Some(self.config.snooze_secs.unwrap_or(DEFAULT_SNOOZE_SECS));
+        }
+        options
+    }
+
+    /// The notifier for the `slack_workspaces` entry named `workspace`, or the default one.
+    fn notifier_for(&self, workspace: Option<&str>) -> &dyn Notifier {
+        match workspace.and_then(|name| self.workspace_notifiers.get(name)) {
+            Some(notifier) => notifier.as_ref(),
+            None => self.notifier.as_ref(),
+        }
+    }
+
+    fn endpoint_notifier(&self, endpoint: &Endpoint) -> &dyn Notifier {
+        self.notifier_for(endpoint.slack_workspace.as_deref())
+    }
+
+    fn store(&self) -> MutexGuard<'_, AlertStore> {
+        lock_or_recover(&self.store, "alert store")
+    }
+
+    fn retry_queue(&self) -> MutexGuard<'_, RetryQueue> {
+        lock_or_recover(&self.retry_queue, "retry queue")
+    }
+
+    /// Records the endpoint's latest overdue bids and returns how many polls in a row before
+    /// this one returned the same set. An empty set never counts, since having nothing overdue
+    /// for a long time is the normal state.
+    fn unchanged_polls(&self, endpoint_name: &str, bids: &[serde_json::Value]) -> u32 {
+        let mut unchanged = lock_or_recover(&self.unchanged_results, "unchanged results");
+        let Some(fingerprint) = bid_set_fingerprint(bids) else {
+            unchanged.remove(endpoint_name);
+            return 0;
+        };
+        match unchanged.get_mut(endpoint_name) {
+            Some(last) if last.fingerprint == fingerprint => {
+                last.polls += 1;
+                last.polls
+            }
+            _ => {
+                unchanged.insert(endpoint_name.to_string(), UnchangedResults { fingerprint, polls: 0 });
+                0
+            }
+        }
+    }
+
+    /// Keeps an undelivered bid alert for the endpoint's next poll, leaving its bids out of the store until then.
+    /// When `error` shows only some `notifiers` backends failed, only those get it again.
+    fn queue_alert(
+        &self,
+        endpoint: &Endpoint,
+        workspace: Option<&str>,
+        channel: Option<&str>,
+        message: String,
+        bids: Vec<QueuedBid>,
+        error: &anyhow::Error,
+    ) {
+        warn!(bids = bids.len(), "Alert not delivered, queued for retry");
+        self.retry_queue().push(QueuedAlert {
+            endpoint: endpoint.name.clone(),
+            chain_id: endpoint.chain_id,
+            workspace: workspace.map(str::to_string),
+            channel: channel.map(str::to_string),
+            message,
+            bids,
+            backends: failed_backends(error),
+            queued_at: Utc::now().timestamp(),
+        });
+    }
+
+    fn health(&self) -> MutexGuard<'_, HealthState> {
+        lock_or_recover(&self.health, "health state")
+    }
+
+    fn health_stale_secs(&self) -> u64 {
+        self.config.health_stale_secs.unwrap_or_else(|| {
+            let longest_interval = self
+                .config
+                .endpoints
+                .iter()
+                .filter(|endpoint| endpoint.is_enabled())
+                .map(|endpoint| endpoint.interval_secs(self.default_interval_secs))
+                .max()
+                .unwrap_or(self.default_interval_secs);
+            longest_interval.saturating_mul(2)
+        })
+    }
+
+    /// Whether a maintenance window is in effect that holds back failure alerts, or with
+    /// `loan_alerts` overdue loan alerts.
+    fn in_maintenance(&self, loan_alerts: bool) -> bool {
+        active_maintenance_windows(&self.config.maintenance_windows, Utc::now(), self.timezone)
+            .any(|window| !loan_alerts || window.suppress_loan_alerts)
+    }
+
+    /// Records a failure of `key` (an endpoint or one of its checks), returning it when an
+    /// alert is due. During a maintenance window the failure is dropped with a log line
+    /// instead, so one that outlasts the window is alerted on as soon as it ends.
+    fn record_failure(&self, key: &str, now: i64) -> Option<EndpointFailure> {
+        if self.in_maintenance(false) {
+            info!(key, "In a maintenance window, failure alert dropped");
+            return None;
+        }
+        let realert_after_secs = self.config.failure_realert_secs.unwrap_or(DEFAULT_FAILURE_REALERT_SECS);
+        self.store().record_endpoint_failure(key, now, realert_after_secs)
+    }
+
+    /// Counts a poll in the endpoint's stats, returning its failed polls in a row.
+    fn record_poll(&self, endpoint_name: &str, result: PollResult) -> u64 {
+        let consecutive_failures = self.health().record_poll(endpoint_name, result).consecutive_failures;
+        metrics().endpoint_polls.with_label_values(&[endpoint_name, result.as_str()]).inc();
+        metrics()
+            .endpoint_consecutive_failures
+            .with_label_values(&[endpoint_name])
+            .set(consecutive_failures as i64);
+        consecutive_failures
+    }
+
+    /// Logs a finished poll and records it for `/healthz`.
+    fn finish_pulse(&self, endpoint_name: &str, result: &anyhow::Result<PulseOutcome>) {
+        log_pulse_result(endpoint_name, result);
+
+        let now = Utc::now().timestamp();
+        let mut health = self.health();
+        match result {
+            Ok(outcome) => {
+                health.record_success(endpoint_name, now);
+                if !outcome.succeeded() {
+                    health.record_error(endpoint_name, now, &format!("failed {}", outcome.errors.join(", ")));
+                }
+            }
+            Err(e) => health.record_error(endpoint_name, now, &format!("{:#}", e)),
+        }
+    }
+
+    /// Feeds each bid's token metadata into the token cache, filling in anything a bid left out
+    /// and logging tokens that report inconsistent decimals.
+    fn track_tokens(&self, chain_id: i32, bids: &mut [serde_json::Value]) {
+        let mut tokens = lock_or_recover(&self.tokens, "token cache");
+        for bid in bids {
+            if let Some(mismatch) = tokens.observe(chain_id, bid) {
+                warn!(
+                    token = %mismatch.address,
+                    chain_id,
+                    reported = mismatch.reported,
+                    known = mismatch.known,
+                    "Token reported inconsistent decimals"
+                );
+            }
+            tokens.enrich(chain_id, bid);
+        }
+    }
+
+    /// Looks up the ENS name of each distinct borrower in `bids` on mainnet, so alerts can show
+    /// it. Borrowers without a name are left out and their alerts show the address alone.
+    async fn resolve_ens_names<'a>(
+        &self,
+        chain_id: i32,
+        bids: impl IntoIterator<Item = &'a serde_json::Value>,
+        options: &mut FormatOptions,
+    ) {
+        let Some(ens) = self.ens.as_ref().filter(|_| chain_id == ENS_CHAIN_ID) else {
+            return;
+        };
+        let borrowers: HashSet<String> = bids
+            .into_iter()
+            .filter_map(|bid| bid.get("borrowerAddress")?.as_str())
+            .map(|borrower| borrower.to_lowercase())
+            .collect();
+        for borrower in borrowers {
+            if let Some(name) = ens.name(&borrower).await {
+                options.ens_names.insert(borrower, name);
+            }
+        }
+    }
+
+    /// Looks up a price for each distinct lending token in `bids` so alerts can show principal
+    /// in fiat. Tokens without a price are left out and their alerts show the amount alone.
+    async fn price_bids<'a>(
+        &self,
+        chain_id: i32,
+        bids: impl IntoIterator<Item = &'a serde_json::Value>,
+        options: &mut FormatOptions,
+    ) {
+        let Some(prices) = &self.prices else {
+            return;
+        };
+        options.fiat_currency = prices.currency().to_string();
+
+        // Collateral tokens are priced too, for the collateral ratio
+        let tokens: HashSet<String> = bids
+            .into_iter()
+            .flat_map(|bid| {
+                let collateral = bid.get("collateral").and_then(|v| v.as_array()).into_iter().flatten();
+                std::iter::once(bid.get("lendingToken"))
+                    .chain(collateral.map(|entry| entry.get("token")))
+                    .filter_map(|token| token?.get("id")?.as_str())
+            })
+            .map(|token| token.to_lowercase())
+            .collect();
+        for token in tokens {
+            if let Some(price) = prices.price(chain_id, &token).await {
+                options.fiat_prices.insert(token, price);
+            }
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 struct Endpoint {
     name: String,
+    /// GraphQL URL, `${VAR}` placeholders are filled from the environment (e.g. a gateway API key)
     url: String,
     chain_id: i32,
     auth_key: Option<String>,
+    /// How the `auth_key` secret is sent, defaults to a bearer token
+    #[serde(default)]
+    auth_style: Option<AuthStyle>,
+    /// Extra request headers for `url`, values support `${VAR}` placeholders. These replace
+    /// the automatic `Content-Type` and `auth_key` bearer headers when the names collide
+    #[serde(default)]
+    headers: Option<HashMap<String, String>>,
+    /// Backup URLs tried in order when `url` fails, each with its own auth and headers
+    #[serde(default)]
+    fallback_urls: Vec<FallbackUrl>,
+    /// Deprecated alias for `enabled: Some(false)`, only read when `enabled` is unset
     #[serde(default)]
     disabled: bool,
-} 
+    /// Set to `Some(false)` to stop polling the endpoint while keeping its config, defaults to enabled
+    #[serde(default)]
+    enabled: Option<bool>,
+    /// How often to poll this endpoint, defaults to `--interval-secs`
+    #[serde(default)]
+    poll_interval_secs: Option<u64>,
+    /// Polls per interval relative to a weight-1 endpoint, defaults to 1
+    #[serde(default)]
+    weight: Option<u32>,
+    /// Slack channel for this endpoint's alerts, defaults to `SLACK_CHANNEL` or `#webserver-alerts`
+    #[serde(default)]
+    slack_channel: Option<String>,
+    /// `slack_workspaces` entry to send this endpoint's alerts to, defaults to `SLACK_OAUTH_TOKEN`'s workspace
+    #[serde(default)]
+    slack_workspace: Option<String>,
+    /// Name the daily summary totals this endpoint under, along with the other endpoints in it
+    #[serde(default)]
+    group: Option<String>,
+    /// Bid statuses to alert on when past due, defaults to `["Accepted"]`
+    #[serde(default = "default_statuses")]
+    statuses: Vec<String>,
+    /// Also alert on bids that can be liquidated, off when unset
+    #[serde(default)]
+    liquidation: Option<LiquidationConfig>,
+    /// Only query bids lent in these token addresses, all tokens when empty
+    #[serde(default)]
+    lending_tokens: Vec<String>,
+    /// Only query bids from these marketplace IDs, all marketplaces when empty
+    #[serde(default)]
+    marketplace_ids: Vec<String>,
+    /// Name of a `.graphql` file under `queries_dir` to send instead of the built-in overdue query
+    #[serde(default)]
+    query_template: Option<String>,
+    /// The `query_template` file's contents, read by `load_query_templates`
+    #[serde(skip)]
+    query: Option<String>,
+}
+
+fn default_statuses() -> Vec<String> {
+    vec!["Accepted".to_string()]
+}
+
+/// Which of an endpoint's bids are sent as liquidation opportunities.
+#[derive(Debug, Deserialize)]
+struct LiquidationConfig {
+    /// Bid statuses that can be liquidated, defaults to `["Defaulted"]`
+    #[serde(default = "default_liquidation_statuses")]
+    statuses: Vec<String>,
+    /// Also count bids in the endpoint's `statuses` whose collateral ratio is under this, e.g.
+    /// `1.0`, whether or not they're due yet. Needs `price_feed`
+    #[serde(default)]
+    max_collateral_ratio: Option<f64>,
+}
+
+fn default_liquidation_statuses() -> Vec<String> {
+    vec!["Defaulted".to_string()]
+}
+
+impl LiquidationConfig {
+    /// Statuses to query: the liquidatable ones, plus the endpoint's own when the collateral
+    /// ratio can make a bid liquidatable too.
+    fn query_statuses(&self, endpoint_statuses: &[String]) -> Vec<String> {
+        let mut statuses = self.statuses.clone();
+        if self.max_collateral_ratio.is_some() {
+            statuses.extend(endpoint_statuses.iter().filter(|status| !self.statuses.contains(status)).cloned());
+        }
+        statuses
+    }
+
+    fn is_liquidatable(&self, bid: &serde_json::Value, options: &FormatOptions) -> bool {
+        let status = bid.get("status").and_then(|v| v.as_str()).unwrap_or_default();
+        self.statuses.iter().any(|liquidatable| liquidatable == status)
+            || self
+                .max_collateral_ratio
+                .is_some_and(|max_ratio| collateral_ratio(bid, options).is_some_and(|ratio| ratio < max_ratio))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FallbackUrl {
+    url: String,
+    #[serde(default)]
+    auth_key: Option<String>,
+    #[serde(default)]
+    auth_style: Option<AuthStyle>,
+    #[serde(default)]
+    headers: Option<HashMap<String, String>>,
+}
+
+/// Where the `auth_key` secret goes on each request.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+enum AuthStyle {
+    /// `Authorization: Bearer <secret>`
+    #[default]
+    Bearer,
+    /// Appended to the URL's query string as `name=<secret>`
+    QueryParam { name: String },
+    /// Sent as the value of the `name` header
+    Header { name: String },
+}
+
+/// One URL an endpoint can be queried at, with its auth resolved from the environment.
+#[derive(Debug)]
+struct Source {
+    /// The URL as configured, safe to log since placeholders are left unexpanded
+    label: String,
+    url: String,
+    auth_token: Option<String>,
+    headers: HeaderMap,
+}
+
+impl Endpoint {
+    /// `enabled` when set, otherwise the deprecated `disabled`. `validate_config` rejects the
+    /// two disagreeing, so which wins only matters for configs that skipped validation.
+    fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(!self.disabled)
+    }
+
+    /// Seconds between polls: `poll_interval_secs` (or `default_secs`) divided by `weight`,
+    /// never under a second.
+    fn interval_secs(&self, default_secs: u64) -> u64 {
+        let weight = u64::from(self.weight.unwrap_or(1).max(1));
+        (self.poll_interval_secs.unwrap_or(default_secs) / weight).max(1)
+    }
+
+    /// The overdue query to send, from `query_template` if set.
+    fn due_query(&self) -> &str {
+        self.query.as_deref().unwrap_or(DUE_BIDS_QUERY)
+    }
+
+    fn bid_filters(&self) -> BidFilters<'_> {
+        BidFilters {
+            lending_tokens: &self.lending_tokens,
+            marketplace_ids: &self.marketplace_ids,
+        }
+    }
+
+    /// The `auth_key` variable names of the primary URL and `fallback_urls`.
+    fn auth_keys(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(&self.auth_key)
+            .chain(self.fallback_urls.iter().map(|fallback| &fallback.auth_key))
+            .flatten()
+            .map(String::as_str)
+    }
+
+    /// The primary URL followed by `fallback_urls`, in the order they are tried.
+    fn sources(&self) -> Vec<Source> {
+        std::iter::once((&self.url, &self.auth_key, &self.auth_style, &self.headers))
+            .chain(
+                self.fallback_urls
+                    .iter()
+                    .map(|fallback| (&fallback.url, &fallback.auth_key, &fallback.auth_style, &fallback.headers)),
+            )
+            .map(|(url, auth_key, auth_style, headers)| {
+                let mut source = Source {
+                    label: url.clone(),
+                    url: expand_env_vars(url),
+                    auth_token: None,
+                    headers: headers.as_ref().map(build_headers).unwrap_or_default(),
+                };
+                if let Some(secret) = auth_key.as_deref().and_then(resolve_auth_token) {
+                    source.apply_auth(auth_style.as_ref().unwrap_or(&AuthStyle::Bearer), secret);
+                }
+                source
+            })
+            .collect()
+    }
+}
+
+impl Source {
+    /// Attaches the `auth_key` secret the way `style` says. As with the bearer token, a
+    /// configured header of the same name is left to replace it.
+    fn apply_auth(&mut self, style: &AuthStyle, secret: String) {
+        match style {
+            AuthStyle::Bearer => self.auth_token = Some(secret),
+            AuthStyle::QueryParam { name } => match reqwest::Url::parse(&self.url) {
+                Ok(mut url) => {
+                    url.query_pairs_mut().append_pair(name, &secret);
+                    self.url = url.into();
+                }
+                Err(_) => warn!(url = %self.label, "Skipping query parameter auth for a URL that doesn't parse"),
+            },
+            AuthStyle::Header { name } => match (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(&secret)) {
+                (Ok(name), Ok(value)) => {
+                    self.headers.entry(name).or_insert(value);
+                }
+                _ => warn!(header = %name, "Skipping auth header that isn't a valid header"),
+            },
+        }
+    }
+}
+
+/// Expands configured header values, skipping (with a warning) any that aren't valid headers.
+fn build_headers(headers: &HashMap<String, String>) -> HeaderMap {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        match (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(&expand_env_vars(value))) {
+            (Ok(name), Ok(value)) => {
+                map.insert(name, value);
+            }
+            _ => warn!(header = %name, "Skipping header that isn't valid after expansion"),
+        }
+    }
+    map
+}
+
+/// Reads the secret for `auth_key` from the environment variable of that name.
+fn resolve_auth_token(auth_key: &str) -> Option<String> {
+    match env::var(auth_key) {
+        Ok(token) => {
+            debug!(auth_key, "Using authentication for endpoint");
+            Some(token)
+        }
+        Err(_) => {
+            warn!(auth_key, "auth_key specified but {} environment variable not set", auth_key);
+            None
+        }
+    }
+}
+
+/// Replaces each `${NAME}` in `value` with the `NAME` environment variable. Unset variables
+/// are left in place so the request fails visibly instead of going out with a blank.
+fn expand_env_vars(value: &str) -> String {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let placeholder = &rest[start..start + len + 3];
+        expanded.push_str(&rest[..start]);
+        match env::var(&placeholder[2..placeholder.len() - 1]) {
+            Ok(value) => expanded.push_str(&value),
+            Err(_) => expanded.push_str(placeholder),
+        }
+        rest = &rest[start + len + 3..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+const ONE_HOUR:u64 = 3600 ;
+
+const ONE_DAY:u64 = 86400;
+
+/// Locks `mutex`, taking over the guard if a task panicked while holding it. The state behind
+/// our locks is never left half updated, so carrying on beats crash-looping the monitor.
+fn lock_or_recover<'a, T>(mutex: &'a Mutex<T>, name: &str) -> MutexGuard<'a, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        warn!(lock = name, "Lock was poisoned by a panicked task, recovering");
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+const DEFAULT_LOOKBACK_SECS: u64 = 30 * ONE_DAY;
+
+const ALERTED_BIDS_FILE: &str = "alerted_bids.txt";
+
+const RETRY_QUEUE_FILE: &str = "pending_alerts.jsonl";
+
+// Alerts kept for retry before the oldest are dropped
+const DEFAULT_MAX_QUEUED_ALERTS: usize = 500;
+
+const DEFAULT_MAX_CONCURRENT_POLLS: usize = 4;
+
+// Default gap between repeat alerts for an endpoint that stays down
+const DEFAULT_FAILURE_REALERT_SECS: u64 = 6 * ONE_HOUR;
+
+// Error text kept in a failure alert, the full error goes to the debug log
+const DEFAULT_MAX_ERROR_CHARS: usize = 1500;
+
+const DEFAULT_SNOOZE_SECS: u64 = 4 * ONE_HOUR;
+
+const DEFAULT_STATS_LOG_SECS: u64 = ONE_HOUR;
+
+const DEFAULT_VALUE_AT_RISK_REALERT_SECS: u64 = ONE_DAY;
+
+// How long a panic alert may hold up the panicking thread
+const PANIC_ALERT_TIMEOUT_SECS: u64 = 10;
+
+const HTTP_CONNECT_TIMEOUT_SECS: u64 = 10;
+const HTTP_REQUEST_TIMEOUT_SECS: u64 = 30;
+const HTTP_POOL_IDLE_TIMEOUT_SECS: u64 = 90;
+
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const DEFAULT_BASE_BACKOFF_MS: u64 = 500;
+
+// Bids requested per page of the due queries, and the most a subgraph returns in one page
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+// Guards against paging forever if an endpoint keeps returning full pages
+const DEFAULT_MAX_PAGES: usize = 10;
+
+// Bid field the due queries are sorted by unless `order_by` says otherwise, most overdue first
+const DEFAULT_ORDER_BY: &str = "nextDueDate";
+
+// How many alerted bid ids to look up per recovery query
+const RECOVERY_LOOKUP_CHUNK: usize = 100;
+
+/// Decides whether a bid that was already alerted should be alerted again.
+///
+/// A changed principal always re-alerts. Otherwise we only re-alert once
+/// `realert_after_secs` has elapsed since the last alert; records without a
+/// timestamp (written before timestamps were stored) count as expired.
+fn should_realert(record: &AlertRecord, principal: &str, now: i64, realert_after_secs: Option<u64>) -> bool {
+    let principal_changed = record.principal.as_deref().is_some_and(|p| p != principal);
+    if principal_changed {
+        return true;
+    }
+
+    match realert_after_secs {
+        Some(cooldown) => record.alerted_at.is_none_or(|t| now - t >= cooldown as i64),
+        None => false,
+    }
+}
+
+
+/// Whether the bid's principal is below the `min_principal` configured for its token, looked
+/// up by token address first and then by symbol. Bids for tokens without a threshold pass.
+fn below_min_principal(bid: &serde_json::Value, min_principal: &HashMap<String, String>) -> bool {
+    if min_principal.is_empty() {
+        return false;
+    }
+
+    let token = bid.get("lendingToken");
+    let address = token.and_then(|v| v.get("id")).and_then(|v| v.as_str()).map(|id| id.to_lowercase());
+    let symbol = token.and_then(|v| v.get("symbol")).and_then(|v| v.as_str());
+    let threshold = address
+        .and_then(|address| min_principal.iter().find(|(key, _)| key.to_lowercase() == address))
+        .map(|(_, amount)| amount)
+        .or_else(|| symbol.and_then(|symbol| min_principal.get(symbol)));
+
+    let Some(threshold) = threshold.and_then(|amount| parse_token_amount(amount, bid_token_decimals(bid))) else {
+        return false;
+    };
+    let principal = bid
+        .get("principal")
+        .and_then(|v| v.as_str())
+        .and_then(|raw| U256::from_dec_str(raw).ok())
+        .unwrap_or_default();
+
+    principal < threshold
+}
+
+/// Normalizes an address for comparison to its EIP-55 checksum form, so any casing matches.
+fn normalize_address(address: &str) -> String {
+    checksum_address(&address.trim().to_lowercase())
+}
+
+/// The bid's `bidId`, which its alert state is keyed by. `None` when it is missing, empty or
+/// not a string, since falling back to a placeholder would merge distinct loans into one key.
+fn bid_identifier(bid: &serde_json::Value) -> Option<&str> {
+    bid.get("bidId").and_then(|v| v.as_str()).filter(|bid_id| !bid_id.trim().is_empty())
+}
+
+/// Whether the bid's borrower appears in `borrowers`.
+fn is_listed_borrower(bid: &serde_json::Value, borrowers: &[String]) -> bool {
+    let Some(borrower) = bid.get("borrowerAddress").and_then(|v| v.as_str()).map(normalize_address) else {
+        return false;
+    };
+    borrowers.iter().any(|listed| normalize_address(listed) == borrower)
+}
+
+/// Where a previously alerted bid stands when it is looked up again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AlertedBidState {
+    /// Still overdue, or its state can't be told from the lookup
+    Overdue,
+    /// Repaid, or still in a monitored status with its next due date ahead
+    Recovered,
+    /// Moved to a status such as `Defaulted` or `Liquidated` that is not monitored, so it is
+    /// no longer tracked but did not recover either
+    Closed,
+}
+
+fn alerted_bid_state(bid: &serde_json::Value, now: i64, statuses: &[String]) -> AlertedBidState {
+    let Some(status) = bid.get("status").and_then(|v| v.as_str()) else {
+        return AlertedBidState::Overdue;
+    };
+    if statuses.iter().any(|monitored| monitored == status) {
+        match bid_next_due(bid) {
+            Some(due) if due >= now => AlertedBidState::Recovered,
+            _ => AlertedBidState::Overdue,
+        }
+    } else if status == "Repaid" {
+        AlertedBidState::Recovered
+    } else {
+        AlertedBidState::Closed
+    }
+}
+
+
+#[tokio::main]
+async fn main() {
+    // Load environment variables from .env file if it exists
+    dotenvy::dotenv().ok();
+
+    let args = Args::parse();
+    init_tracing();
+
+    info!("Starting loan health monitor");
+
+    let timezone_name = env::var("ALERT_TIMEZONE").ok();
+    let timezone = resolve_alert_timezone(timezone_name.as_deref());
+
+    // The alerted bids file is edited directly, without needing a valid config
+    match &args.command {
+        Some(Command::Validate { strict }) => {
+            let (report, valid) = validation_report(load_config(&args.config), timezone_name.as_deref(), *strict);
+            print!("{}", report);
+            if !valid {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::ListAlerts) => {
+            print!("{}", list_alerts(&AlertStore::load(&args.alerted_bids_file), timezone));
+            return;
+        }
+        Some(Command::ClearAlerts { chain, bid, yes }) => {
+            let mut store = AlertStore::load(&args.alerted_bids_file);
+            match alerts_to_clear(&store, *chain, bid.as_deref(), *yes) {
+                Ok(keys) => {
+                    store.remove(&keys);
+                    info!(cleared = keys.len(), file = %args.alerted_bids_file.display(), "Cleared alerted bids");
+                }
+                Err(e) => {
+                    error!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    let config = match load_config(&args.config).and_then(|mut config| {
+        validate_config(&config)?;
+        let queries_dir = config.queries_dir.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_QUERIES_DIR));
+        load_query_templates(&mut config, &queries_dir)?;
+        Ok(config)
+    }) {
+        Ok(config) => config,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(page_size) = config.page_size
+        && page_size > MAX_PAGE_SIZE
+    {
+        warn!(page_size, max = MAX_PAGE_SIZE, "page_size is more than a subgraph returns per page, using the max");
+    }
+
+    // One client for every poll so connections and TLS sessions are pooled across cycles
+    let request_timeout_secs = config.request_timeout_secs.unwrap_or(HTTP_REQUEST_TIMEOUT_SECS);
+    let client = match build_http_client(request_timeout_secs) {
+        Ok(client) => client,
+        Err(e) => {
+            error!(error = %e, "Failed to build HTTP client");
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(Command::Export { format, out }) = &args.command {
+        if !run_export(&client, &config, timezone, *format, out).await {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let notifier = match build_notifier(args.dry_run, &config.notifiers, None, &client, config.retry_policy()) {
+        Ok(notifier) => notifier,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut workspace_notifiers = HashMap::new();
+    for (name, workspace) in &config.slack_workspaces {
+        info!(workspace = %name, "Setting up Slack workspace");
+        match build_notifier(args.dry_run, &config.notifiers, Some(workspace), &client, config.retry_policy()) {
+            Ok(notifier) => {
+                workspace_notifiers.insert(name.clone(), notifier);
+            }
+            Err(e) => {
+                error!(workspace = %name, "{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let prices = config.price_feed.as_ref().map(|feed| PriceCache::from_config(feed, client.clone()));
+    let pagerduty = env::var("PAGERDUTY_ROUTING_KEY")
+        .ok()
+        .map(|routing_key| PagerDuty::new(client.clone(), PAGERDUTY_EVENTS_URL.to_string(), routing_key, config.retry_policy()));
+    // Checked by `validate_config` to have RPC URLs for the chain
+    let ens = config.resolve_ens.then(|| {
+        EnsCache::new(
+            client.clone(),
+            config.rpc_urls.get(&ENS_CHAIN_ID).cloned().unwrap_or_default(),
+            config.retry_policy(),
+            Duration::from_secs(config.ens_cache_ttl_secs.unwrap_or(DEFAULT_ENS_CACHE_TTL_SECS)),
+        )
+    });
+
+    // Bounds how many endpoint tasks hit the network at once so a shared gateway isn't hammered
+    let max_concurrent_polls = config.max_concurrent_polls.unwrap_or(DEFAULT_MAX_CONCURRENT_POLLS).max(1);
+
+    let ctx = Arc::new(MonitorContext {
+        client,
+        store: Mutex::new(AlertStore::load(&args.alerted_bids_file)),
+        retry_queue: Mutex::new(RetryQueue::load(
+            &args.retry_queue_file,
+            config.max_queued_alerts.unwrap_or(DEFAULT_MAX_QUEUED_ALERTS),
+        )),
+        audit: AuditLog::new(args.audit_log_file.clone()),
+        poll_permits: Semaphore::new(max_concurrent_polls),
+        notifier,
+        default_interval_secs: args.interval_secs,
+        timezone,
+        prices,
+        ens,
+        pagerduty,
+        escalated: Mutex::new(HashMap::new()),
+        workspace_notifiers,
+        tokens: Mutex::new(TokenCache::default()),
+        health: Mutex::new(HealthState::new(Utc::now().timestamp())),
+        unchanged_results: Mutex::new(HashMap::new()),
+        all_clear_sent: Mutex::new(HashMap::new()),
+        slack_signing_secret: args.slack_signing_secret.clone(),
+        shutdown: watch::channel(false).0,
+        config,
+    });
+
+    if ctx.config.panic_alerts.unwrap_or(true) {
+        install_panic_alert_hook(Arc::clone(&ctx));
+    }
+
+    for endpoint_data in ctx.config.endpoints.iter().filter(|endpoint| !endpoint.is_enabled()) {
+        info!(endpoint = %endpoint_data.name, url = %endpoint_data.url, "Skipping disabled endpoint");
+    }
+
+    if args.once {
+        let summary = poll_all_endpoints(&ctx).await;
+
+        ctx.store().flush();
+        if !summary.succeeded() {
+            error!("One or more endpoints failed");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let results = preflight(&ctx).await;
+    info!("Preflight results:\n{}", format_preflight_table(&results));
+    let failed = results.iter().filter(|check| check.result.is_err()).count();
+    if failed > 0 {
+        if args.strict_preflight {
+            error!(failed, "Preflight failed, exiting");
+            std::process::exit(1);
+        }
+        warn!(failed, "Preflight failed for some endpoint urls, they will keep being polled");
+    }
+
+    let signal_ctx = Arc::clone(&ctx);
+    tokio::spawn(async move {
+        wait_for_shutdown_signal().await;
+        info!("Shutdown requested, waiting for in-flight polls to finish");
+        signal_ctx.shutdown.send_replace(true);
+    });
+
+    // Each endpoint gets its own task and ticker so busy chains can be polled more often
+    let mut tasks = Vec::new();
+    let enabled_count = ctx.config.endpoints.iter().filter(|endpoint| endpoint.is_enabled()).count();
+    let mut position = 0;
+    for (index, endpoint_data) in ctx.config.endpoints.iter().enumerate() {
+        if !endpoint_data.is_enabled() {
+            continue;
+        }
+
+        let start_delay = if ctx.config.stagger_polls {
+            stagger_offset(endpoint_data.interval_secs(ctx.default_interval_secs), position, enabled_count)
+        } else {
+            Duration::ZERO
+        };
+        position += 1;
+
+        let ctx = Arc::clone(&ctx);
+        tasks.push(tokio::spawn(async move {
+            monitor_endpoint(&ctx, &ctx.config.endpoints[index], start_delay).await;
+        }));
+    }
+
+    if let Some(port) = args.metrics_port {
+        let ctx = Arc::clone(&ctx);
+        tasks.push(tokio::spawn(async move {
+            serve_http(port, ctx).await;
+        }));
+    }
+
+    {
+        let ctx = Arc::clone(&ctx);
+        tasks.push(tokio::spawn(async move {
+            run_stats_log(&ctx).await;
+        }));
+    }
+
+    if ctx.config.heartbeat.is_some() {
+        let ctx = Arc::clone(&ctx);
+        tasks.push(tokio::spawn(async move {
+            run_heartbeat(&ctx).await;
+        }));
+    }
+
+    if ctx.config.daily_summary.is_some() {
+        let ctx = Arc::clone(&ctx);
+        tasks.push(tokio::spawn(async move {
+            run_daily_summary(&ctx).await;
+        }));
+    }
+
+    for result in join_all(tasks).await {
+        if let Err(e) = result {
+            error!(error = %e, "Endpoint monitor task failed");
+        }
+    }
+
+    ctx.store().flush();
+    info!("Shutdown complete");
+}
+
+/// Next time at or after `now` that the wall clock in `timezone` reads `send_at`, skipping to
+/// the following day if it has already passed today.
+fn next_summary_time(now: DateTime<Utc>, send_at: NaiveTime, timezone: Tz) -> DateTime<Utc> {
+    let mut date = now.with_timezone(&timezone).date_naive();
+
+    loop {
+        // A time that doesn't exist locally (a DST gap) is skipped in favour of the next day
+        if let Some(local) = timezone.from_local_datetime(&date.and_time(send_at)).earliest() {
+            let utc = local.with_timezone(&Utc);
+            if utc > now {
+                return utc;
+            }
+        }
+        date = date.succ_opt().expect("date out of range");
+    }
+}
+
+/// Posts the daily overdue summary at the configured time until shutdown.
+async fn run_daily_summary(ctx: &MonitorContext) {
+    let Some(summary_config) = &ctx.config.daily_summary else {
+        return;
+    };
+    // Checked by `validate_config`
+    let send_at = summary_config.send_at().expect("invalid daily summary time");
+    let mut shutdown = ctx.shutdown.subscribe();
+
+    while !*shutdown.borrow() {
+        let next = next_summary_time(Utc::now(), send_at, ctx.timezone);
+        info!(at = %format_timestamp(next, ctx.timezone), "Next daily summary scheduled");
+
+        let wait = (next - Utc::now()).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = time::sleep(wait) => {}
+            _ = shutdown.changed() => break,
+        }
+
+        send_daily_summary(ctx, summary_config.channel.as_deref()).await;
+    }
+
+    info!("Stopped daily summary");
+}
+
+/// Logs each endpoint's poll stats every `stats_log_secs`, so flaky endpoints show up in the
+/// logs without scraping metrics, until shutdown.
+async fn run_stats_log(ctx: &MonitorContext) {
+    let period = Duration::from_secs(ctx.config.stats_log_secs.unwrap_or(DEFAULT_STATS_LOG_SECS));
+    // The first tick would fire before anything has been polled
+    let mut interval = time::interval_at(time::Instant::now() + period, period);
+    let mut shutdown = ctx.shutdown.subscribe();
+
+    while !*shutdown.borrow() {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.changed() => break,
+        }
+
+        for (endpoint, stats) in ctx.health().endpoint_stats() {
+            info!(
+                endpoint = %endpoint,
+                polls = stats.polls,
+                successes = stats.successes,
+                graphql_errors = stats.graphql_errors,
+                transport_errors = stats.transport_errors,
+                consecutive_failures = stats.consecutive_failures,
+                uptime_percent = format!("{:.1}", stats.uptime_percent().unwrap_or(100.0)),
+                "Endpoint poll stats"
+            );
+        }
+    }
+
+    info!("Stopped stats log");
+}
+
+/// Posts the heartbeat every `interval_secs`, starting right away, until shutdown.
+async fn run_heartbeat(ctx: &MonitorContext) {
+    let Some(heartbeat) = &ctx.config.heartbeat else {
+        return;
+    };
+    let mut interval = time::interval(Duration::from_secs(heartbeat.interval_secs.max(1)));
+    let mut shutdown = ctx.shutdown.subscribe();
+
+    while !*shutdown.borrow() {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.changed() => break,
+        }
+
+        let endpoints: Vec<&str> = ctx
+            .config
+            .endpoints
+            .iter()
+            .filter(|endpoint| endpoint.is_enabled())
+            .map(|endpoint| endpoint.name.as_str())
+            .collect();
+        let failing: Vec<&str> = {
+            let store = ctx.store();
+            endpoints.iter().copied().filter(|name| store.is_endpoint_failing(name)).collect()
+        };
+
+        let message = format_heartbeat(&format_timestamp(Utc::now(), ctx.timezone), endpoints.len(), &failing);
+        let _ = send_slack_warning(ctx.notifier.as_ref(), heartbeat.channel.as_deref(), &message).await;
+
+        if let Some(ping_url) = &heartbeat.ping_url {
+            let ping = ctx.client.get(ping_url).send().await.and_then(|response| response.error_for_status());
+            if let Err(e) = ping {
+                warn!(error = %e.without_url(), "Heartbeat ping failed");
+            }
+        }
+    }
+
+    info!("Stopped heartbeat");
+}
+
+/// One line per recorded key for `list-alerts`, sorted by key.
+fn list_alerts(store: &AlertStore, timezone: Tz) -> String {
+    let time = |secs: i64| {
+        DateTime::from_timestamp(secs, 0).map(|at| format_timestamp(at, timezone)).unwrap_or_else(|| secs.to_string())
+    };
+
+    let mut records: Vec<_> = store.records().collect();
+    records.sort_by_key(|(key, _)| *key);
+    records
+        .into_iter()
+        .map(|(key, record)| {
+            let mut line = key.clone();
+            if let Some(alerted_at) = record.alerted_at {
+                line.push_str(&format!("  alerted {}", time(alerted_at)));
+            }
+            if let Some(principal) = &record.principal {
+                line.push_str(&format!("  principal {}", principal));
+            }
+            if let Some(snoozed_until) = record.snoozed_until {
+                line.push_str(&format!("  snoozed until {}", time(snoozed_until)));
+            }
+            line + "\n"
+        })
+        .collect()
+}
+
+/// Keys `clear-alerts` removes: the one bid, every key on the chain, or with `yes` everything.
+fn alerts_to_clear(store: &AlertStore, chain: Option<i32>, bid: Option<&str>, yes: bool) -> Result<HashSet<String>, String> {
+    match (chain, bid) {
+        (Some(chain_id), Some(bid_id)) => {
+            let key = make_bid_key(chain_id, bid_id);
+            match store.get(&key) {
+                Some(_) => Ok(HashSet::from([key])),
+                None => Err(format!("No alert recorded for bid {} on chain {}", bid_id, chain_id)),
+            }
+        }
+        (Some(chain_id), None) => Ok(store.keys_for_chain(chain_id)),
+        (None, _) if yes => Ok(store.records().map(|(key, _)| key.clone()).collect()),
+        (None, _) => Err("clear-alerts without --chain removes every alerted bid, pass --yes to confirm".to_string()),
+    }
+}
+
+/// The startup checks run by `validate`, one line per check: the config parses and passes
+/// `validate_config`, its query templates load, `timezone` (`ALERT_TIMEZONE`) is valid and
+/// the `notifiers` and `slack_workspaces` backends have their credentials set. Unset
+/// credentials and `auth_key` variables are warnings unless `strict`, so CI without the
+/// secrets can still check the config. Returns `false` along with the report if any check
+/// failed.
+fn validation_report(loaded: Result<EndpointConfig, String>, timezone: Option<&str>, strict: bool) -> (String, bool) {
+    let mut lines = Vec::new();
+    let mark = |result: Result<String, String>| match result {
+        Ok(line) => format!("✓ {}", line),
+        Err(line) => format!("✗ {}", line),
+    };
+    let mark_env = |line: String| if strict { format!("✗ {}", line) } else { format!("⚠ {}", line) };
+
+    match loaded {
+        Ok(mut config) => {
+            let enabled = config.endpoints.iter().filter(|endpoint| endpoint.is_enabled()).count();
+            lines.push(format!("✓ Config parsed, {} endpoints ({} enabled)", config.endpoints.len(), enabled));
+            lines.push(mark(validate_config(&config).map(|_| "Config checks passed".to_string())));
+            let queries_dir = config.queries_dir.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_QUERIES_DIR));
+            lines.push(mark(load_query_templates(&mut config, &queries_dir).map(|_| "Query templates loaded".to_string())));
+            for endpoint in &config.endpoints {
+                for key in endpoint.auth_keys().filter(|key| env::var(key).is_err()) {
+                    lines.push(mark_env(format!("Endpoint '{}' auth_key {} is not set", endpoint.name, key)));
+                }
+            }
+            if !config.notifiers.is_empty() || !config.slack_workspaces.is_empty() {
+                // Built the way startup builds them, without sending anything
+                let client = reqwest::Client::new();
+                let mut workspaces: Vec<_> = config.slack_workspaces.iter().collect();
+                workspaces.sort_by_key(|(name, _)| name.as_str());
+                let built = build_notifier(false, &config.notifiers, None, &client, config.retry_policy()).and_then(|_| {
+                    workspaces.into_iter().try_for_each(|(name, workspace)| {
+                        build_notifier(false, &config.notifiers, Some(workspace), &client, config.retry_policy())
+                            .map(|_| ())
+                            .map_err(|e| format!("Slack workspace '{}': {}", name, e))
+                    })
+                });
+                lines.push(match built {
+                    Ok(()) => mark(Ok("Notifier credentials set".to_string())),
+                    Err(e) => mark_env(e),
+                });
+            }
+        }
+        Err(e) => lines.push(mark(Err(e))),
+    }
+
+    if let Some(name) = timezone {
+        let result = match name.parse::<Tz>() {
+            Ok(_) => Ok(format!("ALERT_TIMEZONE {} is valid", name)),
+            Err(_) => Err(format!("ALERT_TIMEZONE '{}' is not a valid timezone", name)),
+        };
+        lines.push(mark(result));
+    }
+
+    let valid = !lines.iter().any(|line| line.starts_with('✗'));
+    (lines.into_iter().map(|line| line + "\n").collect(), valid)
+}
+
+/// Queries every enabled endpoint for its overdue bids, with the fallbacks and paging of a
+/// poll, and writes them to `out`. Nothing is sent and the alert state is not touched.
+///
+/// Endpoints whose query fails are left out of the file. Returns `false` if any failed or
+/// the file couldn't be written.
+async fn run_export(client: &reqwest::Client, config: &EndpointConfig, timezone: Tz, format: ExportFormat, out: &Path) -> bool {
+    let mut rows = Vec::new();
+    let mut succeeded = true;
+
+    for endpoint_data in config.endpoints.iter().filter(|endpoint| endpoint.is_enabled()) {
+        let sources = endpoint_data.sources();
+        let window = DueWindow::new(
+            Utc::now().timestamp(),
+            config.lookback_secs.unwrap_or(DEFAULT_LOOKBACK_SECS),
+            config.grace_secs.unwrap_or(0),
+        );
+
+        match fetch_due_bids_with_fallback(client, config, endpoint_data, &sources, window).await {
+            Ok((bids, _)) => {
+                info!(endpoint = %endpoint_data.name, count = bids.len(), "Exporting overdue bids");
+                rows.extend(bids.iter().map(|bid| ExportRow::from_bid(endpoint_data.chain_id, bid, timezone)));
+            }
+            Err(e) => {
+                error!(endpoint = %endpoint_data.name, error = %e, "Failed to query endpoint for export");
+                succeeded = false;
+            }
+        }
+    }
+
+    let written = export::render(&rows, format).and_then(|content| Ok(fs::write(out, content)?));
+    match written {
+        Ok(()) => {
+            info!(path = %out.display(), rows = rows.len(), "Export written");
+            succeeded
+        }
+        Err(e) => {
+            error!(path = %out.display(), error = %e, "Failed to write export");
+            false
+        }
+    }
+}
+
+/// Queries every enabled endpoint for its overdue bids and posts one summary message. The
+/// per-bid alert state is left untouched.
+async fn send_daily_summary(ctx: &MonitorContext, channel: Option<&str>) {
+    let config = &ctx.config;
+    let mut chains = Vec::new();
+
+    for endpoint_data in config.endpoints.iter().filter(|endpoint| endpoint.is_enabled()) {
+        let sources = endpoint_data.sources();
+        let window = DueWindow::new(
+            Utc::now().timestamp(),
+            config.lookback_secs.unwrap_or(DEFAULT_LOOKBACK_SECS),
+            config.grace_secs.unwrap_or(0),
+        );
+
+        let _permit = ctx.poll_permits.acquire().await.expect("Poll semaphore closed");
+        let bids = fetch_due_bids_with_fallback(&ctx.client, &ctx.config, endpoint_data, &sources, window)
+        .await
+        .map(|(mut bids, _)| {
+            ctx.track_tokens(endpoint_data.chain_id, &mut bids);
+            bids
+        })
+        .map_err(|e| e.to_string());
+
+        chains.push(ChainSummary {
+            name: endpoint_data.name.clone(),
+            chain_id: endpoint_data.chain_id,
+            group: endpoint_data.group.clone(),
+            bids,
+        });
+    }
+
+    let timestamp = format_timestamp(Utc::now(), ctx.timezone);
+    let message = format_daily_summary(&timestamp, &chains, &ctx.format_options());
+    let _ = send_slack_warning(ctx.notifier.as_ref(), channel, &message).await;
+}
+
+/// Sets up logging, filtered by `RUST_LOG` (`info` by default) and written as JSON lines
+/// when `LOG_FORMAT=json`.
+fn init_tracing() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    if env::var("LOG_FORMAT").is_ok_and(|format| format.eq_ignore_ascii_case("json")) {
+        subscriber.json().init();
+    } else {
+        subscriber.init();
+    }
+}
+
+/// Resolves the `ALERT_TIMEZONE` value `name` (an IANA name such as `Europe/London`) for
+/// alert timestamps, falling back to US/Eastern when unset or invalid.
+fn resolve_alert_timezone(name: Option<&str>) -> Tz {
+    match name {
+        Some(name) => match name.parse::<Tz>() {
+            Ok(timezone) => timezone,
+            Err(_) => {
+                warn!("ALERT_TIMEZONE '{}' is not a valid timezone, using {}", name, DEFAULT_TIMEZONE);
+                DEFAULT_TIMEZONE
+            }
+        },
+        None => DEFAULT_TIMEZONE,
+    }
+}
+
+/// Builds the notifier alerts are delivered through. With `notifiers` configured every listed
+/// backend gets each alert (limited to its `severities`, if any); otherwise the first backend
+/// with credentials set is used, in `Backend::ALL` order. Fails if a listed backend has no
+/// credentials or `WEBHOOK_TEMPLATE` is set but isn't valid JSON.
+///
+/// With `workspace`, Slack posts with that workspace's token instead of `SLACK_OAUTH_TOKEN`,
+/// and the other backends are the same as without.
+fn build_notifier(
+    dry_run: bool,
+    routes: &[NotifierRoute],
+    workspace: Option<&SlackWorkspace>,
+    client: &reqwest::Client,
+    retry: RetryPolicy,
+) -> Result<Box<dyn Notifier>, String> {
+    if dry_run {
+        info!("Dry run: alerts will be logged instead of sent");
+        return Ok(Box::new(DryRunNotifier));
+    }
+
+    if !routes.is_empty() {
+        let mut notifiers: Vec<Box<dyn Notifier>> = Vec::new();
+        for route in routes {
+            let notifier = build_backend(route.backend, workspace, client, retry)?.ok_or_else(|| {
+                format!("notifiers lists {:?} but {} is not set", route.backend, route.backend.credentials())
+            })?;
+            notifiers.push(if route.severities.is_empty() {
+                notifier
+            } else {
+                info!(backend = ?route.backend, severities = ?route.severities, "Limiting backend to severities");
+                Box::new(SeverityFilter::new(route.severities.clone(), notifier))
+            });
+        }
+        return Ok(Box::new(MultiNotifier::new(notifiers)));
+    }
+
+    for backend in Backend::ALL {
+        if let Some(notifier) = build_backend(backend, workspace, client, retry)? {
+            return Ok(notifier);
+        }
+    }
+
+    warn!("No notifier credentials set (Slack, Discord, Telegram, Teams, Matrix or webhook), alerts will only be logged");
+    Ok(Box::new(DryRunNotifier))
+}
+
+/// The backend's notifier, or `None` when its environment variables aren't set.
+fn build_backend(
+    backend: Backend,
+    workspace: Option<&SlackWorkspace>,
+    client: &reqwest::Client,
+    retry: RetryPolicy,
+) -> Result<Option<Box<dyn Notifier>>, String> {
+    let notifier: Box<dyn Notifier> = match backend {
+        Backend::Slack => {
+            let (token, channel) = match workspace {
+                Some(workspace) => {
+                    let token = env::var(&workspace.token_env)
+                        .map_err(|_| format!("slack_workspaces entry needs {} to be set", workspace.token_env))?;
+                    (token, workspace.channel.clone())
+                }
+                None => {
+                    let Ok(token) = env::var("SLACK_OAUTH_TOKEN") else {
+                        return Ok(None);
+                    };
+                    (token, env::var("SLACK_CHANNEL").ok())
+                }
+            };
+            let bot = SlackBot::new(client.clone(), token);
+            // Endpoints without their own `slack_channel` post here
+            match channel {
+                Some(channel) => Box::new(bot.with_channel(channel)),
+                None => Box::new(bot),
+            }
+        }
+        Backend::Discord => {
+            let Ok(webhook_url) = env::var("DISCORD_WEBHOOK_URL") else {
+                return Ok(None);
+            };
+            Box::new(DiscordWebhook::new(client.clone(), webhook_url))
+        }
+        Backend::Telegram => {
+            let (Ok(token), Ok(chat_id)) = (env::var("TELEGRAM_BOT_TOKEN"), env::var("TELEGRAM_CHAT_ID")) else {
+                return Ok(None);
+            };
+            let markdown_v2 = env::var("TELEGRAM_PARSE_MODE").is_ok_and(|mode| mode == "MarkdownV2");
+            Box::new(TelegramNotifier::new(client.clone(), token, chat_id).with_markdown_v2(markdown_v2))
+        }
+        Backend::Teams => {
+            let Ok(webhook_url) = env::var("TEAMS_WEBHOOK_URL") else {
+                return Ok(None);
+            };
+            Box::new(TeamsNotifier::new(client.clone(), webhook_url))
+        }
+        Backend::Matrix => {
+            let (Ok(homeserver_url), Ok(access_token), Ok(room_id)) =
+                (env::var("MATRIX_HOMESERVER_URL"), env::var("MATRIX_ACCESS_TOKEN"), env::var("MATRIX_ROOM_ID"))
+            else {
+                return Ok(None);
+            };
+            Box::new(MatrixNotifier::new(client.clone(), homeserver_url, access_token, room_id))
+        }
+        Backend::Webhook => {
+            let Ok(url) = env::var("WEBHOOK_URL") else {
+                return Ok(None);
+            };
+            let template = env::var("WEBHOOK_TEMPLATE").unwrap_or_else(|_| webhook::DEFAULT_TEMPLATE.to_string());
+            Box::new(WebhookNotifier::new(client.clone(), url, &template, retry)?)
+        }
+    };
+
+    info!("Sending alerts to {:?}", backend);
+    Ok(Some(notifier))
+}
+
+fn build_http_client(request_timeout_secs: u64) -> Result<reqwest::Client, reqwest::Error> {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(HTTP_CONNECT_TIMEOUT_SECS))
+        .timeout(Duration::from_secs(request_timeout_secs))
+        .pool_idle_timeout(Duration::from_secs(HTTP_POOL_IDLE_TIMEOUT_SECS))
+        .tcp_keepalive(Duration::from_secs(HTTP_POOL_IDLE_TIMEOUT_SECS))
+        .build()
+}
+
+/// Reads the endpoint config from the `ENDPOINTS_RON` environment variable when it is set, so
+/// containers can inject it from a ConfigMap or Secret, and from `path` otherwise.
+fn load_config(path: &Path) -> Result<EndpointConfig, String> {
+    match env::var("ENDPOINTS_RON") {
+        Ok(content) => {
+            info!("Loading endpoint config from ENDPOINTS_RON");
+            parse_endpoint_config(&content, ConfigFormat::Ron, "ENDPOINTS_RON")
+        }
+        Err(_) => load_endpoint_config(path),
+    }
+}
+
+/// Syntax of a config file, picked by its extension.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ConfigFormat {
+    Ron,
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    fn from_path(path: &Path) -> Result<Self, String> {
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+        match extension.to_ascii_lowercase().as_str() {
+            "ron" => Ok(Self::Ron),
+            "json" => Ok(Self::Json),
+            "yaml" | "yml" => Ok(Self::Yaml),
+            _ => Err(format!(
+                "Unsupported config file {}, expected a .ron, .json, .yaml or .yml extension",
+                path.display()
+            )),
+        }
+    }
+}
+
+fn load_endpoint_config(path: &Path) -> Result<EndpointConfig, String> {
+    let format = ConfigFormat::from_path(path)?;
+    let config_content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+
+    parse_endpoint_config(&config_content, format, &format!("config file {}", path.display()))
+}
+
+/// Parses config read from `source`, which names it in the error. Each format's error
+/// includes the line and column of the mistake.
+fn parse_endpoint_config(content: &str, format: ConfigFormat, source: &str) -> Result<EndpointConfig, String> {
+    let parsed = match format {
+        ConfigFormat::Ron => ron::from_str(content).map_err(|e| e.to_string()),
+        ConfigFormat::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+        ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|e| e.to_string()),
+    };
+    parsed.map_err(|e| format!("Failed to parse {}: {}", source, e))
+}
+
+/// Catches config mistakes up front instead of letting every poll fail on them.
+///
+/// Missing `auth_key` variables only warn, since some gateways still answer unauthenticated.
+fn validate_config(config: &EndpointConfig) -> Result<(), String> {
+    let mut names = HashSet::new();
+
+    if let Some(summary) = &config.daily_summary {
+        summary.send_at()?;
+    }
+
+    if let Some(min_ratio) = config.min_collateral_ratio
+        && (min_ratio.is_nan() || min_ratio <= 0.0)
+    {
+        return Err(format!("Invalid min_collateral_ratio {}, expected a positive ratio", min_ratio));
+    }
+
+    if !config.value_at_risk_thresholds.is_empty() && config.price_feed.is_none() {
+        return Err("value_at_risk_thresholds needs a price_feed to value overdue principal".to_string());
+    }
+    if let Some((chain_id, threshold)) = config
+        .value_at_risk_thresholds
+        .iter()
+        .find(|(_, threshold)| threshold.is_nan() || **threshold <= 0.0)
+    {
+        return Err(format!("Invalid value_at_risk_thresholds entry {} for chain {}, expected a positive amount", threshold, chain_id));
+    }
+
+    for route in &config.notifiers {
+        if let Some(unknown) = route.severities.iter().find(|name| !is_known_severity(config, name)) {
+            return Err(format!("notifiers entry for {:?} lists unknown severity '{}'", route.backend, unknown));
+        }
+    }
+
+    if config.page_size == Some(0) {
+        return Err("Invalid page_size 0, expected at least 1".to_string());
+    }
+
+    if config.resolve_ens && config.rpc_urls.get(&ENS_CHAIN_ID).is_none_or(|urls| urls.is_empty()) {
+        return Err(format!("resolve_ens needs rpc_urls for chain {}", ENS_CHAIN_ID));
+    }
+
+    if config.max_alerts_per_cycle == Some(0) {
+        return Err("Invalid max_alerts_per_cycle 0, expected at least 1".to_string());
+    }
+
+    if let Some(threshold) = config
+        .severity_thresholds
+        .iter()
+        .find(|threshold| threshold.slack_workspace.as_ref().is_some_and(|workspace| !config.slack_workspaces.contains_key(workspace)))
+    {
+        return Err(format!("Severity '{}' uses unknown slack_workspace '{}'", threshold.name, threshold.slack_workspace.as_deref().unwrap_or_default()));
+    }
+
+    if config.escalate_after == Some(0) {
+        return Err("Invalid escalate_after 0, expected at least 1".to_string());
+    }
+
+    if config.stats_log_secs == Some(0) {
+        return Err("Invalid stats_log_secs 0, expected at least 1".to_string());
+    }
+
+    if config.stale_cycles == Some(0) {
+        return Err("Invalid stale_cycles 0, expected at least 1".to_string());
+    }
+
+    if let Some(order_by) = &config.order_by
+        && (order_by.is_empty() || !order_by.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+    {
+        return Err(format!("Invalid order_by '{}', expected a Bid field name", order_by));
+    }
+
+    for window in &config.maintenance_windows {
+        window.times()?;
+    }
+
+    if let Some(template) = &config.alert_template
+        && let Some(unknown) = template_placeholders(template)
+            .into_iter()
+            .find(|name| !ALERT_TEMPLATE_FIELDS.contains(name))
+    {
+        return Err(format!("alert_template uses unknown field '{{{}}}'", unknown));
+    }
+
+    for (token, amount) in &config.min_principal {
+        if parse_token_amount(amount, 0).is_none() {
+            return Err(format!("Invalid min_principal '{}' for token {}", amount, token));
+        }
+    }
+
+    for endpoint in &config.endpoints {
+        if !names.insert(endpoint.name.as_str()) {
+            return Err(format!("Duplicate endpoint name '{}'", endpoint.name));
+        }
+
+        if endpoint.url.trim().is_empty() {
+            return Err(format!("Endpoint '{}' has an empty url", endpoint.name));
+        }
+
+        for url in std::iter::once(&endpoint.url).chain(endpoint.fallback_urls.iter().map(|fallback| &fallback.url)) {
+            if url.trim().is_empty() {
+                return Err(format!("Endpoint '{}' has an empty fallback url", endpoint.name));
+            }
+            if let Err(e) = reqwest::Url::parse(url) {
+                return Err(format!("Endpoint '{}' has an invalid url '{}': {}", endpoint.name, url, e));
+            }
+            if expand_env_vars(url).contains("${") {
+                warn!(endpoint = %endpoint.name, url = %url, "url references an environment variable that is not set");
+            }
+        }
+
+        if endpoint.chain_id <= 0 {
+            return Err(format!("Endpoint '{}' has an invalid chain_id {}", endpoint.name, endpoint.chain_id));
+        }
+
+        if endpoint.disabled && endpoint.enabled == Some(true) {
+            return Err(format!("Endpoint '{}' sets both disabled: true and enabled: Some(true), use enabled only", endpoint.name));
+        }
+
+        if endpoint.statuses.is_empty() {
+            return Err(format!("Endpoint '{}' has no statuses to monitor", endpoint.name));
+        }
+
+        if let Some(liquidation) = &endpoint.liquidation {
+            if liquidation.statuses.is_empty() {
+                return Err(format!("Endpoint '{}' has no liquidation statuses to monitor", endpoint.name));
+            }
+            if let Some(max_ratio) = liquidation.max_collateral_ratio {
+                if max_ratio.is_nan() || max_ratio <= 0.0 {
+                    return Err(format!(
+                        "Endpoint '{}' has an invalid liquidation max_collateral_ratio {}, expected a positive ratio",
+                        endpoint.name, max_ratio
+                    ));
+                }
+                if config.price_feed.is_none() {
+                    return Err(format!("Endpoint '{}' liquidation max_collateral_ratio needs a price_feed", endpoint.name));
+                }
+            }
+        }
+
+        if let Some(workspace) = &endpoint.slack_workspace
+            && !config.slack_workspaces.contains_key(workspace)
+        {
+            return Err(format!("Endpoint '{}' uses unknown slack_workspace '{}'", endpoint.name, workspace));
+        }
+
+        if endpoint.weight == Some(0) {
+            return Err(format!("Endpoint '{}' has weight 0, expected at least 1", endpoint.name));
+        }
+
+        if let Some(id) = endpoint.marketplace_ids.iter().find(|id| id.is_empty() || !id.chars().all(|c| c.is_ascii_digit())) {
+            return Err(format!("Endpoint '{}' has an invalid marketplace id '{}', expected a number", endpoint.name, id));
+        }
+
+        let header_sets = std::iter::once(&endpoint.headers).chain(endpoint.fallback_urls.iter().map(|fallback| &fallback.headers));
+        let auth_styles = std::iter::once(&endpoint.auth_style).chain(endpoint.fallback_urls.iter().map(|fallback| &fallback.auth_style));
+        for style in auth_styles.flatten() {
+            match style {
+                AuthStyle::Bearer => {}
+                AuthStyle::QueryParam { name } if name.is_empty() => {
+                    return Err(format!("Endpoint '{}' has an empty auth_style query parameter name", endpoint.name));
+                }
+                AuthStyle::Header { name } if HeaderName::from_bytes(name.as_bytes()).is_err() => {
+                    return Err(format!("Endpoint '{}' has an invalid auth_style header '{}'", endpoint.name, name));
+                }
+                AuthStyle::QueryParam { .. } | AuthStyle::Header { .. } => {}
+            }
+        }
+        for (name, value) in header_sets.flatten().flatten() {
+            if HeaderName::from_bytes(name.as_bytes()).is_err() || HeaderValue::from_str(value).is_err() {
+                return Err(format!("Endpoint '{}' has an invalid header '{}'", endpoint.name, name));
+            }
+            if expand_env_vars(value).contains("${") {
+                warn!(endpoint = %endpoint.name, header = %name, "header references an environment variable that is not set");
+            }
+        }
+        for key in endpoint.auth_keys() {
+            if env::var(key).is_err() {
+                warn!(endpoint = %endpoint.name, auth_key = %key, "auth_key environment variable is not set");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// How long the endpoint at `position` of `count` waits before its first poll when polls are
+/// staggered, so the endpoints' polls are spread evenly across `interval_secs`.
+fn stagger_offset(interval_secs: u64, position: usize, count: usize) -> Duration {
+    if count == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_secs(interval_secs).mul_f64(position as f64 / count as f64)
+}
+
+async fn monitor_endpoint(ctx: &MonitorContext, endpoint_data: &Endpoint, start_delay: Duration) {
+    let poll_interval_secs = endpoint_data.interval_secs(ctx.default_interval_secs);
+    info!(
+        endpoint = %endpoint_data.name,
+        interval_secs = poll_interval_secs,
+        start_delay_secs = start_delay.as_secs(),
+        "Polling endpoint"
+    );
+
+    let period = Duration::from_secs(poll_interval_secs);
+    let mut next_poll = time::Instant::now() + start_delay;
+    let mut jitter = PollJitter::new(ctx.config.jitter_secs.unwrap_or(0), StdRng::from_entropy());
+    let mut shutdown = ctx.shutdown.subscribe();
+
+    // A poll already under way is allowed to finish so its alerts are recorded before we stop
+    while !*shutdown.borrow() {
+        // Jitter moves single polls only, the schedule itself keeps to the interval
+        tokio::select! {
+            _ = time::sleep_until(jitter.apply(next_poll)) => {}
+            _ = shutdown.changed() => break,
+        }
+        next_poll += period;
+
+        let _permit = tokio::select! {
+            permit = ctx.poll_permits.acquire() => permit.expect("Poll semaphore closed"),
+            _ = shutdown.changed() => break,
+        };
+        ctx.finish_pulse(&endpoint_data.name, &pulse_monitor(ctx, endpoint_data).await);
+    }
+
+    info!(endpoint = %endpoint_data.name, "Stopped polling endpoint");
+}
+
+/// Random shift of each poll around its scheduled time, so replicas started together drift apart
+/// instead of hitting a shared gateway on the same second.
+struct PollJitter {
+    max_ms: u64,
+    rng: StdRng,
+}
+
+impl PollJitter {
+    fn new(max_secs: u64, rng: StdRng) -> Self {
+        Self { max_ms: max_secs.saturating_mul(1000), rng }
+    }
+
+    /// `scheduled` moved by up to `max_secs` earlier or later.
+    fn apply(&mut self, scheduled: time::Instant) -> time::Instant {
+        if self.max_ms == 0 {
+            return scheduled;
+        }
+        let offset_ms = self.rng.gen_range(0..=self.max_ms * 2);
+        let shifted = scheduled + Duration::from_millis(offset_ms);
+        shifted.checked_sub(Duration::from_millis(self.max_ms)).unwrap_or(scheduled)
+    }
+}
+
+/// Totals across one poll of every enabled endpoint.
+#[derive(Debug, Default, PartialEq)]
+struct CycleSummary {
+    polled: usize,
+    /// Polls whose overdue bids query or follow-up checks failed
+    failed: usize,
+    /// Poll tasks that panicked, counted apart from `failed`
+    panicked: usize,
+    bids_found: usize,
+    alerts_sent: usize,
+    alerts_queued: usize,
+}
+
+impl CycleSummary {
+    fn record(&mut self, result: &anyhow::Result<PulseOutcome>) {
+        self.polled += 1;
+        match result {
+            Ok(outcome) => {
+                self.bids_found += outcome.bids_found;
+                self.alerts_sent += outcome.alerts_sent;
+                self.alerts_queued += outcome.alerts_queued;
+                if !outcome.succeeded() {
+                    self.failed += 1;
+                }
+            }
+            Err(_) => self.failed += 1,
+        }
+    }
+
+    fn succeeded(&self) -> bool {
+        self.failed == 0 && self.panicked == 0
+    }
+}
+
+// Smallest query that proves the URL, its auth and the bids schema all work
+const PREFLIGHT_QUERY: &str = "{ bids(first: 1) { id } }";
+
+/// How the startup check of one endpoint URL went, with the time it took when it passed.
+struct PreflightCheck {
+    endpoint: String,
+    url: String,
+    result: Result<Duration, String>,
+}
+
+/// Sends `PREFLIGHT_QUERY` to every URL of every enabled endpoint, fallbacks included, so a
+/// bad URL or auth key shows up at deploy rather than on the endpoint's first poll.
+async fn preflight(ctx: &MonitorContext) -> Vec<PreflightCheck> {
+    let retry = ctx.config.retry_policy();
+    let checks = ctx
+        .config
+        .endpoints
+        .iter()
+        .filter(|endpoint| endpoint.is_enabled())
+        .flat_map(|endpoint| endpoint.sources().into_iter().map(move |source| (endpoint, source)))
+        .map(|(endpoint, source)| async move {
+            let _permit = ctx.poll_permits.acquire().await.expect("Poll semaphore closed");
+            let started = Instant::now();
+            let body = serde_json::json!({ "query": PREFLIGHT_QUERY });
+            let result = post_graphql::<BidsData>(&ctx.client, &source, body, retry)
+                .await
+                .map(|_| started.elapsed())
+                .map_err(|e| e.to_string());
+            PreflightCheck { endpoint: endpoint.name.clone(), url: source.label, result }
+        });
+    join_all(checks).await
+}
+
+/// One aligned `PASS`/`FAIL` line per check.
+fn format_preflight_table(checks: &[PreflightCheck]) -> String {
+    let name_width = checks.iter().map(|check| check.endpoint.len()).max().unwrap_or(0);
+    let url_width = checks.iter().map(|check| check.url.len()).max().unwrap_or(0);
+    checks
+        .iter()
+        .map(|check| {
+            let (status, detail) = match &check.result {
+                Ok(elapsed) => ("PASS", format!("{}ms", elapsed.as_millis())),
+                Err(e) => ("FAIL", e.clone()),
+            };
+            format!("{}  {:name_width$}  {:url_width$}  {}", status, check.endpoint, check.url, detail)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Polls every enabled endpoint at once, each task holding a `poll_permits` permit while it
+/// queries, and logs the totals when all have finished.
+async fn poll_all_endpoints(ctx: &Arc<MonitorContext>) -> CycleSummary {
+    let mut polls = JoinSet::new();
+    let mut names = HashMap::new();
+
+    for (index, endpoint_data) in ctx.config.endpoints.iter().enumerate() {
+        if !endpoint_data.is_enabled() {
+            continue;
+        }
+
+        let ctx = Arc::clone(ctx);
+        let poll = polls.spawn(async move {
+            let endpoint_data = &ctx.config.endpoints[index];
+            let _permit = ctx.poll_permits.acquire().await.expect("Poll semaphore closed");
+            let result = pulse_monitor(&ctx, endpoint_data).await;
+            ctx.finish_pulse(&endpoint_data.name, &result);
+            result
+        });
+        names.insert(poll.id(), endpoint_data.name.clone());
+    }
+
+    let summary = collect_polls(polls, &names).await;
+    info!(
+        endpoints = summary.polled,
+        failed = summary.failed,
+        panicked = summary.panicked,
+        bids_found = summary.bids_found,
+        alerts_sent = summary.alerts_sent,
+        alerts_queued = summary.alerts_queued,
+        "Poll cycle done"
+    );
+    summary
+}
+
+/// Waits for every poll in `polls`. A poll that panics is logged under its endpoint name from
+/// `names` and counted, and the others carry on.
+async fn collect_polls(mut polls: JoinSet<anyhow::Result<PulseOutcome>>, names: &HashMap<task::Id, String>) -> CycleSummary {
+    let mut summary = CycleSummary::default();
+
+    while let Some(joined) = polls.join_next_with_id().await {
+        match joined {
+            Ok((_, result)) => summary.record(&result),
+            Err(e) => {
+                let endpoint = names.get(&e.id()).map_or("unknown", String::as_str);
+                error!(endpoint, error = %e, "Poll task failed");
+                summary.polled += 1;
+                summary.panicked += 1;
+            }
+        }
+    }
+
+    summary
+}
+
+/// `200` with the last poll results, or `503` once no poll has succeeded within `health_stale_secs`.
+async fn health_check(axum::extract::State(ctx): axum::extract::State<Arc<MonitorContext>>) -> impl axum::response::IntoResponse {
+    let stale_after_secs = ctx.health_stale_secs();
+    let health = ctx.health();
+    let status = if health.is_stale(Utc::now().timestamp(), stale_after_secs) {
+        axum::http::StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        axum::http::StatusCode::OK
+    };
+    (status, axum::Json(health.to_json()))
+}
+
+/// Picks the enabled endpoints an `/overdue` command asks about. The text is a chain ID or a
+/// chain name from `chain_names`, in any case.
+fn endpoints_for_command<'a>(config: &'a EndpointConfig, options: &FormatOptions, text: &str) -> Result<Vec<&'a Endpoint>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Usage: `/overdue <chain id or name>`, e.g. `/overdue 137`".to_string());
+    }
+
+    let chain_id = text.parse::<i32>().ok().or_else(|| {
+        options
+            .chain_names
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(text))
+            .map(|(chain_id, _)| *chain_id)
+    });
+    let Some(chain_id) = chain_id else {
+        return Err(format!("Unknown chain `{}`", text));
+    };
+
+    let endpoints: Vec<_> = config
+        .endpoints
+        .iter()
+        .filter(|endpoint| endpoint.is_enabled() && endpoint.chain_id == chain_id)
+        .collect();
+    if endpoints.is_empty() {
+        return Err(format!("No enabled endpoint is monitoring {}", options.chain_label(chain_id)));
+    }
+    Ok(endpoints)
+}
+
+/// Queries `endpoints` as a poll would and renders their overdue bids, after `ignore_borrowers`
+/// and `min_principal`, as alerts. Nothing is sent and the alert state is not touched.
+async fn overdue_report(ctx: &MonitorContext, endpoints: &[&Endpoint]) -> String {
+    let config = &ctx.config;
+    let mut sections = Vec::new();
+
+    for endpoint_data in endpoints {
+        let chain_id = endpoint_data.chain_id;
+        let sources = endpoint_data.sources();
+        let window = DueWindow::new(
+            Utc::now().timestamp(),
+            config.lookback_secs.unwrap_or(DEFAULT_LOOKBACK_SECS),
+            config.grace_secs.unwrap_or(0),
+        );
+
+        let fetched = {
+            let _permit = ctx.poll_permits.acquire().await.expect("Poll semaphore closed");
+            fetch_due_bids_with_fallback(&ctx.client, config, endpoint_data, &sources, window).await
+        };
+        let mut bids = match fetched {
+            Ok((bids, _)) => bids,
+            Err(e) => {
+                warn!(endpoint = %endpoint_data.name, error = %e, "Failed to query endpoint for slash command");
+                sections.push(format!("⚠️ Couldn't query {}: {}", endpoint_data.name, e));
+                continue;
+            }
+        };
+        ctx.track_tokens(chain_id, &mut bids);
+        bids.retain(|bid| is_alertable(config, bid));
+
+        let mut format_options = config.format_options(LinkStyle::Slack, ctx.timezone);
+        let label = format_options.chain_label(chain_id);
+        if bids.is_empty() {
+            sections.push(format!("✅ No overdue loans on {} ({})", label, endpoint_data.name));
+            continue;
+        }
+
+        ctx.price_bids(chain_id, &bids, &mut format_options).await;
+        let now_utc = Utc::now();
+        let timestamp = format_timestamp(now_utc, ctx.timezone);
+        let now = now_utc.timestamp();
+
+        let alerts: Vec<String> = bids
+            .iter()
+            .map(|bid| {
+                let severity = bid_severity(config, bid, bid_threshold(config, bid, now), &format_options);
+                let bucket = OverdueBucket::for_bid(bid, now);
+                format_bid_alert(bid, chain_id, &timestamp, now, &format_options, severity.as_ref(), bucket)
+            })
+            .collect();
+        sections.push(format!(
+            "📋 {} overdue loan(s) on {} ({})\n\n{}",
+            alerts.len(),
+            label,
+            endpoint_data.name,
+            alerts.join("\n\n")
+        ));
+    }
+
+    sections.join("\n\n")
+}
+
+/// Handles the `/overdue <chain>` slash command. Slack wants an answer within 3 seconds, so
+/// the command is acknowledged right away and the report follows on the `response_url`.
+async fn slack_command(
+    axum::extract::State(ctx): axum::extract::State<Arc<MonitorContext>>,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> (axum::http::StatusCode, String) {
+    let Some(signing_secret) = &ctx.slack_signing_secret else {
+        return (axum::http::StatusCode::NOT_FOUND, String::new());
+    };
+    if !slack::verify_signature(signing_secret, &headers, &body, Utc::now().timestamp()) {
+        warn!("Rejected slash command with an invalid signature");
+        return (axum::http::StatusCode::UNAUTHORIZED, String::new());
+    }
+    let command: SlashCommand = match serde_urlencoded::from_bytes(&body) {
+        Ok(command) => command,
+        Err(e) => return (axum::http::StatusCode::BAD_REQUEST, format!("Malformed command: {}", e)),
+    };
+
+    info!(command = %command.command, text = %command.text, user = %command.user_name, "Received slash command");
+    // Errors go straight back in the acknowledgement, only queries wait for the `response_url`
+    if let Err(usage) = endpoints_for_command(&ctx.config, &ctx.format_options(), &command.text) {
+        return (axum::http::StatusCode::OK, usage);
+    }
+
+    let reply_ctx = Arc::clone(&ctx);
+    let text = command.text.clone();
+    tokio::spawn(async move {
+        let endpoints = endpoints_for_command(&reply_ctx.config, &reply_ctx.format_options(), &text).unwrap_or_default();
+        let report = overdue_report(&reply_ctx, &endpoints).await;
+        if let Err(e) = slack::respond(&reply_ctx.client, &command.response_url, &report).await {
+            error!(error = %e, "Failed to post slash command reply");
+        }
+    });
+
+    (axum::http::StatusCode::OK, format!("Looking up overdue loans for `{}`…", command.text.trim()))
+}
+
+/// The bid a snooze button is for, carried as JSON in the button's value.
+#[derive(Debug, Deserialize)]
+struct SnoozeTarget {
+    chain_id: i32,
+    bid_id: String,
+}
+
+/// Handles clicks on the snooze button of Block Kit alerts: the bid's re-alerts are held back
+/// for `snooze_secs` and the alert is updated to say who snoozed it and until when.
+async fn slack_interaction(
+    axum::extract::State(ctx): axum::extract::State<Arc<MonitorContext>>,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> axum::http::StatusCode {
+    let Some(signing_secret) = &ctx.slack_signing_secret else {
+        return axum::http::StatusCode::NOT_FOUND;
+    };
+    let now_utc = Utc::now();
+    if !slack::verify_signature(signing_secret, &headers, &body, now_utc.timestamp()) {
+        warn!("Rejected Slack interaction with an invalid signature");
+        return axum::http::StatusCode::UNAUTHORIZED;
+    }
+    let interaction = match slack::parse_block_actions(&body) {
+        Ok(interaction) => interaction,
+        Err(e) => {
+            warn!(error = %e, "Malformed Slack interaction");
+            return axum::http::StatusCode::BAD_REQUEST;
+        }
+    };
+
+    let Some(action) = interaction.actions.iter().find(|action| action.action_id == SNOOZE_ACTION_ID) else {
+        return axum::http::StatusCode::OK;
+    };
+    let target: SnoozeTarget = match serde_json::from_str(&action.value) {
+        Ok(target) => target,
+        Err(e) => {
+            warn!(value = %action.value, error = %e, "Malformed snooze button value");
+            return axum::http::StatusCode::BAD_REQUEST;
+        }
+    };
+
+    let snooze_secs = ctx.config.snooze_secs.unwrap_or(DEFAULT_SNOOZE_SECS);
+    let until = now_utc + chrono::Duration::seconds(snooze_secs as i64);
+    ctx.store().snooze(&make_bid_key(target.chain_id, &target.bid_id), until.timestamp(), now_utc.timestamp());
+    info!(
+        chain_id = target.chain_id,
+        bid_id = %target.bid_id,
+        user = %interaction.user.username,
+        until = until.timestamp(),
+        "Bid snoozed"
+    );
+
+    // Updating the message can be left to run after Slack has its acknowledgement
+    let note = format!("😴 Snoozed by <@{}> until {}", interaction.user.id, format_timestamp(until, ctx.timezone));
+    let text = format!("{}\n{}", interaction.message["text"].as_str().unwrap_or_default(), note);
+    let blocks = format_snoozed_blocks(&interaction.message["blocks"], &note);
+    let client = ctx.client.clone();
+    tokio::spawn(async move {
+        if let Err(e) = slack::replace_message(&client, &interaction.response_url, &text, &blocks).await {
+            error!(error = %e, "Failed to update snoozed alert");
+        }
+    });
+
+    axum::http::StatusCode::OK
+}
+
+/// Serves `/metrics` and `/healthz` on all interfaces at `port` until shutdown, plus the
+/// Slack slash command at `/slack/commands` and the snooze button at `/slack/interactions`
+/// when a signing secret is set.
+async fn serve_http(port: u16, ctx: Arc<MonitorContext>) {
+    let mut shutdown = ctx.shutdown.subscribe();
+    let app = axum::Router::new()
+        .route(
+            "/metrics",
+            axum::routing::get(|| async { ([(axum::http::header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], metrics().render()) }),
+        )
+        .route("/healthz", axum::routing::get(health_check))
+        .route("/slack/commands", axum::routing::post(slack_command))
+        .route("/slack/interactions", axum::routing::post(slack_interaction))
+        .with_state(ctx);
+
+    let listener = match tokio::net::TcpListener::bind(("0.0.0.0", port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!(port, error = %e, "Failed to bind HTTP server");
+            return;
+        }
+    };
+    info!(port, "Serving /metrics and /healthz");
+
+    let stopped = async move {
+        let _ = shutdown.wait_for(|stopped| *stopped).await;
+    };
+    if let Err(e) = axum::serve(listener, app).with_graceful_shutdown(stopped).await {
+        error!(error = %e, "HTTP server failed");
+    }
+}
+
+/// Chains a hook onto the default panic hook that posts the panic message and location to
+/// `SLACK_CHANNEL`, so a crash in production leaves a breadcrumb rather than only a stderr
+/// backtrace.
+///
+/// The post is best-effort and blocks the panicking thread for at most
+/// `PANIC_ALERT_TIMEOUT_SECS`. It runs on a thread of its own with its own runtime, since the
+/// panic may happen on a runtime thread where blocking on a future would panic again.
+fn install_panic_alert_hook(ctx: Arc<MonitorContext>) {
+    // Set while a panic alert is being sent, so a panic while sending doesn't loop
+    static SENDING: AtomicBool = AtomicBool::new(false);
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        if SENDING.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let location = info.location().map(|location| location.to_string());
+        let message = format_panic(
+            &format_timestamp(Utc::now(), ctx.timezone),
+            &panic_message(info.payload()),
+            location.as_deref(),
+            std::thread::current().name(),
+        );
+        let ctx = Arc::clone(&ctx);
+        let sender = std::thread::spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    error!(error = %e, "Failed to start a runtime for the panic alert");
+                    return;
+                }
+            };
+            let timeout = Duration::from_secs(PANIC_ALERT_TIMEOUT_SECS);
+            let sent = runtime.block_on(time::timeout(timeout, send_slack_warning(ctx.notifier.as_ref(), None, &message)));
+            if sent.is_err() {
+                error!("Timed out sending the panic alert");
+            }
+        });
+        let _ = sender.join();
+        SENDING.store(false, Ordering::SeqCst);
+    }));
+}
+
+/// The text a panic was raised with, which is a `&str` or a `String` for `panic!` and `expect`.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|msg| msg.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string())
+}
+
+/// Resolves on Ctrl-C, or on SIGTERM (e.g. a container stop) where supported.
+async fn wait_for_shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = signal::ctrl_c().await {
+            error!(error = %e, "Failed to listen for Ctrl-C");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                error!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// What one poll of an endpoint found and did.
+#[derive(Debug, Default, PartialEq)]
+struct PulseOutcome {
+    /// Overdue bids the endpoint returned, before ignored borrowers and `min_principal` are applied
+    bids_found: usize,
+    /// Alerts and escalations delivered, queued ones included once they go out, counting each
+    /// bid in a batch
+    alerts_sent: usize,
+    /// New alerts and escalations that couldn't be delivered and wait in the retry queue
+    alerts_queued: usize,
+    /// Follow-up checks that failed after the overdue query succeeded
+    errors: Vec<String>,
+}
+
+impl PulseOutcome {
+    fn succeeded(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+fn log_pulse_result(endpoint_name: &str, result: &anyhow::Result<PulseOutcome>) {
+    match result {
+        Ok(outcome) if outcome.succeeded() => info!(
+            endpoint = endpoint_name,
+            bids_found = outcome.bids_found,
+            alerts_sent = outcome.alerts_sent,
+            alerts_queued = outcome.alerts_queued,
+            "Poll done"
+        ),
+        Ok(outcome) => warn!(
+            endpoint = endpoint_name,
+            bids_found = outcome.bids_found,
+            alerts_sent = outcome.alerts_sent,
+            alerts_queued = outcome.alerts_queued,
+            failed = %outcome.errors.join(", "),
+            "Poll done with errors"
+        ),
+        Err(e) => error!(endpoint = endpoint_name, "Poll failed: {:#}", e),
+    }
+}
+
+/// Runs one poll of the endpoint. Fails if the overdue bids query does; failures of the
+/// follow-up checks are collected in the outcome instead.
+#[tracing::instrument(name = "poll", skip_all, fields(endpoint = %endpoint_data.name, chain_id = endpoint_data.chain_id))]
+async fn pulse_monitor(ctx: &MonitorContext, endpoint_data: &Endpoint) -> anyhow::Result<PulseOutcome> {
+    let config = &ctx.config;
+
+    info!(url = %endpoint_data.url, "Querying endpoint");
+
+    let chain_id = endpoint_data.chain_id;
+    let channel = endpoint_data.slack_channel.as_deref();
+    let sources = endpoint_data.sources();
+
+    let current_timestamp = Utc::now().timestamp();
+    let window = DueWindow::new(
+        current_timestamp,
+        config.lookback_secs.unwrap_or(DEFAULT_LOOKBACK_SECS),
+        config.grace_secs.unwrap_or(0),
+    );
+
+    let resent = retry_queued_alerts(ctx, endpoint_data).await;
+
+    let started = Instant::now();
+    let result = fetch_due_bids_with_fallback(&ctx.client, &ctx.config, endpoint_data, &sources, window).await;
+    let latency = started.elapsed();
+
+    match result {
+        Ok((mut bids, source)) => {
+            info!(url = %source.label, latency_ms = latency.as_millis() as u64, "Successfully queried endpoint");
+            check_latency(ctx, endpoint_data, source, latency).await;
+            check_unchanged_results(ctx, endpoint_data, source, &bids).await;
+            ctx.track_tokens(chain_id, &mut bids);
+            let mut outcome = PulseOutcome { bids_found: bids.len(), alerts_sent: resent, ..PulseOutcome::default() };
+
+            let recovered_failure = ctx.store().record_endpoint_success(&endpoint_data.name);
+            if let Some(failure) = recovered_failure {
+                let now_utc = Utc::now();
+                let message = format_endpoint_recovered(
+                    &endpoint_data.name,
+                    &endpoint_data.url,
+                    &format_timestamp(now_utc, ctx.timezone),
+                    now_utc.timestamp() - failure.since,
+                );
+                let sent = send_alert_about(ctx.endpoint_notifier(endpoint_data), channel, &message, AlertSubject::chain(chain_id)).await.is_ok();
+                ctx.audit.record(&AuditEvent::endpoint(
+                    AuditKind::EndpointRecovered,
+                    now_utc.timestamp(),
+                    chain_id,
+                    &endpoint_data.name,
+                    sent,
+                ));
+            }
+            // Only the overdue query counts, so a poll whose follow-up checks fail is still a success
+            ctx.record_poll(&endpoint_data.name, PollResult::Success);
+            resolve_escalation(ctx, endpoint_data).await;
+
+            let mut format_options = ctx.format_options();
+
+            let bid_count = bids.len();
+            let bids: Vec<_> = bids.into_iter().filter(|bid| !is_listed_borrower(bid, &config.ignore_borrowers)).collect();
+            if bids.len() < bid_count {
+                info!(skipped = bid_count - bids.len(), "Skipped overdue bids from ignored borrowers");
+            }
+
+            // Priority borrowers are alerted on however small the loan
+            let bid_count = bids.len();
+            let bids: Vec<_> = bids
+                .into_iter()
+                .filter(|bid| {
+                    is_listed_borrower(bid, &config.priority_borrowers) || !below_min_principal(bid, &config.min_principal)
+                })
+                .collect();
+            if bids.len() < bid_count {
+                info!(skipped = bid_count - bids.len(), "Skipped overdue bids below min_principal");
+            }
+
+            if bids.is_empty() {
+                info!("No overdue bids found");
+                post_all_clear(ctx, endpoint_data, &format_options).await;
+                check_value_at_risk(ctx, endpoint_data, &bids, &format_options).await;
+            } else if ctx.in_maintenance(true) {
+                // Nothing is recorded, so these are alerted on once the window ends
+                info!(count = bids.len(), "In a maintenance window, overdue alerts held back");
+            } else {
+                info!(count = bids.len(), "Found overdue bids, checking for new alerts");
+                ctx.price_bids(chain_id, &bids, &mut format_options).await;
+                ctx.resolve_ens_names(chain_id, &bids, &mut format_options).await;
+
+                let now_utc: DateTime<Utc> = Utc::now();
+                let timestamp = format_timestamp(now_utc, ctx.timezone);
+                let now = now_utc.timestamp();
+
+                // With `batch_alerts` the chain's alerts are collected per route and sent together after the loop
+                let mut batches: Vec<AlertBatch> = Vec::new();
+                let mut alerts_this_cycle = 0;
+                let mut held_back = 0;
+
+                for bid in &bids {
+                    let Some(bid_id) = bid_identifier(bid) else {
+                        warn!(id = %bid.get("id").unwrap_or(&serde_json::Value::Null), "Skipping overdue bid without a usable bidId");
+                        continue;
+                    };
+                    let principal = bid.get("principal").and_then(|v| v.as_str()).unwrap_or("0");
+                    let bid_key = make_bid_key(chain_id, bid_id);
+
+                    let existing_record = ctx.store().get(&bid_key).cloned();
+                    if existing_record.as_ref().is_some_and(|record| record.is_snoozed(now)) {
+                        debug!(bid_id, "Bid snoozed, skipping");
+                        continue;
+                    }
+                    if ctx.retry_queue().is_queued(chain_id, bid_id) {
+                        debug!(bid_id, "Alert for bid is queued for retry, skipping");
+                        continue;
+                    }
+
+                    let threshold = bid_threshold(config, bid, now);
+                    let severity = bid_severity(config, bid, threshold, &format_options);
+                    let bucket = OverdueBucket::for_bid(bid, now);
+
+                    let kind = if existing_record.is_some() { AuditKind::Escalation } else { AuditKind::Alert };
+                    let (message, blocks) = match &existing_record {
+                        None => (
+                            format_bid_alert(bid, chain_id, &timestamp, now, &format_options, severity.as_ref(), bucket),
+                            config.slack_blocks.then(|| {
+                                format_bid_alert_blocks(bid, chain_id, &timestamp, now, &format_options, severity.as_ref(), bucket)
+                            }),
+                        ),
+                        Some(record) if should_realert(record, principal, now, config.realert_after_secs) => {
+                            info!(bid_id, "Bid still overdue, re-alerting");
+                            (
+                                format_bid_escalation(bid, chain_id, &timestamp, now, &format_options, severity.as_ref(), bucket),
+                                config.slack_blocks.then(|| {
+                                    format_bid_escalation_blocks(bid, chain_id, &timestamp, now, &format_options, severity.as_ref(), bucket)
+                                }),
+                            )
+                        }
+                        Some(_) => {
+                            debug!(bid_id, "Bid already alerted, skipping");
+                            continue;
+                        }
+                    };
+
+                    // Held back bids aren't recorded, so they are alerted on once the flood is over
+                    if config.max_alerts_per_cycle.is_some_and(|max| alerts_this_cycle >= max) {
+                        held_back += 1;
+                        continue;
+                    }
+                    alerts_this_cycle += 1;
+
+                    let severity_name = severity.map(|severity| severity.name);
+                    // A severity with its own channel or workspace takes the alert away from the endpoint's
+                    let channel = threshold.and_then(|threshold| threshold.channel.as_deref()).or(channel);
+                    let workspace = threshold
+                        .and_then(|threshold| threshold.slack_workspace.as_deref())
+                        .or(endpoint_data.slack_workspace.as_deref());
+
+                    if config.batch_alerts {
+                        let batch = match batches.iter().position(|batch| batch.channel == channel && batch.workspace == workspace) {
+                            Some(index) => &mut batches[index],
+                            None => {
+                                batches.push(AlertBatch { channel, workspace, bids: Vec::new(), alerts: Vec::new() });
+                                batches.last_mut().expect("just pushed")
+                            }
+                        };
+                        batch.bids.push(queued_bid(bid_id, principal, kind, severity_name));
+                        batch.alerts.push(message);
+                        continue;
+                    }
+
+                    let notifier = ctx.notifier_for(workspace);
+                    let subject = AlertSubject::bid(chain_id, bid_id, severity_name);
+                    let result = match &blocks {
+                        Some(blocks) => send_slack_blocks(notifier, channel, &message, blocks, subject).await,
+                        None => send_alert_about(notifier, channel, &message, subject).await,
+                    };
+                    let sent = result.is_ok();
+                    ctx.audit.record(&AuditEvent::bid(kind, now, chain_id, bid_id, principal, sent).with_severity(severity_name));
+                    match result {
+                        Ok(()) => {
+                            ctx.store().record(chain_id, bid_id, principal, now);
+                            outcome.alerts_sent += 1;
+                        }
+                        Err(e) => {
+                            let bids = vec![queued_bid(bid_id, principal, kind, severity_name)];
+                            ctx.queue_alert(endpoint_data, workspace, channel, message, bids, &e);
+                            outcome.alerts_queued += 1;
+                        }
+                    }
+                }
+
+                for AlertBatch { channel, workspace, bids: batched_bids, alerts } in batches {
+                    let notifier = ctx.notifier_for(workspace);
+                    let mut unsent = Vec::new();
+                    for message in format_alert_batch(chain_id, &alerts, notifier.max_message_chars()) {
+                        if let Err(e) = send_alert_about(notifier, channel, &message, AlertSubject::chain(chain_id)).await {
+                            unsent.push((message, e));
+                        }
+                    }
+                    let sent = unsent.is_empty();
+
+                    for bid in &batched_bids {
+                        let event = AuditEvent::bid(bid.kind, now, chain_id, &bid.bid_id, &bid.principal, sent);
+                        ctx.audit.record(&event.with_severity(bid.severity.as_deref()));
+                    }
+                    if sent {
+                        let mut store = ctx.store();
+                        for bid in &batched_bids {
+                            store.record(chain_id, &bid.bid_id, &bid.principal, now);
+                        }
+                        outcome.alerts_sent += batched_bids.len();
+                    } else {
+                        outcome.alerts_queued += batched_bids.len();
+                        // Which bids went out in the parts that were sent isn't tracked, so each unsent
+                        // part holds the whole batch and the first to get through records it
+                        for (message, e) in unsent {
+                            ctx.queue_alert(endpoint_data, workspace, channel, message, batched_bids.clone(), &e);
+                        }
+                    }
+                }
+
+                if let Some(max_alerts) = config.max_alerts_per_cycle
+                    && held_back > 0
+                {
+                    warn!(max_alerts, held_back, "Alert cap reached, holding back the remaining bid alerts");
+                    let message = format_alert_cap(
+                        &endpoint_data.name,
+                        &format_options.chain_label(chain_id),
+                        &timestamp,
+                        alerts_this_cycle + held_back,
+                        max_alerts,
+                    );
+                    let _ = send_alert_about(ctx.endpoint_notifier(endpoint_data), channel, &message, AlertSubject::chain(chain_id)).await;
+                }
+
+                check_value_at_risk(ctx, endpoint_data, &bids, &format_options).await;
+            }
+
+            // Follow-up checks stick with the URL that just answered
+            if !check_recovered_bids(ctx, endpoint_data, source, &format_options).await {
+                outcome.errors.push("recovered bids check".to_string());
+            }
+            if !check_due_soon_bids(ctx, endpoint_data, source, &format_options).await {
+                outcome.errors.push("due soon check".to_string());
+            }
+            if !check_liquidation_bids(ctx, endpoint_data, source, &format_options).await {
+                outcome.errors.push("liquidation check".to_string());
+            }
+            if !check_sync_lag(ctx, endpoint_data, source).await {
+                outcome.errors.push("sync lag check".to_string());
+            }
+
+            metrics().overdue_loans.with_label_values(&[&chain_id.to_string()]).set(outcome.bids_found as i64);
+            metrics().alerts_sent.inc_by(outcome.alerts_sent as u64);
+            metrics().alerts_queued.inc_by(outcome.alerts_queued as u64);
+            metrics().endpoint_errors.with_label_values(&[&endpoint_data.name]).inc_by(outcome.errors.len() as u64);
+            Ok(outcome)
+        }
+        Err(e) => {
+            match &e {
+                QueryError::GraphQl { messages, raw } => {
+                    error!(url = %endpoint_data.url, errors = %messages.join("; "), "GraphQL query returned errors");
+                    debug!(response = %raw, "GraphQL error response");
+                }
+                QueryError::Timeout(e) => {
+                    error!(url = %endpoint_data.url, error = %e, "Endpoint query timed out");
+                }
+                QueryError::Transport(e) => {
+                    error!(url = %endpoint_data.url, error = %e, "Failed to query endpoint");
+                    debug!(error = ?e, "Transport error");
+                }
+                QueryError::Decode { error, raw } => {
+                    error!(url = %endpoint_data.url, error = %error, "Endpoint answered with an unexpected response");
+                    debug!(response = %raw, "Unexpected response");
+                }
+                QueryError::Http { status, body } => {
+                    error!(url = %endpoint_data.url, status = %status, "Endpoint answered with an error status");
+                    debug!(response = %body, "Error response");
+                }
+            }
+
+            let now_utc = Utc::now();
+            let now = now_utc.timestamp();
+
+            let failure = ctx.record_failure(&endpoint_data.name, now);
+            match failure {
+                Some(failure) => {
+                    let max_error_chars = config.max_error_chars.unwrap_or(DEFAULT_MAX_ERROR_CHARS);
+                    let message = format_endpoint_failed(
+                        &endpoint_data.name,
+                        &endpoint_data.url,
+                        &format_timestamp(now_utc, ctx.timezone),
+                        &truncate_error(&e.to_string(), max_error_chars),
+                        now - failure.since,
+                    );
+                    let sent = send_alert_about(ctx.endpoint_notifier(endpoint_data), channel, &message, AlertSubject::chain(chain_id)).await.is_ok();
+                    ctx.audit.record(&AuditEvent::endpoint(AuditKind::EndpointFailed, now, chain_id, &endpoint_data.name, sent));
+                }
+                None => info!("Endpoint still failing, alert suppressed"),
+            }
+            let consecutive_failures = ctx.record_poll(&endpoint_data.name, e.poll_result());
+            escalate_failure(ctx, endpoint_data, consecutive_failures, &e).await;
+            metrics().endpoint_errors.with_label_values(&[&endpoint_data.name]).inc();
+            metrics().alerts_sent.inc_by(resent as u64);
+            Err(anyhow::Error::new(e).context(format!("overdue bids query to {} failed", endpoint_data.url)))
+        }
+    }
+}
+
+/// Once the endpoint has failed `escalate_after` polls in a row, posts to `critical_channel`
+/// and pages PagerDuty if set up, once per outage. Held back during a maintenance window, so
+/// an outage that outlasts it escalates when it ends.
+async fn escalate_failure(ctx: &MonitorContext, endpoint_data: &Endpoint, consecutive_failures: u64, error: &QueryError) {
+    let Some(escalate_after) = ctx.config.escalate_after else {
+        return;
+    };
+    if consecutive_failures < u64::from(escalate_after)
+        || lock_or_recover(&ctx.escalated, "escalated endpoints").contains_key(&endpoint_data.name)
+        || ctx.in_maintenance(false)
+    {
+        return;
+    }
+
+    warn!(endpoint = %endpoint_data.name, consecutive_failures, "Endpoint keeps failing, escalating");
+    let now_utc = Utc::now();
+    let max_error_chars = ctx.config.max_error_chars.unwrap_or(DEFAULT_MAX_ERROR_CHARS);
+    let error = truncate_error(&error.to_string(), max_error_chars);
+    let message = format_endpoint_escalated(
+        &endpoint_data.name,
+        &endpoint_data.url,
+        &format_timestamp(now_utc, ctx.timezone),
+        consecutive_failures,
+        &error,
+    );
+    let channel = ctx.config.critical_channel.as_deref().or(endpoint_data.slack_channel.as_deref());
+    let mut delivered =
+        send_alert_about(ctx.endpoint_notifier(endpoint_data), channel, &message, AlertSubject::chain(endpoint_data.chain_id)).await.is_ok();
+    if let Some(pagerduty) = &ctx.pagerduty {
+        let summary = format!("{} failed {} polls in a row: {}", endpoint_data.name, consecutive_failures, error);
+        match pagerduty.trigger(&escalation_dedup_key(&endpoint_data.name), &summary, "health_bot").await {
+            Ok(()) => delivered = true,
+            Err(e) => error!(error = %e, "Failed to page PagerDuty"),
+        }
+    }
+
+    // Left unmarked when nothing went out, so the next failed poll tries again
+    if delivered {
+        lock_or_recover(&ctx.escalated, "escalated endpoints").insert(endpoint_data.name.clone(), now_utc.timestamp());
+    }
+}
+
+/// Posts a recovery to `critical_channel`, and resolves the PagerDuty incident, when the
+/// endpoint answers again after its outage was escalated.
+async fn resolve_escalation(ctx: &MonitorContext, endpoint_data: &Endpoint) {
+    let Some(escalated_at) = lock_or_recover(&ctx.escalated, "escalated endpoints").remove(&endpoint_data.name) else {
+        return;
+    };
+
+    let now_utc = Utc::now();
+    let message = format_endpoint_escalation_resolved(
+        &endpoint_data.name,
+        &endpoint_data.url,
+        &format_timestamp(now_utc, ctx.timezone),
+        now_utc.timestamp() - escalated_at,
+    );
+    let channel = ctx.config.critical_channel.as_deref().or(endpoint_data.slack_channel.as_deref());
+    let _ = send_alert_about(ctx.endpoint_notifier(endpoint_data), channel, &message, AlertSubject::chain(endpoint_data.chain_id)).await;
+    if let Some(pagerduty) = &ctx.pagerduty
+        && let Err(e) = pagerduty.resolve(&escalation_dedup_key(&endpoint_data.name)).await
+    {
+        error!(error = %e, "Failed to resolve PagerDuty incident");
+    }
+}
+
+fn escalation_dedup_key(endpoint_name: &str) -> String {
+    format!("health-bot:endpoint:{}", endpoint_name)
+}
+
+/// The range of `nextDueDate` values a bids query matches: at or after `due_since` and
+/// strictly before `due_before`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DueWindow {
+    due_before: i64,
+    due_since: i64,
+}
+
+impl DueWindow {
+    /// A bid is overdue once its due date is before now minus the grace period, and only bids
+    /// due within the lookback are fetched.
+    fn new(now: i64, lookback_secs: u64, grace_secs: u64) -> Self {
+        Self {
+            due_before: now - grace_secs as i64,
+            due_since: now - lookback_secs as i64,
+        }
+    }
+
+    /// Bids that are not due yet but will be within `warn_before_secs`.
+    fn due_soon(now: i64, warn_before_secs: u64) -> Self {
+        Self {
+            due_before: now + warn_before_secs as i64,
+            due_since: now,
+        }
+    }
+
+    /// Every bid whatever its due date, for checks that go by status and collateral instead.
+    fn unbounded() -> Self {
+        Self {
+            due_before: i64::MAX,
+            due_since: 0,
+        }
+    }
+}
+
+// Parameterized so the window and paging are passed as variables rather than spliced into the query.
+// Endpoints can swap it for another template with `query_template`
+const DUE_BIDS_QUERY: &str = include_str!("../queries/due_bids.graphql");
+
+/// Where `query_template` files are looked up, relative to the working directory.
+const DEFAULT_QUERIES_DIR: &str = "queries";
+
+/// Variables an overdue query template has to use, since the due window and paging depend on them.
+const REQUIRED_QUERY_VARIABLES: [&str; 4] = ["dueBefore", "dueSince", "first", "skip"];
+
+/// Comment lines in an overdue query template replaced by the `lending_tokens` and
+/// `marketplace_ids` variables and `where` conditions.
+const FILTER_VARIABLES_PLACEHOLDER: &str = "# filter variables";
+const FILTERS_PLACEHOLDER: &str = "# filters";
+
+const BIDS_BY_ID_QUERY: &str = r#"
+  query BidsById($bidIds: [BigInt!]!, $first: Int!) {
+    bids(
+      where: {
+        bidId_in: $bidIds
+      }
+      first: $first
+    ) {
+      id
+      bidId
+      nextDueDate
+      borrowerAddress
+      status
+    }
+  }
+"#;
+
+/// An endpoint's optional `lending_tokens` and `marketplace_ids` filters, ANDed into the
+/// overdue query's `where` clause. An empty list doesn't filter.
+#[derive(Debug, Clone, Copy, Default)]
+struct BidFilters<'a> {
+    lending_tokens: &'a [String],
+    marketplace_ids: &'a [String],
+}
+
+impl BidFilters<'_> {
+    fn is_empty(&self) -> bool {
+        self.lending_tokens.is_empty() && self.marketplace_ids.is_empty()
+    }
+
+    /// `template` with a variable and a `where` condition for each filter in use. The
+    /// placeholders are GraphQL comments, so the query is sent as is when nothing is filtered.
+    fn due_bids_query<'q>(&self, template: &'q str) -> Cow<'q, str> {
+        let mut variables = Vec::new();
+        let mut conditions = Vec::new();
+        if !self.lending_tokens.is_empty() {
+            variables.push("$lendingTokens: [String!]!");
+            conditions.push("lendingToken_in: $lendingTokens");
+        }
+        if !self.marketplace_ids.is_empty() {
+            variables.push("$marketplaceIds: [BigInt!]!");
+            conditions.push("marketplaceId_in: $marketplaceIds");
+        }
+        if variables.is_empty() {
+            return Cow::Borrowed(template);
+        }
+        let query = fill_placeholder(template, FILTER_VARIABLES_PLACEHOLDER, &variables);
+        Cow::Owned(fill_placeholder(&query, FILTERS_PLACEHOLDER, &conditions))
+    }
+}
+
+/// Replaces the first line of `query` that is just `placeholder` with `lines`, indented like it.
+fn fill_placeholder(query: &str, placeholder: &str, lines: &[&str]) -> String {
+    let mut filled = false;
+    let mut result: Vec<String> = Vec::new();
+    for line in query.split('\n') {
+        if !filled && line.trim() == placeholder {
+            let indent = &line[..line.len() - line.trim_start().len()];
+            result.extend(lines.iter().map(|replacement| format!("{}{}", indent, replacement)));
+            filled = true;
+        } else {
+            result.push(line.to_string());
+        }
+    }
+    result.join("\n")
+}
+
+/// Names of the `$variables` used anywhere in a GraphQL query.
+fn query_variables(query: &str) -> HashSet<&str> {
+    query
+        .split('$')
+        .skip(1)
+        .filter_map(|rest| {
+            let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            (end > 0).then(|| &rest[..end])
+        })
+        .collect()
+}
+
+/// Checks an overdue query template uses the window and paging variables, and has the filter
+/// placeholders when the endpoint filters by token or marketplace.
+fn check_query_template(name: &str, query: &str, filters: BidFilters) -> Result<(), String> {
+    let variables = query_variables(query);
+    if let Some(missing) = REQUIRED_QUERY_VARIABLES.iter().find(|variable| !variables.contains(*variable)) {
+        return Err(format!("Query template '{}' does not use the ${} variable", name, missing));
+    }
+    let has_placeholder = |placeholder: &str| query.lines().any(|line| line.trim() == placeholder);
+    let has_placeholders = has_placeholder(FILTER_VARIABLES_PLACEHOLDER) && has_placeholder(FILTERS_PLACEHOLDER);
+    if !filters.is_empty() && !has_placeholders {
+        return Err(format!(
+            "Query template '{}' needs '{}' and '{}' lines for the endpoint's lending_tokens and marketplace_ids",
+            name, FILTER_VARIABLES_PLACEHOLDER, FILTERS_PLACEHOLDER
+        ));
+    }
+    Ok(())
+}
+
+/// Reads each endpoint's `query_template` from `dir` into the endpoint, checking it with
+/// `check_query_template` so a bad template stops startup instead of failing every poll.
+fn load_query_templates(config: &mut EndpointConfig, dir: &Path) -> Result<(), String> {
+    for endpoint in &mut config.endpoints {
+        let Some(name) = endpoint.query_template.as_deref() else {
+            continue;
+        };
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(format!("Endpoint '{}' has an invalid query_template '{}', expected a file name", endpoint.name, name));
+        }
+        let file_name = if name.ends_with(".graphql") { name.to_string() } else { format!("{}.graphql", name) };
+        let path = dir.join(file_name);
+        let query = fs::read_to_string(&path).map_err(|e| format!("Failed to read query template {}: {}", path.display(), e))?;
+        check_query_template(name, &query, endpoint.bid_filters())?;
+        info!(endpoint = %endpoint.name, template = %path.display(), "Using query template");
+        endpoint.query = Some(query);
+    }
+    Ok(())
+}
+
+/// Request body for one page of the overdue query. `BigInt` variables are sent as strings.
+fn build_due_query(
+    template: &str,
+    window: DueWindow,
+    statuses: &[String],
+    filters: BidFilters,
+    order: BidOrder,
+    first: usize,
+    skip: usize,
+) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "query": filters.due_bids_query(template),
+        "variables": {
+            "dueBefore": window.due_before.to_string(),
+            "dueSince": window.due_since.to_string(),
+            "statuses": statuses,
+            "orderBy": order.by,
+            "orderDirection": order.direction.as_str(),
+            "first": first,
+            "skip": skip
+        }
+    });
+    if !filters.lending_tokens.is_empty() {
+        // Token entity IDs are lowercase addresses
+        let tokens: Vec<String> = filters.lending_tokens.iter().map(|token| token.trim().to_lowercase()).collect();
+        body["variables"]["lendingTokens"] = serde_json::json!(tokens);
+    }
+    if !filters.marketplace_ids.is_empty() {
+        body["variables"]["marketplaceIds"] = serde_json::json!(filters.marketplace_ids);
+    }
+    body
+}
+
+/// Fetches the bids due in the window from the first of `sources` that answers, returning
+/// the bids along with the source that served them, or the last source's error.
+async fn fetch_due_bids_with_fallback<'a>(
+    client: &reqwest::Client,
+    config: &EndpointConfig,
+    endpoint_data: &Endpoint,
+    sources: &'a [Source],
+    window: DueWindow,
+) -> Result<(Vec<serde_json::Value>, &'a Source), QueryError> {
+    let mut last_error = None;
+    for (index, source) in sources.iter().enumerate() {
+        match fetch_due_bids(client, config, endpoint_data, source, window, &endpoint_data.statuses).await {
+            Ok(bids) => {
+                if index > 0 {
+                    warn!(url = %source.label, "Served by fallback url");
+                }
+                return Ok((bids, source));
+            }
+            Err(e) => {
+                if index + 1 < sources.len() {
+                    warn!(url = %source.label, error = %e, "Query failed, trying the next url");
+                }
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error.expect("an endpoint always has its primary url"))
+}
+
+/// Pages through every bid due in the window, stopping at the first short page or after
+/// `paging.max_pages` pages.
+async fn fetch_due_bids(
+    client: &reqwest::Client,
+    config: &EndpointConfig,
+    endpoint_data: &Endpoint,
+    source: &Source,
+    window: DueWindow,
+    statuses: &[String],
+) -> Result<Vec<serde_json::Value>, QueryError> {
+    let Paging { page_size, max_pages } = config.paging();
+    let (retry, order) = (config.retry_policy(), config.bid_order());
+    let mut all_bids = Vec::new();
+    // Bids tied on the sort field can shift between pages, so one may come back twice
+    let mut seen = HashSet::new();
+
+    for page in 0..max_pages {
+        let filters = endpoint_data.bid_filters();
+        let body = build_due_query(endpoint_data.due_query(), window, statuses, filters, order, page_size, page * page_size);
+
+        debug!(body = %body, "Query body");
+
+        let bids = post_graphql::<BidsData>(client, source, body, retry).await?.bids;
+        let page_len = bids.len();
+        all_bids.extend(bids.into_iter().filter(|bid| match bid.get("id").and_then(|v| v.as_str()) {
+            Some(id) => seen.insert(id.to_string()),
+            None => true,
+        }));
+
+        if page_len < page_size {
+            return Ok(all_bids);
+        }
+    }
+
+    warn!(
+        endpoint = %endpoint_data.name,
+        max_pages,
+        "Stopped paging, there may be more bids in the window"
+    );
+    Ok(all_bids)
+}
+
+#[derive(Debug)]
+enum QueryError {
+    /// The request timed out, on its last retry
+    Timeout(reqwest::Error),
+    /// The request itself failed (connection, TLS, redirect)
+    Transport(reqwest::Error),
+    /// The endpoint answered with a non-2xx status
+    Http { status: reqwest::StatusCode, body: String },
+    /// The endpoint answered with a GraphQL `errors` field
+    GraphQl {
+        /// One line per error, see `graphql_error_messages`
+        messages: Vec<String>,
+        /// The whole response body, for debug logs only
+        raw: String,
+    },
+    /// The response wasn't JSON, or its `data` didn't have the shape asked for
+    Decode {
+        error: String,
+        /// The whole response body, for debug logs only
+        raw: String,
+    },
+}
+
+impl QueryError {
+    /// How the failed poll counts in its endpoint's stats.
+    fn poll_result(&self) -> PollResult {
+        match self {
+            QueryError::GraphQl { .. } | QueryError::Decode { .. } => PollResult::GraphQlError,
+            QueryError::Timeout(_) | QueryError::Transport(_) | QueryError::Http { .. } => PollResult::TransportError,
+        }
+    }
+}
+
+impl std::fmt::Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryError::Timeout(e) => write!(f, "Request timed out: {}", e),
+            QueryError::Transport(e) => write!(f, "{}", e),
+            QueryError::Http { status, body } if body.trim().is_empty() => write!(f, "HTTP {}", status),
+            QueryError::Http { status, body } => write!(f, "HTTP {}: {}", status, body.trim()),
+            QueryError::GraphQl { messages, .. } => {
+                write!(f, "GraphQL errors:")?;
+                for message in messages {
+                    write!(f, "\n• {}", message)?;
+                }
+                Ok(())
+            }
+            QueryError::Decode { error, .. } => write!(f, "Invalid response: {}", error),
+        }
+    }
+}
+
+/// Each entry of a GraphQL `errors` array as `message (line 3, column 7; path bids.0.collateral)`,
+/// leaving out `locations` and `path` when the server didn't send them.
+fn graphql_error_messages(errors: &serde_json::Value) -> Vec<String> {
+    let Some(errors) = errors.as_array() else {
+        return vec![errors.to_string()];
+    };
+
+    errors
+        .iter()
+        .map(|error| {
+            let message = match error.get("message").and_then(|v| v.as_str()) {
+                Some(message) => message.to_string(),
+                None => error.to_string(),
+            };
+
+            let mut details = Vec::new();
+            let locations = error.get("locations").and_then(|v| v.as_array()).into_iter().flatten();
+            for location in locations {
+                if let (Some(line), Some(column)) = (location["line"].as_u64(), location["column"].as_u64()) {
+                    details.push(format!("line {}, column {}", line, column));
+                }
+            }
+            if let Some(path) = error.get("path").and_then(|v| v.as_array()).filter(|path| !path.is_empty()) {
+                let segments: Vec<String> = path
+                    .iter()
+                    .map(|segment| match segment.as_str() {
+                        Some(field) => field.to_string(),
+                        None => segment.to_string(),
+                    })
+                    .collect();
+                details.push(format!("path {}", segments.join(".")));
+            }
+
+            if details.is_empty() { message } else { format!("{} ({})", message, details.join("; ")) }
+        })
+        .collect()
+}
+
+impl std::error::Error for QueryError {}
+
+/// Posts a GraphQL query (with retries) and returns the parsed response, treating an
+/// `errors` field as a failure.
+async fn run_graphql_query(
+    client: &reqwest::Client,
+    source: &Source,
+    body: serde_json::Value,
+    retry: RetryPolicy,
+) -> Result<serde_json::Value, QueryError> {
+    let timer = metrics().graphql_request_duration.start_timer();
+    let response = make_post_request_with_retry(
+        client,
+        &source.url,
+        body,
+        source.auth_token.as_deref(),
+        &source.headers,
+        retry,
+    )
+    .await;
+    timer.observe_duration();
+    // The URL may carry an expanded API key, callers log the configured one instead
+    let response = response.map_err(|e| {
+        if e.is_timeout() { QueryError::Timeout(e.without_url()) } else { QueryError::Transport(e.without_url()) }
+    })?;
+    if !response.status.is_success() {
+        return Err(QueryError::Http { status: response.status, body: response.body });
+    }
+
+    let json_response: serde_json::Value = match serde_json::from_str(&response.body) {
+        Ok(json) => json,
+        Err(e) => return Err(QueryError::Decode { error: e.to_string(), raw: response.body }),
+    };
+    if let Some(errors) = json_response.get("errors") {
+        return Err(QueryError::GraphQl { messages: graphql_error_messages(errors), raw: response.body });
+    }
+
+    Ok(json_response)
+}
+
+/// `run_graphql_query` with the response's `data` deserialized into `T`, so a response
+/// missing the fields asked for is an error rather than an empty result.
+async fn post_graphql<T: DeserializeOwned>(
+    client: &reqwest::Client,
+    source: &Source,
+    body: serde_json::Value,
+    retry: RetryPolicy,
+) -> Result<T, QueryError> {
+    let json_response = run_graphql_query(client, source, body, retry).await?;
+    T::deserialize(&json_response["data"]).map_err(|e| QueryError::Decode { error: e.to_string(), raw: json_response.to_string() })
+}
+
+/// `data` of a query for `bids`.
+#[derive(Debug, Deserialize)]
+struct BidsData {
+    bids: Vec<serde_json::Value>,
+}
+
+/// `data` of a `{ _meta { block { number } } }` query.
+#[derive(Debug, Deserialize)]
+struct MetaData {
+    #[serde(rename = "_meta")]
+    meta: Meta,
+}
+
+#[derive(Debug, Deserialize)]
+struct Meta {
+    block: MetaBlock,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetaBlock {
+    number: u64,
+}
+
+/// Sends a pre-warning for bids coming due within `warn_before_secs`, once per bid and due
+/// date so each installment is warned about separately from its overdue alert.
+///
+/// Returns `false` if the query failed.
+async fn check_due_soon_bids(
+    ctx: &MonitorContext,
+    endpoint_data: &Endpoint,
+    source: &Source,
+    format_options: &FormatOptions,
+) -> bool {
+    let Some(warn_before_secs) = ctx.config.warn_before_secs else {
+        return true;
+    };
+    if ctx.in_maintenance(true) {
+        return true;
+    }
+    let chain_id = endpoint_data.chain_id;
+    let channel = endpoint_data.slack_channel.as_deref();
+
+    let now_utc = Utc::now();
+    let now = now_utc.timestamp();
+    let timestamp = format_timestamp(now_utc, ctx.timezone);
+
+    // Warnings for due dates that have passed are no longer needed to dedup anything
+    {
+        let mut store = ctx.store();
+        let expired = store.expired_due_soon_keys(chain_id, now);
+        if !expired.is_empty() {
+            store.remove(&expired);
+        }
+    }
+
+    let window = DueWindow::due_soon(now, warn_before_secs);
+    let mut bids = match fetch_due_bids(&ctx.client, &ctx.config, endpoint_data, source, window, &endpoint_data.statuses).await {
+        Ok(bids) => bids,
+        Err(e) => {
+            error!(url = %source.label, error = %e, "Failed to query due soon bids");
+            return false;
+        }
+    };
+    ctx.track_tokens(chain_id, &mut bids);
+
+    let config = &ctx.config;
+    let pending: Vec<_> = {
+        let store = ctx.store();
+        bids
+            .into_iter()
+            .filter(|bid| is_alertable(config, bid))
+            .filter_map(|bid| {
+                let bid_id = bid_identifier(&bid)?;
+                let key = make_due_soon_key(chain_id, bid_id, bid_next_due(&bid)?);
+                store.get(&key).is_none().then_some((key, bid))
+            })
+            .collect()
+    };
+
+    if pending.is_empty() {
+        return true;
+    }
+
+    let mut format_options = format_options.clone();
+    ctx.price_bids(chain_id, pending.iter().map(|(_, bid)| bid), &mut format_options).await;
+
+    for (key, bid) in pending {
+        let bid_id = bid_identifier(&bid).unwrap_or_default();
+        let principal = bid.get("principal").and_then(|v| v.as_str()).unwrap_or("0");
+
+        info!(bid_id, "Bid is due soon, sending pre-warning");
+        let message = format_bid_due_soon(&bid, chain_id, &timestamp, now, &format_options);
+        let sent = send_alert_about(ctx.endpoint_notifier(endpoint_data), channel, &message, AlertSubject::bid(chain_id, bid_id, None)).await.is_ok();
+        ctx.audit.record(&AuditEvent::bid(AuditKind::DueSoon, now, chain_id, bid_id, principal, sent));
+        if sent {
+            ctx.store().record_key(key, principal, now);
+        } else {
+            warn!(bid_id, "Due soon warning not delivered, sending it again next poll");
+        }
+    }
+
+    true
+}
+
+/// Sends a liquidation opportunity alert for each of the endpoint's bids its `liquidation`
+/// config counts as liquidatable, once per bid and apart from the bid's overdue alert. The
+/// alert is forgotten once the bid closes or stops being liquidatable.
+///
+/// Returns `false` if the query failed.
+async fn check_liquidation_bids(
+    ctx: &MonitorContext,
+    endpoint_data: &Endpoint,
+    source: &Source,
+    format_options: &FormatOptions,
+) -> bool {
+    let Some(liquidation) = &endpoint_data.liquidation else {
+        return true;
+    };
+    if ctx.in_maintenance(true) {
+        return true;
+    }
+    let config = &ctx.config;
+    let chain_id = endpoint_data.chain_id;
+    let channel = endpoint_data.slack_channel.as_deref();
+
+    let now_utc = Utc::now();
+    let now = now_utc.timestamp();
+    let timestamp = format_timestamp(now_utc, ctx.timezone);
+
+    // A bid can be liquidated however long ago it fell due, or before it's due at all when
+    // undercollateralized, so the due date doesn't narrow the query
+    let statuses = liquidation.query_statuses(&endpoint_data.statuses);
+    let mut bids = match fetch_due_bids(&ctx.client, config, endpoint_data, source, DueWindow::unbounded(), &statuses).await {
+        Ok(bids) => bids,
+        Err(e) => {
+            error!(url = %source.label, error = %e, "Failed to query liquidatable bids");
+            return false;
+        }
+    };
+    ctx.track_tokens(chain_id, &mut bids);
+    let bids: Vec<_> = bids.into_iter().filter(|bid| is_alertable(config, bid)).collect();
+
+    // Every candidate is priced, alerted or not, since the collateral ratio decides whether
+    // an earlier alert still stands
+    let mut format_options = format_options.clone();
+    ctx.price_bids(chain_id, &bids, &mut format_options).await;
+    let mut liquidatable = Vec::new();
+    let mut current = HashSet::new();
+    for bid in bids {
+        let Some(key) = bid_identifier(&bid).map(|bid_id| make_liquidation_key(chain_id, bid_id)) else {
+            continue;
+        };
+        if liquidation.is_liquidatable(&bid, &format_options) {
+            current.insert(key.clone());
+            liquidatable.push((key, bid));
+        } else if liquidation.max_collateral_ratio.is_some() && collateral_ratio(&bid, &format_options).is_none() {
+            // Without a price the ratio is unknown, which isn't reason enough to alert again later
+            current.insert(key);
+        }
+    }
+
+    // A bid that closed or is no longer liquidatable loses its key, so it's alerted on again if
+    // it becomes liquidatable later
+    let pending: Vec<_> = {
+        let mut store = ctx.store();
+        let stale: HashSet<String> = store.liquidation_keys_for_chain(chain_id).difference(&current).cloned().collect();
+        if !stale.is_empty() {
+            info!(count = stale.len(), "Bids no longer liquidatable, clearing their liquidation alerts");
+            store.remove(&stale);
+        }
+        liquidatable.into_iter().filter(|(key, _)| store.get(key).is_none()).collect()
+    };
+
+    for (key, bid) in pending {
+        let bid_id = bid_identifier(&bid).unwrap_or_default();
+        let principal = bid.get("principal").and_then(|v| v.as_str()).unwrap_or("0");
+
+        info!(bid_id, "Bid can be liquidated, sending liquidation opportunity");
+        let message = format_liquidation_opportunity(&bid, chain_id, &timestamp, now, &format_options);
+        let sent = send_alert_about(ctx.endpoint_notifier(endpoint_data), channel, &message, AlertSubject::bid(chain_id, bid_id, None)).await.is_ok();
+        ctx.audit.record(&AuditEvent::bid(AuditKind::Liquidation, now, chain_id, bid_id, principal, sent));
+        if sent {
+            ctx.store().record_key(key, principal, now);
+        } else {
+            warn!(bid_id, "Liquidation opportunity not delivered, sending it again next poll");
+        }
+    }
+
+    true
+}
+
+/// Block number the subgraph has indexed up to, read from its `_meta` field.
+async fn get_subgraph_block(client: &reqwest::Client, source: &Source, retry: RetryPolicy) -> Result<u64, String> {
+    let body = serde_json::json!({
+        "query": "{ _meta { block { number } } }"
+    });
+
+    let data: MetaData = post_graphql(client, source, body, retry).await.map_err(|e| e.to_string())?;
+    Ok(data.meta.block.number)
+}
+
+fn parse_block_number(hex: &str) -> Option<u64> {
+    u64::from_str_radix(hex.strip_prefix("0x")?, 16).ok()
+}
+
+fn block_number_from_rpc_response(response: &serde_json::Value) -> Result<u64, String> {
+    if let Some(error) = response.get("error") {
+        return Err(format!("RPC error: {}", error));
+    }
+
+    response
+        .get("result")
+        .and_then(|result| result.as_str())
+        .and_then(parse_block_number)
+        .ok_or_else(|| format!("Invalid eth_blockNumber response: {}", response))
+}
+
+/// Latest block on the chain according to the JSON-RPC node at `rpc_url`.
+async fn get_block_number(client: &reqwest::Client, rpc_url: &str, retry: RetryPolicy) -> Result<u64, String> {
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "eth_blockNumber",
+        "params": [],
+        "id": 1
+    });
+
+    // The URL is left out of errors because it may contain the provider's API key
+    let response = make_post_request_with_retry(client, rpc_url, body, None, &HeaderMap::new(), retry)
+        .await
+        .map_err(|e| e.without_url().to_string())?;
+    if !response.status.is_success() {
+        return Err(format!("HTTP {}", response.status));
+    }
+    let json_response: serde_json::Value = serde_json::from_str(&response.body).map_err(|e| e.to_string())?;
+    block_number_from_rpc_response(&json_response)
+}
+
+/// Names an RPC provider or GraphQL URL by host only, since URLs often carry an API key in the path.
+fn rpc_label(rpc_url: &str) -> String {
+    reqwest::Url::parse(rpc_url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_string()))
+        .unwrap_or_else(|| "invalid url".to_string())
+}
+
+/// Latest block on the chain, asking each of its RPC providers in order until one answers.
+async fn get_chain_head(client: &reqwest::Client, chain_id: i32, rpc_urls: &[String], retry: RetryPolicy) -> Result<u64, String> {
+    let mut errors = Vec::new();
+
+    for rpc_url in rpc_urls {
+        match get_block_number(client, rpc_url, retry).await {
+            Ok(block) => return Ok(block),
+            Err(e) => {
+                warn!(provider = %rpc_label(rpc_url), chain_id, error = %e, "RPC provider failed");
+                errors.push(format!("{}: {}", rpc_label(rpc_url), e));
+            }
+        }
+    }
+
+    Err(format!("All {} RPC provider(s) failed: {}", rpc_urls.len(), errors.join("; ")))
+}
+
+/// Key the chain head lookup state of a chain is tracked under in the store.
+fn chain_head_key(chain_id: i32) -> String {
+    format!("chain {} (rpc)", chain_id)
+}
+
+/// Key the sync-lag state of an endpoint is tracked under in the store, next to its failure state.
+fn sync_lag_key(endpoint_name: &str) -> String {
+    format!("{} (sync lag)", endpoint_name)
+}
+
+/// Key a chain's value at risk alert state is tracked under in the store, shared by every
+/// endpoint on the chain so they don't each alert.
+fn value_at_risk_key(chain_id: i32) -> String {
+    format!("chain {} (value at risk)", chain_id)
+}
+
+/// Alerts when the overdue principal on the endpoint's chain, valued with the priced `bids`,
+/// is over its `value_at_risk_thresholds` entry. Repeats are held back for
+/// `value_at_risk_realert_secs` and the state is cleared quietly once it is back under.
+async fn check_value_at_risk(ctx: &MonitorContext, endpoint_data: &Endpoint, bids: &[serde_json::Value], format_options: &FormatOptions) {
+    let chain_id = endpoint_data.chain_id;
+    let Some(&threshold) = ctx.config.value_at_risk_thresholds.get(&chain_id) else {
+        return;
+    };
+    let key = value_at_risk_key(chain_id);
+    let risk = value_at_risk(bids, format_options);
+
+    if risk.total <= threshold {
+        if ctx.store().record_endpoint_success(&key).is_some() {
+            info!(total = risk.total, threshold, "Value at risk back under threshold");
+        }
+        return;
+    }
+
+    let now_utc = Utc::now();
+    let now = now_utc.timestamp();
+    let realert_after_secs = ctx.config.value_at_risk_realert_secs.unwrap_or(DEFAULT_VALUE_AT_RISK_REALERT_SECS);
+    if ctx.store().record_endpoint_failure(&key, now, realert_after_secs).is_none() {
+        info!(total = risk.total, threshold, "Value at risk still over threshold, alert suppressed");
+        return;
+    }
+
+    let message = format_value_at_risk(chain_id, &format_timestamp(now_utc, ctx.timezone), threshold, &risk, format_options);
+    let _ = send_alert_about(ctx.endpoint_notifier(endpoint_data), endpoint_data.slack_channel.as_deref(), &message, AlertSubject::chain(chain_id)).await;
+}
+
+/// Posts `post_all_clear`'s message for the endpoint's chain, unless one went out less than a
+/// poll interval ago (from this or another endpoint on the chain). The interval is the
+/// endpoint's unweighted one, less `jitter_secs` since a jittered poll can come early.
+async fn post_all_clear(ctx: &MonitorContext, endpoint_data: &Endpoint, format_options: &FormatOptions) {
+    if !ctx.config.post_all_clear {
+        return;
+    }
+    let chain_id = endpoint_data.chain_id;
+    let now_utc = Utc::now();
+    let now = now_utc.timestamp();
+    let min_gap_secs = endpoint_data
+        .poll_interval_secs
+        .unwrap_or(ctx.default_interval_secs)
+        .saturating_sub(ctx.config.jitter_secs.unwrap_or(0));
+
+    let last_sent = lock_or_recover(&ctx.all_clear_sent, "all clear times").get(&chain_id).copied();
+    if last_sent.is_some_and(|last_sent| now - last_sent < min_gap_secs as i64) {
+        debug!(chain_id, "All clear already posted this interval");
+        return;
+    }
+
+    let message = format_all_clear(&format_options.chain_label(chain_id), &format_timestamp(now_utc, ctx.timezone));
+    let channel = ctx.config.all_clear_channel.as_deref().or(endpoint_data.slack_channel.as_deref());
+    if send_alert_about(ctx.endpoint_notifier(endpoint_data), channel, &message, AlertSubject::chain(chain_id)).await.is_ok() {
+        lock_or_recover(&ctx.all_clear_sent, "all clear times").insert(chain_id, now);
+    }
+}
+
+/// Key the latency state of an endpoint is tracked under in the store, next to its failure state.
+fn latency_key(endpoint_name: &str) -> String {
+    format!("{} (latency)", endpoint_name)
+}
+
+/// Records how long the overdue query took and warns when it is over `latency_warn_ms`, as
+/// early warning of a degraded gateway that still answers.
+///
+/// Slow warnings share the endpoint failure cooldown, and a notice is sent once the endpoint
+/// is back under the limit.
+async fn check_latency(ctx: &MonitorContext, endpoint_data: &Endpoint, source: &Source, latency: Duration) {
+    let latency_ms = latency.as_millis() as u64;
+    metrics()
+        .endpoint_latency
+        .with_label_values(&[endpoint_data.name.as_str()])
+        .set(latency.as_secs_f64());
+
+    let Some(latency_warn_ms) = ctx.config.latency_warn_ms else {
+        return;
+    };
+    let channel = endpoint_data.slack_channel.as_deref();
+    let now_utc = Utc::now();
+    let now = now_utc.timestamp();
+    let timestamp = format_timestamp(now_utc, ctx.timezone);
+    let key = latency_key(&endpoint_data.name);
+
+    if latency_ms > latency_warn_ms {
+        let failure = ctx.record_failure(&key, now);
+        match failure {
+            Some(failure) => {
+                let message = format_endpoint_slow(
+                    &endpoint_data.name,
+                    &source.label,
+                    &timestamp,
+                    latency_ms,
+                    latency_warn_ms,
+                    now - failure.since,
+                );
+                let _ = send_slack_warning(ctx.endpoint_notifier(endpoint_data), channel, &message).await;
+            }
+            None => info!(latency_ms, "Endpoint still slow, warning suppressed"),
+        }
+    } else {
+        let recovered = ctx.store().record_endpoint_success(&key);
+        if let Some(failure) = recovered {
+            let message = format_endpoint_fast(&endpoint_data.name, &source.label, &timestamp, latency_ms, now - failure.since);
+            let _ = send_slack_warning(ctx.endpoint_notifier(endpoint_data), channel, &message).await;
+        }
+    }
+}
+
+/// The endpoint's last overdue bids fingerprint and how many polls since have matched it.
+struct UnchangedResults {
+    fingerprint: u64,
+    polls: u32,
+}
+
+/// Hash of the sorted bid IDs, or `None` when there are no bids.
+fn bid_set_fingerprint(bids: &[serde_json::Value]) -> Option<u64> {
+    let mut bid_ids: Vec<&str> = bids.iter().filter_map(bid_identifier).collect();
+    if bid_ids.is_empty() {
+        return None;
+    }
+    bid_ids.sort_unstable();
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    bid_ids.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// Key the unchanged results state of an endpoint is tracked under in the store, next to its failure state.
+fn unchanged_results_key(endpoint_name: &str) -> String {
+    format!("{} (unchanged results)", endpoint_name)
+}
+
+/// Warns when the endpoint has returned the same overdue bids for more than `stale_cycles`
+/// polls, a cheap sign of a frozen subgraph on gateways that don't expose `_meta` for
+/// `max_block_lag`.
+///
+/// Warnings share the endpoint failure cooldown, and a notice is sent once the results change.
+async fn check_unchanged_results(ctx: &MonitorContext, endpoint_data: &Endpoint, source: &Source, bids: &[serde_json::Value]) {
+    let Some(stale_cycles) = ctx.config.stale_cycles else {
+        return;
+    };
+    let unchanged_polls = ctx.unchanged_polls(&endpoint_data.name, bids);
+    let channel = endpoint_data.slack_channel.as_deref();
+    let now_utc = Utc::now();
+    let now = now_utc.timestamp();
+    let timestamp = format_timestamp(now_utc, ctx.timezone);
+    let key = unchanged_results_key(&endpoint_data.name);
+
+    if unchanged_polls > stale_cycles {
+        let failure = ctx.record_failure(&key, now);
+        match failure {
+            Some(_) => {
+                let message = format_results_unchanged(&endpoint_data.name, &source.label, &timestamp, unchanged_polls, bids.len());
+                let _ = send_slack_warning(ctx.endpoint_notifier(endpoint_data), channel, &message).await;
+            }
+            None => info!(unchanged_polls, "Endpoint results still unchanged, warning suppressed"),
+        }
+    } else if unchanged_polls == 0 {
+        let changed = ctx.store().record_endpoint_success(&key);
+        if let Some(failure) = changed {
+            let message = format_results_changed(&endpoint_data.name, &source.label, &timestamp, now - failure.since);
+            let _ = send_slack_warning(ctx.endpoint_notifier(endpoint_data), channel, &message).await;
+        }
+    }
+}
+
+/// Compares the block the subgraph has indexed with the chain head and alerts when it has
+/// fallen more than `max_block_lag` blocks behind, since a stalled subgraph hides overdue
+/// loans behind an all-clear.
+///
+/// Lag alerts share the endpoint failure cooldown. Returns `false` if either block number
+/// could not be fetched.
+async fn check_sync_lag(ctx: &MonitorContext, endpoint_data: &Endpoint, source: &Source) -> bool {
+    let Some(max_block_lag) = ctx.config.max_block_lag else {
+        return true;
+    };
+    let Some(rpc_urls) = ctx.config.rpc_urls.get(&endpoint_data.chain_id).filter(|urls| !urls.is_empty()) else {
+        return true;
+    };
+    let retry = ctx.config.retry_policy();
+    let channel = endpoint_data.slack_channel.as_deref();
+
+    let indexed_block = match get_subgraph_block(&ctx.client, source, retry).await {
+        Ok(block) => block,
+        Err(e) => {
+            error!(url = %source.label, error = %e, "Failed to read indexed block");
+            return false;
+        }
+    };
+    let chain_id = endpoint_data.chain_id;
+    let chain_head_result = get_chain_head(&ctx.client, chain_id, rpc_urls, retry).await;
+
+    let now_utc = Utc::now();
+    let now = now_utc.timestamp();
+    let timestamp = format_timestamp(now_utc, ctx.timezone);
+
+    // Without a chain head the lag is unknown, which is itself worth an alert
+    let chain_head = match chain_head_result {
+        Ok(block) => {
+            let recovered = ctx.store().record_endpoint_success(&chain_head_key(chain_id));
+            if let Some(failure) = recovered {
+                let message = format_rpc_recovered(chain_id, &timestamp, now - failure.since);
+                let _ = send_slack_warning(ctx.endpoint_notifier(endpoint_data), channel, &message).await;
+            }
+            block
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to read chain head");
+            let failure = ctx.record_failure(&chain_head_key(chain_id), now);
+            if let Some(failure) = failure {
+                let message = format_rpc_failed(chain_id, &timestamp, &e, now - failure.since);
+                let _ = send_slack_warning(ctx.endpoint_notifier(endpoint_data), channel, &message).await;
+            }
+            return false;
+        }
+    };
+
+    let lag = chain_head.saturating_sub(indexed_block);
+    info!(indexed_block, chain_head, lag, "Checked subgraph sync");
+
+    let key = sync_lag_key(&endpoint_data.name);
+
+    if lag > max_block_lag {
+        let failure = ctx.record_failure(&key, now);
+        match failure {
+            Some(failure) => {
+                let message = format_sync_lag(
+                    &endpoint_data.name,
+                    &endpoint_data.url,
+                    &timestamp,
+                    indexed_block,
+                    chain_head,
+                    now - failure.since,
+                );
+                let _ = send_slack_warning(ctx.endpoint_notifier(endpoint_data), channel, &message).await;
+            }
+            None => info!("Endpoint still lagging, alert suppressed"),
+        }
+    } else {
+        let caught_up = ctx.store().record_endpoint_success(&key);
+        if let Some(failure) = caught_up {
+            let message = format_sync_caught_up(
+                &endpoint_data.name,
+                &endpoint_data.url,
+                &timestamp,
+                indexed_block,
+                chain_head,
+                now - failure.since,
+            );
+            let _ = send_slack_warning(ctx.endpoint_notifier(endpoint_data), channel, &message).await;
+        }
+    }
+
+    true
+}
+
+/// Looks up the current state of every bid previously alerted on this endpoint's chain and
+/// sends a recovery notice for those that have recovered. Bids that moved to an unmonitored
+/// status such as `Defaulted` are dropped from the store without a notice.
+///
+/// The bids are fetched by id rather than diffed against the overdue query, since that query
+/// is paged and windowed and a bid missing from it is not necessarily caught up.
+///
+/// Returns `false` if a lookup failed.
+async fn check_recovered_bids(
+    ctx: &MonitorContext,
+    endpoint_data: &Endpoint,
+    source: &Source,
+    format_options: &FormatOptions,
+) -> bool {
+    let chain_id = endpoint_data.chain_id;
+    let channel = endpoint_data.slack_channel.as_deref();
+
+    let alerted_bid_ids = ctx.store().bid_ids_for_chain(chain_id);
+
+    if alerted_bid_ids.is_empty() {
+        return true;
+    }
+
+    let now_utc: DateTime<Utc> = Utc::now();
+    let now = now_utc.timestamp();
+    let timestamp = format_timestamp(now_utc, ctx.timezone);
+
+    // Recovered and closed bids alike
+    let mut recovered_keys = HashSet::new();
+    let mut succeeded = true;
+
+    for chunk in alerted_bid_ids.chunks(RECOVERY_LOOKUP_CHUNK) {
+        let body = serde_json::json!({
+            "query": BIDS_BY_ID_QUERY,
+            "variables": {
+                "bidIds": chunk,
+                "first": RECOVERY_LOOKUP_CHUNK
+            }
+        });
+
+        let bids = match post_graphql::<BidsData>(&ctx.client, source, body, ctx.config.retry_policy()).await {
+            Ok(data) => data.bids,
+            Err(e) => {
+                error!(url = %source.label, error = %e, "Failed to look up alerted bids");
+                // Still drop the bids already announced as recovered from earlier chunks
+                succeeded = false;
+                break;
+            }
+        };
+
+        for bid in &bids {
+            let Some(bid_id) = bid_identifier(bid) else {
+                continue;
+            };
+            match alerted_bid_state(bid, now, &endpoint_data.statuses) {
+                AlertedBidState::Overdue => continue,
+                AlertedBidState::Closed => {
+                    let status = bid.get("status").and_then(|v| v.as_str()).unwrap_or_default();
+                    info!(bid_id, status, "Alerted bid left the monitored statuses without recovering, no longer tracking it");
+                    recovered_keys.insert(make_bid_key(chain_id, bid_id));
+                    continue;
+                }
+                AlertedBidState::Recovered => {}
+            }
+
+            info!(bid_id, "Bid is no longer overdue, sending recovery notice");
+
+            let message = format_bid_recovered(bid, chain_id, &timestamp, now, format_options);
+            let sent = send_alert_about(ctx.endpoint_notifier(endpoint_data), channel, &message, AlertSubject::bid(chain_id, bid_id, None)).await.is_ok();
+            let principal = bid.get("principal").and_then(|v| v.as_str()).unwrap_or("0");
+            ctx.audit.record(&AuditEvent::bid(AuditKind::Recovered, now, chain_id, bid_id, principal, sent));
+            if sent {
+                recovered_keys.insert(make_bid_key(chain_id, bid_id));
+            } else {
+                warn!(bid_id, "Recovery notice not delivered, keeping the bid to send it again next poll");
+            }
+        }
+    }
+
+    if !recovered_keys.is_empty() {
+        ctx.store().remove(&recovered_keys);
+    }
+
+    succeeded
+}
+
+/// New alerts for one channel and workspace, sent together with `batch_alerts`.
+struct AlertBatch<'a> {
+    channel: Option<&'a str>,
+    workspace: Option<&'a str>,
+    bids: Vec<QueuedBid>,
+    alerts: Vec<String>,
+}
+
+fn queued_bid(bid_id: &str, principal: &str, kind: AuditKind, severity: Option<&str>) -> QueuedBid {
+    QueuedBid {
+        bid_id: bid_id.to_string(),
+        principal: principal.to_string(),
+        kind,
+        severity: severity.map(str::to_string),
+    }
+}
+
+/// Resends the endpoint's queued alerts, oldest first, recording their bids as alerted once
+/// delivered. Alerts only some backends missed go to just those. Stops at the first failure,
+/// since the notifier is most likely still down.
+/// Returns how many bids' alerts went out.
+async fn retry_queued_alerts(ctx: &MonitorContext, endpoint_data: &Endpoint) -> usize {
+    let queued = ctx.retry_queue().take_for_endpoint(&endpoint_data.name);
+    if queued.is_empty() {
+        return 0;
+    }
+    info!(count = queued.len(), "Resending queued alerts");
+
+    let mut remaining = queued.into_iter();
+    let mut unsent = Vec::new();
+    let mut delivered = 0;
+    for alert in remaining.by_ref() {
+        let subject = match alert.bids.as_slice() {
+            [bid] => AlertSubject::bid(alert.chain_id, &bid.bid_id, bid.severity.as_deref()),
+            _ => AlertSubject::chain(alert.chain_id),
+        };
+        let notifier = ctx.notifier_for(alert.workspace.as_deref());
+        let resend = notifier.resend_about(alert.backends.as_deref(), alert.channel.as_deref(), &alert.message, subject);
+        if let Err(e) = log_send_result(resend.await) {
+            // Backends that got it this time aren't sent it again
+            let backends = failed_backends(&e).or(alert.backends);
+            unsent.push(QueuedAlert { backends, ..alert });
+            break;
+        }
+
+        let now = Utc::now().timestamp();
+        let mut store = ctx.store();
+        for bid in &alert.bids {
+            ctx.audit.record(
+                &AuditEvent::bid(bid.kind, now, alert.chain_id, &bid.bid_id, &bid.principal, true)
+                    .with_severity(bid.severity.as_deref()),
+            );
+            store.record(alert.chain_id, &bid.bid_id, &bid.principal, now);
+        }
+        delivered += alert.bids.len();
+    }
+    unsent.extend(remaining);
+
+    if !unsent.is_empty() {
+        warn!(count = unsent.len(), "Queued alerts still not delivered, keeping them for the next poll");
+    }
+    ctx.retry_queue().requeue(unsent);
+    delivered
+}
+
+/// Sends the message, logging a failure. Callers only record the message as delivered on `Ok`.
+async fn send_slack_warning(notifier: &dyn Notifier, channel: Option<&str>, message: &str) -> anyhow::Result<()> {
+    send_alert_about(notifier, channel, message, AlertSubject::default()).await
+}
+
+/// Like `send_slack_warning`, telling backends with structured payloads which chain or bid it is about.
+async fn send_alert_about(
+    notifier: &dyn Notifier,
+    channel: Option<&str>,
+    message: &str,
+    subject: AlertSubject<'_>,
+) -> anyhow::Result<()> {
+    log_send_result(notifier.notify_about(channel, message, subject).await)
+}
+
+async fn send_slack_blocks(
+    notifier: &dyn Notifier,
+    channel: Option<&str>,
+    message: &str,
+    blocks: &serde_json::Value,
+    subject: AlertSubject<'_>,
+) -> anyhow::Result<()> {
+    log_send_result(notifier.notify_blocks(channel, message, blocks, subject).await)
+}
+
+fn log_send_result(result: anyhow::Result<()>) -> anyhow::Result<()> {
+    match &result {
+        Ok(_) => debug!("Alert sent"),
+        Err(e) => error!(error = %e, "Failed to send alert"),
+    }
+    result
+}
+
+fn build_post_request(
+    client: &reqwest::Client,
+    url: &str,
+    body: serde_json::Value,
+    auth_token: Option<&str>,
+    headers: &HeaderMap,
+) -> reqwest::RequestBuilder {
+    let mut request = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .json(&body);
+
+    // Add Bearer token if provided
+    if let Some(token) = auth_token {
+        request = request.bearer_auth(token);
+    }
+
+    // Configured headers go last so they replace the ones above
+    request.headers(headers.clone())
+}
+
+async fn make_post_request(
+    client: &reqwest::Client,
+    url: &str,
+    body: serde_json::Value,
+    auth_token: Option<&str>,
+    headers: &HeaderMap,
+) -> Result<HttpResponse, reqwest::Error> {
+    let request = build_post_request(client, url, body, auth_token, headers);
+    let response = request.send().await?;
+
+    let status = response.status();
+    let body = response.text().await?;
+    Ok(HttpResponse { status, body })
+}
+
+/// Status and body of an answered request, whatever the status.
+#[derive(Debug)]
+struct HttpResponse {
+    status: reqwest::StatusCode,
+    body: String,
+}
+
+/// Timeouts and connection failures are worth retrying, as are 5xx responses (see
+/// `make_post_request_with_retry`); anything else (notably 4xx) will fail the same way again.
+fn is_retryable(e: &reqwest::Error) -> bool {
+    e.is_timeout() || e.is_connect()
+}
+
+async fn make_post_request_with_retry(
+    client: &reqwest::Client,
+    url: &str,
+    body: serde_json::Value,
+    auth_token: Option<&str>,
+    headers: &HeaderMap,
+    retry: RetryPolicy,
+) -> Result<HttpResponse, reqwest::Error> {
+    let mut attempt = 0;
+
+    loop {
+        match make_post_request(client, url, body.clone(), auth_token, headers).await {
+            Err(e) if attempt < retry.max_retries && is_retryable(&e) => {
+                let delay = retry.backoff(attempt, rand::random::<f64>());
+                // Host only, the URL may carry an API key expanded from `${VAR}` or an RPC provider key
+                warn!(host = %rpc_label(url), error = %e.without_url(), ?delay, "Request failed, retrying");
+                time::sleep(delay).await;
+                attempt += 1;
+            }
+            Ok(response) if attempt < retry.max_retries && response.status.is_server_error() => {
+                let delay = retry.backoff(attempt, rand::random::<f64>());
+                warn!(host = %rpc_label(url), status = %response.status, ?delay, "Request failed, retrying");
+                time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::RecordingNotifier;
+
+    #[test]
+    fn test_should_realert_respects_cooldown() {
+        let record = AlertRecord { alerted_at: Some(1_000), principal: Some("1000000".to_string()), snoozed_until: None };
+
+        assert!(!should_realert(&record, "1000000", 1_000 + ONE_DAY as i64, None));
+        assert!(!should_realert(&record, "1000000", 1_000 + 59, Some(60)));
+        assert!(should_realert(&record, "1000000", 1_000 + 60, Some(60)));
+    }
+
+    #[test]
+    fn test_should_realert_when_principal_changes() {
+        let record = AlertRecord { alerted_at: Some(1_000), principal: Some("1000000".to_string()), snoozed_until: None };
+
+        assert!(should_realert(&record, "2000000", 1_001, Some(ONE_DAY)));
+        assert!(should_realert(&record, "2000000", 1_001, None));
+    }
+
+
+    #[test]
+    fn test_alerted_bid_state() {
+        let statuses = default_statuses();
+        let state = |status: &str, due: &str| alerted_bid_state(&serde_json::json!({ "status": status, "nextDueDate": due }), 2000, &statuses);
+
+        assert_eq!(state("Accepted", "1000"), AlertedBidState::Overdue);
+        assert_eq!(state("Accepted", "2000"), AlertedBidState::Recovered);
+        assert_eq!(state("Accepted", "soon"), AlertedBidState::Overdue);
+        assert_eq!(state("Repaid", "1000"), AlertedBidState::Recovered);
+        let with_repaid = ["Accepted".to_string(), "Repaid".to_string()];
+        assert_eq!(alerted_bid_state(&serde_json::json!({ "status": "Repaid", "nextDueDate": "1000" }), 2000, &with_repaid), AlertedBidState::Overdue);
+        assert_eq!(state("Defaulted", "1000"), AlertedBidState::Closed);
+        assert_eq!(state("Liquidated", "3000"), AlertedBidState::Closed);
+        assert_eq!(alerted_bid_state(&serde_json::json!({ "nextDueDate": "3000" }), 2000, &statuses), AlertedBidState::Overdue);
+    }
+
+
+    #[test]
+    fn test_retry_backoff_doubles_within_jitter_bounds() {
+        let retry = RetryPolicy { max_retries: 3, base_backoff_ms: 500 };
+
+        assert_eq!(retry.backoff(0, 0.0), Duration::from_millis(250));
+        assert_eq!(retry.backoff(0, 1.0), Duration::from_millis(500));
+        assert_eq!(retry.backoff(2, 0.0), Duration::from_millis(1000));
+        assert_eq!(retry.backoff(2, 1.0), Duration::from_millis(2000));
+    }
+
+    #[test]
+    fn test_build_due_query_pages_with_skip() {
+        let body = build_due_query(DUE_BIDS_QUERY, DueWindow { due_before: 2000, due_since: 1000 }, &default_statuses(), BidFilters::default(), BidOrder::default(), 100, 200);
+
+        assert_eq!(body["query"], DUE_BIDS_QUERY);
+        assert_eq!(
+            body["variables"],
+            serde_json::json!({
+                "dueBefore": "2000",
+                "dueSince": "1000",
+                "statuses": ["Accepted"],
+                "orderBy": "nextDueDate",
+                "orderDirection": "asc",
+                "first": 100,
+                "skip": 200
+            })
+        );
+    }
+
+    #[test]
+    fn test_build_due_query_filters_multiple_statuses() {
+        let statuses = vec!["Accepted".to_string(), "DueSoon".to_string(), "Liquidated".to_string()];
+        let body = build_due_query(DUE_BIDS_QUERY, DueWindow { due_before: 2000, due_since: 1000 }, &statuses, BidFilters::default(), BidOrder::default(), 100, 0);
+
+        assert!(DUE_BIDS_QUERY.contains("status_in: $statuses"));
+        assert_eq!(body["variables"]["statuses"], serde_json::json!(["Accepted", "DueSoon", "Liquidated"]));
+    }
+
+    #[test]
+    fn test_build_due_query_filters_tokens_and_marketplaces() {
+        let tokens = vec!["0x2791Bca1f2de4661ED88A30C99A7a9449Aa84174".to_string()];
+        let marketplaces = vec!["1".to_string(), "33".to_string()];
+        let window = DueWindow { due_before: 2000, due_since: 1000 };
+
+        let filters = BidFilters { lending_tokens: &tokens, marketplace_ids: &marketplaces };
+        let body = build_due_query(DUE_BIDS_QUERY, window, &default_statuses(), filters, BidOrder::default(), 100, 0);
+        let query = body["query"].as_str().unwrap();
+        assert!(query.contains("$lendingTokens: [String!]!\n  $marketplaceIds: [BigInt!]!\n) {"));
+        assert!(query.contains("status_in: $statuses\n      lendingToken_in: $lendingTokens\n      marketplaceId_in: $marketplaceIds\n"));
+        assert_eq!(body["variables"]["lendingTokens"], serde_json::json!(["0x2791bca1f2de4661ed88a30c99a7a9449aa84174"]));
+        assert_eq!(body["variables"]["marketplaceIds"], serde_json::json!(["1", "33"]));
+
+        let filters = BidFilters { marketplace_ids: &marketplaces, ..BidFilters::default() };
+        let body = build_due_query(DUE_BIDS_QUERY, window, &default_statuses(), filters, BidOrder::default(), 100, 0);
+        assert!(!body["query"].as_str().unwrap().contains("lendingToken_in"));
+        assert!(body["variables"].get("lendingTokens").is_none());
+    }
+
+    #[test]
+    fn test_load_query_templates() {
+        let test_store = TestStore::new("health_bot_query_templates_test");
+        let unfiltered = "query Due($dueBefore: BigInt!, $dueSince: BigInt!, $first: Int!, $skip: Int!) {\n  \
+                          bids(where: { nextDueDate_lt: $dueBefore, nextDueDate_gte: $dueSince }, first: $first, skip: $skip) { id }\n}\n";
+        fs::write(test_store.dir.join("unfiltered.graphql"), unfiltered).unwrap();
+        fs::write(test_store.dir.join("no_paging.graphql"), "query Due($dueBefore: BigInt!, $dueSince: BigInt!) { bids { id } }").unwrap();
+
+        let mut config = config_with_endpoints(
+            r#"(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: None, query_template: Some("unfiltered")),
+               (name: "b", url: "https://example.com/b", chain_id: 1, auth_key: None)"#,
+        );
+        load_query_templates(&mut config, &test_store.dir).unwrap();
+        assert_eq!(config.endpoints[0].due_query(), unfiltered);
+        assert_eq!(config.endpoints[1].due_query(), DUE_BIDS_QUERY);
+
+        let mut config = config_with_endpoints(
+            r#"(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: None, query_template: Some("no_paging.graphql"))"#,
+        );
+        assert_eq!(load_query_templates(&mut config, &test_store.dir), Err("Query template 'no_paging.graphql' does not use the $first variable".to_string()));
+
+        // Filtering needs the placeholders to put the filters in
+        let mut config = config_with_endpoints(
+            r#"(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: None, query_template: Some("unfiltered"), marketplace_ids: ["1"])"#,
+        );
+        assert!(load_query_templates(&mut config, &test_store.dir).unwrap_err().contains("needs '# filter variables' and '# filters' lines"));
+
+        let mut config = config_with_endpoints(
+            r#"(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: None, query_template: Some("../unfiltered"))"#,
+        );
+        assert!(load_query_templates(&mut config, &test_store.dir).unwrap_err().contains("invalid query_template"));
+
+        // The shipped template is the built-in query and passes its own checks
+        let shipped = fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join(DEFAULT_QUERIES_DIR).join("due_bids.graphql")).unwrap();
+        assert_eq!(shipped, DUE_BIDS_QUERY);
+        let tokens = vec!["0x2791bca1f2de4661ed88a30c99a7a9449aa84174".to_string()];
+        assert_eq!(check_query_template("due_bids", DUE_BIDS_QUERY, BidFilters { lending_tokens: &tokens, ..BidFilters::default() }), Ok(()));
+    }
+
+    #[test]
+    fn test_build_due_query_uses_configured_order() {
+        let config: EndpointConfig =
+            ron::from_str(r#"(endpoints: [], order_by: Some("principal"), order_direction: Desc)"#).unwrap();
+        let body = build_due_query(DUE_BIDS_QUERY, DueWindow { due_before: 2000, due_since: 1000 }, &default_statuses(), BidFilters::default(), config.bid_order(), 100, 0);
+
+        assert!(DUE_BIDS_QUERY.contains("orderBy: $orderBy"));
+        assert_eq!(body["variables"]["orderBy"], "principal");
+        assert_eq!(body["variables"]["orderDirection"], "desc");
+    }
+
+    #[test]
+    fn test_validate_config_rejects_bad_order_by() {
+        let config: EndpointConfig = ron::from_str(r#"(endpoints: [], order_by: Some("nextDueDate desc"))"#).unwrap();
+
+        assert_eq!(validate_config(&config), Err("Invalid order_by 'nextDueDate desc', expected a Bid field name".to_string()));
+    }
+
+    #[test]
+    fn test_query_error_poll_result() {
+        let graphql = QueryError::GraphQl { messages: vec!["bad".to_string()], raw: String::new() };
+        assert_eq!(graphql.poll_result(), PollResult::GraphQlError);
+        let decode = QueryError::Decode { error: "eof".to_string(), raw: String::new() };
+        assert_eq!(decode.poll_result(), PollResult::GraphQlError);
+        let http = QueryError::Http { status: reqwest::StatusCode::BAD_GATEWAY, body: String::new() };
+        assert_eq!(http.poll_result(), PollResult::TransportError);
+    }
+
+    #[tokio::test]
+    async fn test_post_graphql_decodes_data() {
+        let app = axum::Router::new()
+            .route("/bids", axum::routing::post(|| async { r#"{"data":{"bids":[{"bidId":"1"},{"bidId":"2"}]}}"# }))
+            .route("/empty", axum::routing::post(|| async { r#"{"data":{}}"# }))
+            .route("/html", axum::routing::post(|| async { "<html>Bad gateway</html>" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = reqwest::Client::new();
+        let retry = RetryPolicy { max_retries: 0, base_backoff_ms: 1 };
+        let source = |path: &str| Source {
+            label: path.to_string(),
+            url: format!("http://{}{}", addr, path),
+            auth_token: None,
+            headers: HeaderMap::new(),
+        };
+
+        let data: BidsData = post_graphql(&client, &source("/bids"), serde_json::json!({}), retry).await.unwrap();
+        assert_eq!(data.bids.len(), 2);
+
+        // A response without bids is an error rather than an all-clear
+        let error = post_graphql::<BidsData>(&client, &source("/empty"), serde_json::json!({}), retry).await.unwrap_err();
+        assert!(matches!(&error, QueryError::Decode { raw, .. } if raw == r#"{"data":{}}"#));
+        assert_eq!(error.to_string(), "Invalid response: missing field `bids`");
+
+        let error = post_graphql::<BidsData>(&client, &source("/html"), serde_json::json!({}), retry).await.unwrap_err();
+        assert!(matches!(&error, QueryError::Decode { raw, .. } if raw == "<html>Bad gateway</html>"));
+    }
+
+    #[test]
+    fn test_due_soon_window() {
+        assert_eq!(DueWindow::due_soon(10_000, 3_600), DueWindow { due_before: 13_600, due_since: 10_000 });
+    }
+
+    #[test]
+    fn test_overdue_window_boundaries() {
+        let window = DueWindow::new(10_000, 5_000, 600);
+
+        assert_eq!(window, DueWindow { due_before: 9_400, due_since: 5_000 });
+
+        // Due exactly at the grace cutoff is not overdue yet, exactly at the lookback edge is included
+        let body = build_due_query(DUE_BIDS_QUERY, window, &default_statuses(), BidFilters::default(), BidOrder::default(), 100, 0);
+        assert_eq!(body["variables"]["dueBefore"], "9400");
+        assert_eq!(body["variables"]["dueSince"], "5000");
+        assert!(DUE_BIDS_QUERY.contains("nextDueDate_lt: $dueBefore"));
+        assert!(DUE_BIDS_QUERY.contains("nextDueDate_gte: $dueSince"));
+    }
+
+    fn config_with_endpoints(endpoints: &str) -> EndpointConfig {
+        ron::from_str(&format!("(endpoints: [{}])", endpoints)).unwrap()
+    }
+
+    #[test]
+    fn test_validate_config_accepts_valid_endpoints() {
+        let config = config_with_endpoints(
+            r#"(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: None),
+               (name: "b", url: "https://example.com/b", chain_id: 8453, auth_key: None)"#,
+        );
+
+        assert!(validate_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_endpoints_for_command() {
+        let config = config_with_endpoints(
+            r#"(name: "mainnet", url: "https://example.com/a", chain_id: 1, auth_key: None),
+               (name: "polygon", url: "https://example.com/b", chain_id: 137, auth_key: None),
+               (name: "base", url: "https://example.com/c", chain_id: 8453, auth_key: None, disabled: true)"#,
+        );
+        let options = config.format_options(LinkStyle::Slack, DEFAULT_TIMEZONE);
+        let names = |text: &str| {
+            endpoints_for_command(&config, &options, text).map(|endpoints| endpoints.iter().map(|e| e.name.as_str()).collect::<Vec<_>>())
+        };
+
+        assert_eq!(names(" 137 "), Ok(vec!["polygon"]));
+        assert_eq!(names("ethereum"), Ok(vec!["mainnet"]));
+        assert_eq!(names("8453"), Err("No enabled endpoint is monitoring Base (8453)".to_string()));
+        assert_eq!(names("dogechain"), Err("Unknown chain `dogechain`".to_string()));
+        assert!(names("").unwrap_err().starts_with("Usage:"));
+    }
+
+    #[test]
+    fn test_parse_endpoint_config_names_source_on_error() {
+        let config = parse_endpoint_config(
+            r#"(endpoints: [(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: None)])"#,
+            ConfigFormat::Ron,
+            "ENDPOINTS_RON",
+        )
+        .unwrap();
+        assert_eq!(config.endpoints[0].name, "a");
+
+        let error = parse_endpoint_config("(endpoints: [", ConfigFormat::Ron, "ENDPOINTS_RON").unwrap_err();
+        assert!(error.starts_with("Failed to parse ENDPOINTS_RON: "));
+    }
+
+    #[test]
+    fn test_parse_endpoint_config_json_and_yaml() {
+        let json = r#"{
+            "endpoints": [{ "name": "a", "url": "https://example.com/a", "chain_id": 1, "auth_key": null }],
+            "realert_after_secs": 3600,
+            "chain_names": { "5": "Goerli" },
+            "order_direction": "Desc"
+        }"#;
+        let yaml = "
+endpoints:
+  - name: a
+    url: https://example.com/a
+    chain_id: 1
+    auth_key: null
+realert_after_secs: 3600
+chain_names:
+  5: Goerli
+order_direction: Desc
+";
+
+        for (content, format) in [(json, ConfigFormat::Json), (yaml, ConfigFormat::Yaml)] {
+            let config = parse_endpoint_config(content, format, "test").unwrap();
+            assert_eq!(config.endpoints[0].url, "https://example.com/a");
+            assert_eq!(config.realert_after_secs, Some(3600));
+            assert_eq!(config.chain_names[&5], "Goerli");
+            assert_eq!(config.order_direction, OrderDirection::Desc);
+        }
+
+        let error = parse_endpoint_config("endpoints:\n  - name: [", ConfigFormat::Yaml, "config file a.yaml").unwrap_err();
+        assert!(error.contains("line 2"), "{}", error);
+        let error = parse_endpoint_config("{\n\"endpoints\": 5}", ConfigFormat::Json, "config file a.json").unwrap_err();
+        assert!(error.contains("line 2"), "{}", error);
+    }
+
+    #[test]
+    fn test_config_format_from_extension() {
+        assert_eq!(ConfigFormat::from_path(Path::new("src/endpoints.ron")), Ok(ConfigFormat::Ron));
+        assert_eq!(ConfigFormat::from_path(Path::new("endpoints.JSON")), Ok(ConfigFormat::Json));
+        assert_eq!(ConfigFormat::from_path(Path::new("/etc/bot/endpoints.yml")), Ok(ConfigFormat::Yaml));
+        assert!(ConfigFormat::from_path(Path::new("endpoints.toml")).unwrap_err().contains("endpoints.toml"));
+        assert!(ConfigFormat::from_path(Path::new("endpoints")).is_err());
+    }
+
+    #[test]
+    fn test_endpoint_is_enabled() {
+        let config = config_with_endpoints(
+            r#"(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: None),
+               (name: "b", url: "https://example.com/b", chain_id: 1, auth_key: None, enabled: Some(true)),
+               (name: "c", url: "https://example.com/c", chain_id: 1, auth_key: None, enabled: Some(false)),
+               (name: "d", url: "https://example.com/d", chain_id: 1, auth_key: None, disabled: true),
+               (name: "e", url: "https://example.com/e", chain_id: 1, auth_key: None, disabled: true, enabled: Some(false))"#,
+        );
+
+        let enabled: Vec<bool> = config.endpoints.iter().map(Endpoint::is_enabled).collect();
+        assert_eq!(enabled, [true, true, false, false, false]);
+        assert!(validate_config(&config).is_ok());
+
+        let config = config_with_endpoints(
+            r#"(name: "d", url: "https://example.com/d", chain_id: 1, auth_key: None, disabled: true, enabled: Some(true))"#,
+        );
+        assert_eq!(
+            validate_config(&config),
+            Err("Endpoint 'd' sets both disabled: true and enabled: Some(true), use enabled only".to_string())
+        );
+    }
+
+    #[test]
+    fn test_shipped_config_is_valid() {
+        let config = load_endpoint_config(Path::new("src/endpoints.ron")).unwrap();
+
+        assert!(validate_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_validate_config_rejects_duplicate_names() {
+        let config = config_with_endpoints(
+            r#"(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: None),
+               (name: "a", url: "https://example.com/b", chain_id: 137, auth_key: None)"#,
+        );
+
+        assert_eq!(validate_config(&config), Err("Duplicate endpoint name 'a'".to_string()));
+    }
+
+    #[test]
+    fn test_validate_config_rejects_empty_url() {
+        let config = config_with_endpoints(r#"(name: "a", url: " ", chain_id: 1, auth_key: None)"#);
+
+        assert_eq!(validate_config(&config), Err("Endpoint 'a' has an empty url".to_string()));
+    }
+
+    #[test]
+    fn test_validate_config_rejects_unparseable_url() {
+        let config = config_with_endpoints(r#"(name: "a", url: "gateway.thegraph.com/api", chain_id: 1, auth_key: None)"#);
+
+        assert!(validate_config(&config).unwrap_err().starts_with("Endpoint 'a' has an invalid url"));
+    }
+
+    #[test]
+    fn test_validate_config_rejects_non_positive_chain_id() {
+        let config = config_with_endpoints(r#"(name: "a", url: "https://example.com/a", chain_id: 0, auth_key: None)"#);
+
+        assert_eq!(validate_config(&config), Err("Endpoint 'a' has an invalid chain_id 0".to_string()));
+    }
+
+    #[test]
+    fn test_validate_config_rejects_unparseable_fallback_url() {
+        let config = config_with_endpoints(
+            r#"(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: None,
+                fallback_urls: [(url: "https://example.com/b"), (url: "example.com/c")])"#,
+        );
+
+        assert!(validate_config(&config).unwrap_err().starts_with("Endpoint 'a' has an invalid url 'example.com/c'"));
+    }
+
+    #[test]
+    fn test_configured_headers_replace_automatic_ones() {
+        let configured = HashMap::from([
+            ("X-Api-Key".to_string(), "${HEALTH_BOT_UNSET_TEST_VAR}".to_string()),
+            ("Authorization".to_string(), "Token abc".to_string()),
+            ("bad header".to_string(), "x".to_string()),
+        ]);
+        let headers = build_headers(&configured);
+
+        let request = build_post_request(&reqwest::Client::new(), "https://example.com", serde_json::json!({}), Some("secret"), &headers)
+            .build()
+            .unwrap();
 
-#[derive(Debug, Clone, Default )]
-struct MonitorConfig {
+        assert_eq!(request.headers()["x-api-key"], "${HEALTH_BOT_UNSET_TEST_VAR}");
+        assert_eq!(request.headers().get_all("authorization").iter().collect::<Vec<_>>(), ["Token abc"]);
+        assert_eq!(request.headers()["content-type"], "application/json");
+        assert_eq!(headers.len(), 2);
+    }
 
-    endpoint_monitor_index: usize 
+    #[test]
+    fn test_apply_auth_styles() {
+        let source = |url: &str, headers: HeaderMap| Source { label: url.to_string(), url: url.to_string(), auth_token: None, headers };
+        let build = |source: &Source| {
+            build_post_request(&reqwest::Client::new(), &source.url, serde_json::json!({}), source.auth_token.as_deref(), &source.headers)
+                .build()
+                .unwrap()
+        };
 
-}
+        let mut bearer = source("https://example.com/graphql", HeaderMap::new());
+        bearer.apply_auth(&AuthStyle::Bearer, "secret".to_string());
+        let request = build(&bearer);
+        assert_eq!(request.headers()["authorization"], "Bearer secret");
+        assert_eq!(request.url().as_str(), "https://example.com/graphql");
 
-const ONE_HOUR:u64 = 3600 ;
+        let mut query = source("https://example.com/graphql?network=polygon", HeaderMap::new());
+        query.apply_auth(&AuthStyle::QueryParam { name: "api_key".to_string() }, "s3cr&t".to_string());
+        let request = build(&query);
+        assert_eq!(request.url().as_str(), "https://example.com/graphql?network=polygon&api_key=s3cr%26t");
+        assert!(request.headers().get("authorization").is_none());
 
-const ONE_DAY:u64 = 86400;
+        let mut header = source("https://example.com/graphql", HeaderMap::new());
+        header.apply_auth(&AuthStyle::Header { name: "X-Api-Key".to_string() }, "secret".to_string());
+        let request = build(&header);
+        assert_eq!(request.headers()["x-api-key"], "secret");
+        assert!(request.headers().get("authorization").is_none());
 
-const ALERTED_BIDS_FILE: &str = "alerted_bids.txt";
+        // A configured header of the same name still wins
+        let configured = build_headers(&HashMap::from([("x-api-key".to_string(), "configured".to_string())]));
+        let mut header = source("https://example.com/graphql", configured);
+        header.apply_auth(&AuthStyle::Header { name: "X-Api-Key".to_string() }, "secret".to_string());
+        assert_eq!(build(&header).headers()["x-api-key"], "configured");
+    }
 
-fn load_alerted_bids() -> HashSet<String> {
-    let mut alerted = HashSet::new();
-    if let Ok(file) = fs::File::open(ALERTED_BIDS_FILE) {
-        let reader = BufReader::new(file);
-        for line in reader.lines().flatten() {
-            if !line.trim().is_empty() {
-                alerted.insert(line.trim().to_string());
-            }
-        }
+    #[test]
+    fn test_validate_config_rejects_invalid_auth_style() {
+        let config = config_with_endpoints(
+            r#"(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: None, auth_style: Some(Header(name: "bad header")))"#,
+        );
+        assert_eq!(validate_config(&config), Err("Endpoint 'a' has an invalid auth_style header 'bad header'".to_string()));
+
+        let config = config_with_endpoints(
+            r#"(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: None, auth_style: Some(QueryParam(name: "api_key")))"#,
+        );
+        assert_eq!(validate_config(&config), Ok(()));
     }
-    alerted
-}
 
-fn save_alerted_bid(chain_id: i32, bid_id: &str) {
-    let mut file = fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(ALERTED_BIDS_FILE)
-        .expect("Failed to open alerted bids file");
-    writeln!(file, "{}:{}", chain_id, bid_id).expect("Failed to write to alerted bids file");
-}
+    #[test]
+    fn test_validate_config_rejects_invalid_header() {
+        let config = config_with_endpoints(
+            r#"(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: None, headers: Some({"bad header": "x"}))"#,
+        );
 
-fn make_bid_key(chain_id: i32, bid_id: &str) -> String {
-    format!("{}:{}", chain_id, bid_id)
-}
+        assert_eq!(validate_config(&config), Err("Endpoint 'a' has an invalid header 'bad header'".to_string()));
+    }
 
-fn format_bid_alert(bid: &serde_json::Value, chain_id: i32, timestamp: &str) -> String {
-    let bid_id = bid.get("bidId").and_then(|v| v.as_str()).unwrap_or("unknown");
-    let borrower = bid.get("borrowerAddress").and_then(|v| v.as_str()).unwrap_or("unknown");
-    let principal_raw = bid.get("principal").and_then(|v| v.as_str()).unwrap_or("0");
-    let lending_token_obj = bid.get("lendingToken");
-    let lending_token = lending_token_obj
-        .and_then(|v| v.get("symbol"))
-        .and_then(|v| v.as_str())
-        .unwrap_or("unknown");
-    let decimals = lending_token_obj
-        .and_then(|v| v.get("decimals"))
-        .and_then(|v| v.as_u64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
-        .unwrap_or(0) as u32;
-    let principal: f64 = principal_raw.parse().unwrap_or(0.0) / 10_f64.powi(decimals as i32);
-    let next_due = bid.get("nextDueDate").and_then(|v| v.as_str()).unwrap_or("unknown");
-    let status = bid.get("status").and_then(|v| v.as_str()).unwrap_or("unknown");
-
-    format!(
-        "🚨 Overdue Loan Alert!\nTimestamp: {}\nChain ID: {}\nBid ID: {}\nBorrower: {}\nPrincipal Token: {}\nPrincipal Amount: {:.2}\nNext Due Date: {}\nStatus: {}",
-        timestamp, chain_id, bid_id, borrower, lending_token, principal, next_due, status
-    )
-} 
+    #[test]
+    fn test_expand_env_vars() {
+        let path = env::var("PATH").unwrap();
 
+        assert_eq!(expand_env_vars("https://example.com/${PATH}/x"), format!("https://example.com/{}/x", path));
+        // Unset variables and unclosed placeholders are left as written
+        assert_eq!(expand_env_vars("a/${HEALTH_BOT_UNSET_TEST_VAR}/b"), "a/${HEALTH_BOT_UNSET_TEST_VAR}/b");
+        assert_eq!(expand_env_vars("a/${PATH"), "a/${PATH");
+    }
 
-impl MonitorConfig {
+    #[test]
+    fn test_sources_try_primary_url_first() {
+        let config = config_with_endpoints(
+            r#"(name: "a", url: "https://gateway.example.com/api/${HEALTH_BOT_UNSET_TEST_VAR}/subgraphs/id/x", chain_id: 1,
+                auth_key: None, fallback_urls: [(url: "https://b.example.com", auth_key: Some("HEALTH_BOT_UNSET_TEST_VAR"))])"#,
+        );
 
-    fn get_monitor_index(&self) -> usize {
+        let sources = config.endpoints[0].sources();
 
-        self.endpoint_monitor_index
+        assert_eq!(
+            sources.iter().map(|source| source.label.as_str()).collect::<Vec<_>>(),
+            ["https://gateway.example.com/api/${HEALTH_BOT_UNSET_TEST_VAR}/subgraphs/id/x", "https://b.example.com"]
+        );
+        assert!(sources.iter().all(|source| source.auth_token.is_none()));
     }
 
-    fn set_monitor_index(&mut self, new_index: usize) {
-        self.endpoint_monitor_index = new_index; 
+    #[test]
+    fn test_severity_for_picks_highest_threshold_passed() {
+        let config: EndpointConfig = ron::from_str(
+            r##"(
+                endpoints: [],
+                severity_thresholds: [
+                    (name: "critical", min_overdue_secs: 604800, emoji: "🔥", channel: Some("#loans-critical")),
+                    (name: "warning", min_overdue_secs: 0, emoji: "⚠️"),
+                ],
+            )"##,
+        )
+        .unwrap();
+        let thresholds = &config.severity_thresholds;
+
+        assert_eq!(severity_for(thresholds, ONE_HOUR as i64).map(|t| t.name.as_str()), Some("warning"));
+        assert_eq!(severity_for(thresholds, 7 * ONE_DAY as i64).map(|t| t.name.as_str()), Some("critical"));
+        assert!(severity_for(thresholds, -1).is_none());
+        assert!(severity_for(&[], ONE_DAY as i64).is_none());
     }
-}
 
+    #[test]
+    fn test_block_number_from_rpc_response() {
+        assert_eq!(block_number_from_rpc_response(&serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": "0x12a05f2" })), Ok(19_531_250));
+        assert!(block_number_from_rpc_response(&serde_json::json!({ "error": { "code": -32000, "message": "rate limited" } })).is_err());
+        assert!(block_number_from_rpc_response(&serde_json::json!({ "result": "12a05f2" })).is_err());
+        assert!(block_number_from_rpc_response(&serde_json::json!({})).is_err());
+    }
 
-#[tokio::main]
-async fn main() {
-    // Load environment variables from .env file if it exists
-    dotenvy::dotenv().ok();
+    #[test]
+    fn test_rpc_label_hides_api_key() {
+        assert_eq!(rpc_label("https://eth-mainnet.g.alchemy.com/v2/secret-key"), "eth-mainnet.g.alchemy.com");
+        assert_eq!(rpc_label("not a url"), "invalid url");
+    }
 
-    println!("Starting periodic POST requests ...");
+    #[test]
+    fn test_next_summary_time() {
+        let send_at = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+        // 2024-01-01 12:00 UTC is 07:00 in New York
+        let now = DateTime::from_timestamp(1704110400, 0).unwrap();
 
-    // Create a shared index to track which endpoint to check next
-    let endpoint_config =   Arc::new(Mutex::new(  MonitorConfig::default() ))   ;
+        let next = next_summary_time(now, send_at, chrono_tz::US::Eastern);
+        assert_eq!(format_timestamp(next, chrono_tz::US::Eastern), "2024-01-01 09:00:00 EST");
 
-    let mut interval = time::interval(Duration::from_secs( ONE_HOUR )); // 1 hour = 3600 seconds
+        // Once today's time has passed the summary moves to tomorrow
+        let next = next_summary_time(now, send_at, chrono_tz::UTC);
+        assert_eq!(format_timestamp(next, chrono_tz::UTC), "2024-01-02 09:00:00 UTC");
+    }
 
-    loop {
-        interval.tick().await;
+    #[test]
+    fn test_validate_config_rejects_bad_summary_time() {
+        let config: EndpointConfig = ron::from_str(r#"(endpoints: [], daily_summary: Some((time: "9am")))"#).unwrap();
 
- 
-        pulse_monitor(Arc::clone(&endpoint_config)).await;
+        assert!(validate_config(&config).unwrap_err().starts_with("Invalid daily_summary time '9am'"));
     }
-}
 
+    #[test]
+    fn test_validate_config_rejects_unknown_notifier_severity() {
+        let config: EndpointConfig = ron::from_str(
+            r#"(endpoints: [], severity_thresholds: [(name: "warning", min_overdue_secs: 0, emoji: "⚠️")], notifiers: [
+                (backend: Slack),
+                (backend: Teams, severities: ["warning", "critical", "under-collateralized"]),
+                (backend: Webhook, severities: ["sev1"]),
+            ])"#,
+        )
+        .unwrap();
 
+        assert_eq!(validate_config(&config), Err("notifiers entry for Webhook lists unknown severity 'sev1'".to_string()));
+    }
 
-async fn pulse_monitor(endpoint_config: Arc< Mutex<  MonitorConfig> > ) {
-    // Read and parse the endpoints.ron file
-    let config_content = match fs::read_to_string("src/endpoints.ron") {
-        Ok(content) => content,
-        Err(e) => {
-            eprintln!("Failed to read endpoints.ron file: {}", e);
-            return;
-        }
-    };
+    #[test]
+    fn test_validate_config_rejects_unknown_template_field() {
+        let config: EndpointConfig = ron::from_str(r#"(endpoints: [], alert_template: Some("{bid_id} {amount}"))"#).unwrap();
 
-    let config: EndpointConfig = match ron::from_str(&config_content) {
-        Ok(cfg) => cfg,
-        Err(e) => {
-            eprintln!("Failed to parse endpoints.ron file: {}", e);
-            return;
-        }
-    };
+        assert_eq!(validate_config(&config), Err("alert_template uses unknown field '{amount}'".to_string()));
+    }
 
-    let client = reqwest::Client::new();
+    #[test]
+    fn test_maintenance_window_uses_alert_timezone() {
+        let config: EndpointConfig = ron::from_str(
+            r#"(endpoints: [], maintenance_windows: [
+                (start: "02:00", end: "04:00"),
+                (start: "23:00", end: "01:00", suppress_loan_alerts: true),
+            ])"#,
+        )
+        .unwrap();
+        let windows = &config.maintenance_windows;
+        let active = |utc: &str| {
+            let now = DateTime::parse_from_rfc3339(utc).unwrap().with_timezone(&Utc);
+            active_maintenance_windows(windows, now, chrono_tz::US::Eastern)
+                .map(|window| window.start.as_str())
+                .collect::<Vec<_>>()
+        };
 
+        // 07:30 UTC is 02:30 EST
+        assert_eq!(active("2024-01-15T07:30:00Z"), ["02:00"]);
+        assert_eq!(active("2024-01-15T02:30:00Z"), Vec::<&str>::new());
+        // Spanning midnight: 23:30 and 00:30 EST
+        assert_eq!(active("2024-01-16T04:30:00Z"), ["23:00"]);
+        assert_eq!(active("2024-01-16T05:30:00Z"), ["23:00"]);
+        assert_eq!(active("2024-01-16T06:00:00Z"), Vec::<&str>::new());
+    }
 
-    let total_endpoints_count = config.endpoints.len(); 
+    #[test]
+    fn test_paging_defaults_and_clamps_page_size() {
+        let paging = |config: &str| ron::from_str::<EndpointConfig>(config).unwrap().paging();
 
+        assert_eq!(paging("(endpoints: [])"), Paging { page_size: DEFAULT_PAGE_SIZE, max_pages: DEFAULT_MAX_PAGES });
+        assert_eq!(paging("(endpoints: [], page_size: Some(250), max_pages: Some(2))"), Paging { page_size: 250, max_pages: 2 });
+        assert_eq!(paging("(endpoints: [], page_size: Some(5000))").page_size, MAX_PAGE_SIZE);
 
-    let endpoint_index = endpoint_config.lock().unwrap().get_monitor_index() .clone() ;
+        let config: EndpointConfig = ron::from_str("(endpoints: [], page_size: Some(0))").unwrap();
+        assert!(validate_config(&config).is_err());
+    }
 
-    if let Some(endpoint_data) = config.endpoints.get(endpoint_index) {
-        if endpoint_data.disabled {
-            println!("Skipping disabled endpoint {}: {}", endpoint_index, endpoint_data.url);
-        } else {
-        println!("Querying endpoint {}: {}", endpoint_index, endpoint_data.url);
+    #[test]
+    fn test_validate_config_checks_value_at_risk_thresholds() {
+        let config = |extra: &str| ron::from_str::<EndpointConfig>(&format!("(endpoints: [], {})", extra)).unwrap();
 
-        let chain_id = endpoint_data.chain_id;
+        assert!(validate_config(&config("value_at_risk_thresholds: { 137: 1000000.0 }, price_feed: Some(())")).is_ok());
+        assert!(validate_config(&config("value_at_risk_thresholds: { 137: 1000000.0 }")).unwrap_err().contains("price_feed"));
+        assert!(validate_config(&config("value_at_risk_thresholds: { 137: 0.0 }, price_feed: Some(())")).is_err());
+    }
 
-        // Get auth token from environment if auth_key is specified
-        let auth_token = endpoint_data.auth_key.as_ref().and_then(|key| {
-            let env_var_name = format!("{}", key );
-            match env::var(&env_var_name) {
-                Ok(token) => {
-                    println!("Using authentication for endpoint with key: {}", key);
-                    Some(token)
-                }
-                Err(_) => {
-                    eprintln!("Warning: auth_key '{}' specified but {} environment variable not set", key, env_var_name);
-                    None
-                }
-            }
-        });
+    #[test]
+    fn test_validate_config_rejects_bad_maintenance_window() {
+        let config: EndpointConfig =
+            ron::from_str(r#"(endpoints: [], maintenance_windows: [(start: "2am", end: "04:00")])"#).unwrap();
 
-         let current_timestamp = Utc::now().timestamp();
-          let last_week = current_timestamp - (ONE_DAY as i64);
-          let query_body = format!(r#"
-          {{
-            bids(
-              where: {{
-                nextDueDate_lt: "{}",
-                nextDueDate_gt: "{}",
-                status: "Accepted"
-              }}
-              first: 5
-            ) {{
-              id
-              bidId
-              nextDueDate
-              borrowerAddress
-              status
-              principal
-              lendingToken {{
-                id
-                symbol
-                decimals
-              }}
-            }}
-          }}
-          "#, current_timestamp, last_week);   
-
-        // Construct proper JSON body for GraphQL query
-        let body = serde_json::json!({
-            "query": query_body
-        });
+        assert!(validate_config(&config).unwrap_err().starts_with("Invalid maintenance window time '2am'"));
+    }
 
-        println!("Query body: {}", serde_json::to_string_pretty(&body).unwrap_or_default());
+    #[test]
+    fn test_below_min_principal() {
+        let min_principal = HashMap::from([
+            ("USDC".to_string(), "10".to_string()),
+            ("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2".to_string(), "0.01".to_string()),
+        ]);
+        let bid = |principal: &str, id: &str, symbol: &str, decimals: u32| {
+            serde_json::json!({ "principal": principal, "lendingToken": { "id": id, "symbol": symbol, "decimals": decimals } })
+        };
 
-        // Make the POST request
-        match make_post_request(&client, &endpoint_data.url, body, auth_token.as_deref()).await {
-            Ok(response) => {
-                // Check if the response contains errors
-                let has_errors = if let Ok(json_response) = serde_json::from_str::<serde_json::Value>(&response) {
-                    json_response.get("errors").is_some()
-                } else {
-                    false
-                };
+        assert!(below_min_principal(&bid("9999999", "0xa0b8", "USDC", 6), &min_principal));
+        assert!(!below_min_principal(&bid("10000000", "0xa0b8", "USDC", 6), &min_principal));
+        // Matched by address regardless of case, ahead of symbol
+        assert!(below_min_principal(&bid("1000000000000000", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2", "WETH", 18), &min_principal));
+        assert!(!below_min_principal(&bid("1", "0xdead", "DAI", 18), &min_principal));
+        assert!(!below_min_principal(&bid("1", "0xa0b8", "USDC", 6), &HashMap::new()));
+    }
+
+    #[test]
+    fn test_normalize_address() {
+        assert_eq!(
+            normalize_address(" 0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2 "),
+            "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+        );
+        assert_eq!(normalize_address("0xABCDEF"), normalize_address("0xabcdef"));
+    }
 
-                if has_errors {
-                    eprintln!("✗ GraphQL query returned errors for endpoint: {}", endpoint_data.url);
-                    eprintln!("Response: {}", response);
+    #[test]
+    fn test_bids_without_bid_id_are_not_keyed_together() {
+        let bids = [
+            serde_json::json!({ "id": "0xaaa", "principal": "1000000" }),
+            serde_json::json!({ "id": "0xbbb", "principal": "2000000", "bidId": null }),
+        ];
 
-                    // Get current timestamp in New York time
-                    let now_utc: DateTime<Utc> = Utc::now();
-                    let now_ny = now_utc.with_timezone(&Eastern);
-                    let timestamp = now_ny.format("%Y-%m-%d %H:%M:%S %Z").to_string();
+        // No shared fallback key such as `1:unknown`, each bid is skipped on its own
+        assert!(bids.iter().all(|bid| bid_identifier(bid).is_none()));
+        assert_eq!(bid_identifier(&serde_json::json!({ "bidId": 42 })), None);
+        assert_eq!(bid_identifier(&serde_json::json!({ "bidId": " " })), None);
+        assert_eq!(bid_identifier(&serde_json::json!({ "bidId": "42" })), Some("42"));
+    }
 
-                    let message = format!(
-                        "⚠️ GraphQL Endpoint Failed!\nTimestamp: {}\nEndpoint: {} {}\nError: {}",
-                        timestamp, endpoint_data.name, endpoint_data.url, response
-                    );
+    #[tokio::test]
+    async fn test_run_graphql_query_retries_5xx_and_reports_status() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
 
-                    send_slack_warning(&message).await;
-                } else {
-                    println!("✓ Successfully queried endpoint: {}", endpoint_data.url);
-
-                    // Parse response and check for overdue bids
-                    if let Ok(json_response) = serde_json::from_str::<serde_json::Value>(&response) {
-                        if let Some(bids) = json_response.get("data").and_then(|d| d.get("bids")).and_then(|b| b.as_array()) {
-                            if bids.is_empty() {
-                                println!("No overdue bids found.");
-                            } else {
-                                println!("Found {} overdue bid(s), checking for new alerts...", bids.len());
-
-                                let alerted_bids = load_alerted_bids();
-                                let now_utc: DateTime<Utc> = Utc::now();
-                                let now_ny = now_utc.with_timezone(&Eastern);
-                                let timestamp = now_ny.format("%Y-%m-%d %H:%M:%S %Z").to_string();
-
-                                for bid in bids {
-                                    let bid_id = bid.get("bidId").and_then(|v| v.as_str()).unwrap_or("unknown");
-                                    let bid_key = make_bid_key(chain_id, bid_id);
-
-                                    // Skip if already alerted
-                                    if alerted_bids.contains(&bid_key) {
-                                        println!("Bid {} on chain {} already alerted, skipping.", bid_id, chain_id);
-                                        continue;
-                                    }
-
-                                    let message = format_bid_alert(bid, chain_id, &timestamp);
-
-                                    send_slack_warning(&message).await;
-                                    save_alerted_bid(chain_id, bid_id);
-                                }
-                            }
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler_calls = calls.clone();
+        let app = axum::Router::new()
+            .route(
+                "/flaky",
+                axum::routing::post(move || {
+                    let calls = handler_calls.clone();
+                    async move {
+                        if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                            (axum::http::StatusCode::BAD_GATEWAY, "upstream down".to_string())
+                        } else {
+                            (axum::http::StatusCode::OK, r#"{"data":{"bids":[]}}"#.to_string())
                         }
                     }
-                }
-            }
-            Err(e) => {
-                eprintln!("✗ Failed to query endpoint {}: {}", endpoint_data.url, e);
+                }),
+            )
+            .route("/down", axum::routing::post(|| async { (axum::http::StatusCode::BAD_GATEWAY, "upstream down") }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
 
-                // Get current timestamp in New York time
-                let now_utc: DateTime<Utc> = Utc::now();
-                let now_ny = now_utc.with_timezone(&Eastern);
-                let timestamp = now_ny.format("%Y-%m-%d %H:%M:%S %Z").to_string();
+        let client = reqwest::Client::new();
+        let retry = RetryPolicy { max_retries: 1, base_backoff_ms: 1 };
+        let source = |path: &str| Source {
+            label: path.to_string(),
+            url: format!("http://{}{}", addr, path),
+            auth_token: None,
+            headers: HeaderMap::new(),
+        };
 
-                let message = format!(
-                    "⚠️ GraphQL Endpoint Failed!\nTimestamp: {}\nEndpoint: {} {}\nError: {}",
-                    timestamp, endpoint_data.name,  endpoint_data.url, e
-                );
+        let response = run_graphql_query(&client, &source("/flaky"), serde_json::json!({}), retry).await.unwrap();
+        assert_eq!(response["data"]["bids"], serde_json::json!([]));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let error = run_graphql_query(&client, &source("/down"), serde_json::json!({}), retry).await.unwrap_err();
+        assert_eq!(error.to_string(), "HTTP 502 Bad Gateway: upstream down");
+    }
+
+    #[test]
+    fn test_graphql_errors_are_listed_one_per_line() {
+        let response = serde_json::json!({
+            "errors": [
+                {
+                    "message": "Type `Bid` has no field `collateralz`",
+                    "locations": [{ "line": 12, "column": 7 }]
+                },
+                {
+                    "message": "indexing_error",
+                    "path": ["bids", 0, "collateral"]
+                },
+                { "message": "Store error: database unavailable" }
+            ]
+        });
+        let error = QueryError::GraphQl {
+            messages: graphql_error_messages(&response["errors"]),
+            raw: response.to_string(),
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "GraphQL errors:\n\
+             • Type `Bid` has no field `collateralz` (line 12, column 7)\n\
+             • indexing_error (path bids.0.collateral)\n\
+             • Store error: database unavailable"
+        );
+        assert_eq!(graphql_error_messages(&serde_json::json!("rate limited")), ["\"rate limited\""]);
+    }
+
+    #[test]
+    fn test_is_listed_borrower_ignores_case() {
+        let borrowers = vec!["0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2".to_string()];
+        let bid = |borrower: &str| serde_json::json!({ "borrowerAddress": borrower });
+
+        assert!(is_listed_borrower(&bid("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"), &borrowers));
+        assert!(is_listed_borrower(&bid("0xC02AAA39B223FE8D0A0E5C4F27EAD9083C756CC2"), &borrowers));
+        assert!(!is_listed_borrower(&bid("0xdeadbeef"), &borrowers));
+        assert!(!is_listed_borrower(&serde_json::json!({}), &borrowers));
+        assert!(!is_listed_borrower(&bid("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"), &[]));
+    }
+
+    #[test]
+    fn test_lock_or_recover_survives_poisoned_lock() {
+        let mutex = Arc::new(Mutex::new(1));
+        let poisoner = mutex.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("poison the lock");
+        })
+        .join();
+        assert!(mutex.is_poisoned());
 
-                send_slack_warning(&message).await;
+        *lock_or_recover(&mutex, "test") += 1;
+
+        assert!(!mutex.is_poisoned());
+        assert_eq!(*mutex.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_collect_polls_survives_panicking_task() {
+        let mut polls = JoinSet::new();
+        let mut names = HashMap::new();
+        polls.spawn(async { Ok(PulseOutcome { bids_found: 3, alerts_sent: 2, alerts_queued: 1, errors: vec![] }) });
+        polls.spawn(async { Ok(PulseOutcome { bids_found: 1, alerts_sent: 0, alerts_queued: 0, errors: vec!["sync lag".to_string()] }) });
+        polls.spawn(async { Err(anyhow::anyhow!("query failed")) });
+        let panicking = polls.spawn(async { panic!("poll bug") });
+        names.insert(panicking.id(), "Polygon".to_string());
+
+        let summary = collect_polls(polls, &names).await;
+
+        assert_eq!(
+            summary,
+            CycleSummary { polled: 4, failed: 2, panicked: 1, bids_found: 4, alerts_sent: 2, alerts_queued: 1 }
+        );
+        assert!(!summary.succeeded());
+    }
+
+    #[test]
+    fn test_stagger_offset_spreads_across_interval() {
+        let offsets: Vec<Duration> = (0..4).map(|position| stagger_offset(3600, position, 4)).collect();
+
+        assert_eq!(offsets, [0, 900, 1800, 2700].map(Duration::from_secs));
+        assert_eq!(stagger_offset(3600, 0, 0), Duration::ZERO);
+    }
+
+    /// A fresh temp dir for a test's alerted bids file and retry queue, removed on drop. Create
+    /// it before the contexts using it: they're dropped first, which flushes their store
+    /// writers, so nothing is still writing into the dir when it's removed.
+    struct TestStore {
+        dir: PathBuf,
+        path: PathBuf,
+    }
+
+    impl TestStore {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self { path: dir.join("alerted_bids.csv"), dir }
+        }
+
+        /// A context polling with `config` and sending to `notifier`, keeping its state here.
+        fn context(&self, config: EndpointConfig, notifier: RecordingNotifier) -> MonitorContext {
+            MonitorContext {
+                client: reqwest::Client::new(),
+                config,
+                retry_queue: Mutex::new(RetryQueue::load(self.path.with_extension("queue.jsonl"), DEFAULT_MAX_QUEUED_ALERTS)),
+                store: Mutex::new(AlertStore::load(&self.path)),
+                audit: AuditLog::default(),
+                poll_permits: Semaphore::new(1),
+                notifier: Box::new(notifier),
+                default_interval_secs: 60,
+                timezone: DEFAULT_TIMEZONE,
+                prices: None,
+                ens: None,
+                pagerduty: None,
+                escalated: Mutex::new(HashMap::new()),
+                workspace_notifiers: HashMap::new(),
+                tokens: Mutex::new(TokenCache::default()),
+                health: Mutex::new(HealthState::new(0)),
+                unchanged_results: Mutex::new(HashMap::new()),
+                all_clear_sent: Mutex::new(HashMap::new()),
+                slack_signing_secret: None,
+                shutdown: watch::channel(false).0,
             }
         }
+    }
+
+    impl Drop for TestStore {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
         }
     }
 
-    // Always increment index to move to next endpoint, even if current one failed
-    let mut next_endpoint_index = endpoint_index + 1;
-    if next_endpoint_index >= total_endpoints_count {
-        next_endpoint_index = 0;
+    fn test_bid(bid_id: &str, status: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": format!("0x{}", bid_id),
+            "bidId": bid_id,
+            "borrowerAddress": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+            "principal": "1000000",
+            "lendingToken": { "symbol": "USDC", "decimals": 6 },
+            "nextDueDate": "1700000000",
+            "status": status
+        })
     }
 
-    endpoint_config.lock().unwrap().set_monitor_index(next_endpoint_index);
+    /// Subgraph answering the overdue query with `overdue` and lookups by bid ID with `by_id`.
+    async fn serve_bids(overdue: Vec<serde_json::Value>, by_id: Vec<serde_json::Value>) -> std::net::SocketAddr {
+        serve_subgraph(move |body| {
+            let lookup = body["query"].as_str().is_some_and(|query| query.contains("bidId_in"));
+            if lookup { by_id.clone() } else { overdue.clone() }
+        })
+        .await
+    }
 
-}
+    /// Subgraph answering each request with the bids `answer` picks for its JSON body.
+    async fn serve_subgraph(
+        answer: impl Fn(&serde_json::Value) -> Vec<serde_json::Value> + Clone + Send + Sync + 'static,
+    ) -> std::net::SocketAddr {
+        let app = axum::Router::new().route(
+            "/subgraph",
+            axum::routing::post(move |axum::Json(body): axum::Json<serde_json::Value>| async move {
+                axum::Json(serde_json::json!({ "data": { "bids": answer(&body) } }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
 
-/*
-async fn query_endpoint(endpoint_config: Arc< &MonitorConfig> ) {
+    /// Subgraph answering every query with the same two overdue bids.
+    async fn serve_overdue_bids() -> std::net::SocketAddr {
+        let bids = vec![test_bid("1", "Accepted"), test_bid("2", "Accepted")];
+        serve_bids(bids.clone(), bids).await
+    }
 
-}*/ 
+    #[tokio::test]
+    async fn test_pulse_alerts_each_overdue_bid_once() {
+        let addr = serve_overdue_bids().await;
+        let config = config_with_endpoints(&format!(r#"(name: "polygon", url: "http://{}/subgraph", chain_id: 137, auth_key: None)"#, addr));
+        let test_store = TestStore::new("pulse_alert_test");
 
-async fn send_slack_warning(message: &str) {
+        let notifier = RecordingNotifier::default();
+        let sent = notifier.sent();
+        let ctx = test_store.context(config, notifier);
+        let endpoint = &ctx.config.endpoints[0];
 
-    println!("sending slack warning ");
+        let outcome = pulse_monitor(&ctx, endpoint).await.unwrap();
+        assert_eq!(outcome, PulseOutcome { bids_found: 2, alerts_sent: 2, alerts_queued: 0, errors: vec![] });
+        {
+            let sent = sent.lock().unwrap();
+            assert_eq!(sent.len(), 2);
+            assert!(sent.iter().all(|message| message.starts_with("🚨 Overdue Loan Alert!")));
+            assert!(sent[0].contains("Bid ID: 1") && sent[1].contains("Bid ID: 2"));
+        }
 
-    let token = match env::var("SLACK_OAUTH_TOKEN") {
-        Ok(t) => t,
-        Err(_) => {
-            eprintln!("SLACK_OAUTH_TOKEN environment variable not set, skipping Slack notification");
-            return;
+        // Both bids are in the store now and still overdue, so nothing more is sent
+        let outcome = pulse_monitor(&ctx, endpoint).await.unwrap();
+        assert_eq!(outcome, PulseOutcome { bids_found: 2, alerts_sent: 0, alerts_queued: 0, errors: vec![] });
+        assert_eq!(sent.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_pulse_sends_liquidation_opportunities_once() {
+        let repaid = Arc::new(AtomicBool::new(false));
+        let bid_repaid = repaid.clone();
+        let addr = serve_subgraph(move |body| {
+            if body["variables"]["statuses"] == serde_json::json!(["Defaulted"]) {
+                if bid_repaid.load(Ordering::SeqCst) { Vec::new() } else { vec![test_bid("3", "Defaulted")] }
+            } else {
+                vec![test_bid("1", "Accepted"), test_bid("2", "Accepted")]
+            }
+        })
+        .await;
+
+        let config = config_with_endpoints(&format!(
+            r#"(name: "polygon", url: "http://{}/subgraph", chain_id: 137, auth_key: None, liquidation: Some(()))"#,
+            addr
+        ));
+        let test_store = TestStore::new("pulse_liquidation_test");
+
+        let notifier = RecordingNotifier::default();
+        let sent = notifier.sent();
+        let ctx = test_store.context(config, notifier);
+        let endpoint = &ctx.config.endpoints[0];
+
+        pulse_monitor(&ctx, endpoint).await.unwrap();
+        {
+            let sent = sent.lock().unwrap();
+            assert_eq!(sent.len(), 3);
+            assert!(sent[2].starts_with("🔨 Liquidation opportunity\n"));
+            assert!(sent[2].contains("Bid ID: 3\n"));
         }
-    };
+        assert!(ctx.store().get(&make_liquidation_key(137, "3")).is_some());
+        // The liquidation key is kept apart from the overdue alerts the recovery check goes through
+        assert_eq!(ctx.store().bid_ids_for_chain(137).len(), 2);
 
-    let bot = SlackBot::new(token);
+        pulse_monitor(&ctx, endpoint).await.unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 3);
 
-    match bot.send_message("#webserver-alerts", message).await {
-        Ok(_) => println!("Slack alert sent successfully"),
-        Err(e) => eprintln!("Failed to send Slack alert: {}", e),
+        // Once the bid drops out of the liquidatable statuses its key goes, so it's alerted on again if it returns
+        repaid.store(true, Ordering::SeqCst);
+        pulse_monitor(&ctx, endpoint).await.unwrap();
+        assert!(ctx.store().get(&make_liquidation_key(137, "3")).is_none());
+        repaid.store(false, Ordering::SeqCst);
+        pulse_monitor(&ctx, endpoint).await.unwrap();
+        {
+            let sent = sent.lock().unwrap();
+            assert_eq!(sent.len(), 4);
+            assert!(sent[3].contains("Bid ID: 3\n"));
+        }
     }
-}
 
+    /// Price source quoting every token at one unit of fiat.
+    struct UnitPrices;
 
-/*
-async fn get_cursor_block() -> Result<U256, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-    
-    //hit hasura for the cursor 
-    let url = "https://hasura-mainnet.nfteller.org/v1/graphql";
-    let body = serde_json::json!({
-        "query": "query MyQuery { cursors { block_id block_num cursor id } }"
-    });
-    
-    match make_post_request(&client, url, body).await {
-        Ok(response) => {
-            println!("Hasura GraphQL request successful:");
-            println!("{}", response);
-            
-            // Parse the response to get the cursor block
-            parse_cursor_response(&response)
+    #[async_trait::async_trait]
+    impl crate::price::PriceSource for UnitPrices {
+        async fn token_price(&self, _chain_id: i32, _token_address: &str, _currency: &str) -> anyhow::Result<f64> {
+            Ok(1.0)
         }
-        Err(e) => {
-            Err(e.into())
+    }
+
+    #[tokio::test]
+    async fn test_pulse_sends_undercollateralized_bids_before_they_are_due() {
+        let mut bid = test_bid("4", "Accepted");
+        bid["nextDueDate"] = serde_json::json!((Utc::now().timestamp() + 86_400).to_string());
+        bid["lendingToken"]["id"] = serde_json::json!("0xusdc");
+        bid["collateral"] = serde_json::json!([{ "amount": "500000", "type": "ERC20", "token": { "id": "0xweth", "symbol": "WETH", "decimals": 6 } }]);
+        let bid = Arc::new(Mutex::new(bid));
+        let served_bid = bid.clone();
+        // Only the liquidation query asks for bids whatever their due date
+        let addr = serve_subgraph(move |body| {
+            if body["variables"]["dueSince"] == "0" { vec![served_bid.lock().unwrap().clone()] } else { Vec::new() }
+        })
+        .await;
+        let config = config_with_endpoints(&format!(
+            r#"(name: "polygon", url: "http://{}/subgraph", chain_id: 137, auth_key: None,
+                liquidation: Some((max_collateral_ratio: Some(1.0))))"#,
+            addr
+        ));
+        let test_store = TestStore::new("pulse_undercollateralized_test");
+
+        let notifier = RecordingNotifier::default();
+        let sent = notifier.sent();
+        let mut ctx = test_store.context(config, notifier);
+        ctx.prices = Some(PriceCache::new(Box::new(UnitPrices), "usd", Duration::from_secs(60)));
+        let endpoint = &ctx.config.endpoints[0];
+        pulse_monitor(&ctx, endpoint).await.unwrap();
+        {
+            let sent = sent.lock().unwrap();
+            assert_eq!(sent.len(), 1);
+            assert!(sent[0].starts_with("🔨 Liquidation opportunity\n"));
+            assert!(sent[0].contains("Bid ID: 4\n"));
         }
+        assert!(ctx.store().get(&make_liquidation_key(137, "4")).is_some());
+
+        // Topped up collateral takes it out of liquidation, and falling short again alerts anew
+        let set_collateral = |amount: &str| bid.lock().unwrap()["collateral"][0]["amount"] = serde_json::json!(amount);
+        set_collateral("2000000");
+        pulse_monitor(&ctx, endpoint).await.unwrap();
+        assert!(ctx.store().get(&make_liquidation_key(137, "4")).is_none());
+        set_collateral("500000");
+        pulse_monitor(&ctx, endpoint).await.unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 2);
     }
-}*/
 
-async fn make_post_request(client: &reqwest::Client, url: &str, body: serde_json::Value, auth_token: Option<&str>) -> Result<String, reqwest::Error> {
+    #[test]
+    fn test_liquidation_config() {
+        let config = config_with_endpoints(
+            r#"(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: None, statuses: ["Accepted", "Late"],
+                liquidation: Some((statuses: ["Defaulted", "Late"], max_collateral_ratio: Some(1.0))))"#,
+        );
+        assert_eq!(
+            validate_config(&config),
+            Err("Endpoint 'a' liquidation max_collateral_ratio needs a price_feed".to_string())
+        );
+        let liquidation = config.endpoints[0].liquidation.as_ref().unwrap();
+        assert_eq!(liquidation.query_statuses(&config.endpoints[0].statuses), ["Defaulted", "Late", "Accepted"]);
 
-    let mut request = client
-        .post(url)
-        .header("Content-Type", "application/json")
-        .json(&body);
+        let options = FormatOptions::default();
+        assert!(liquidation.is_liquidatable(&serde_json::json!({ "status": "Defaulted" }), &options));
+        // Without prices there's no collateral ratio to go by
+        assert!(!liquidation.is_liquidatable(&serde_json::json!({ "status": "Accepted" }), &options));
 
-    // Add Bearer token if provided
-    if let Some(token) = auth_token {
-        request = request.bearer_auth(token);
+        let config = config_with_endpoints(
+            r#"(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: None, liquidation: Some((statuses: [])))"#,
+        );
+        assert_eq!(validate_config(&config), Err("Endpoint 'a' has no liquidation statuses to monitor".to_string()));
     }
 
-    let response = request.send().await?;
+    #[tokio::test]
+    async fn test_pulse_batches_alerts_per_severity_route() {
+        let addr = serve_overdue_bids().await;
+        let config: EndpointConfig = ron::from_str(&format!(
+            r#"(endpoints: [(name: "polygon", url: "http://{}/subgraph", chain_id: 137, auth_key: None)], batch_alerts: true,
+                slack_workspaces: {{ "risk": (token_env: "SLACK_RISK_TOKEN") }},
+                severity_thresholds: [(name: "late", min_overdue_secs: 1, emoji: "🔥", slack_workspace: Some("risk"))])"#,
+            addr
+        ))
+        .unwrap();
+        let test_store = TestStore::new("pulse_batch_route_test");
 
-    let text = response.text().await?;
-    Ok(text)
-}
+        let default = RecordingNotifier::default();
+        let risk = RecordingNotifier::default();
+        let (default_sent, risk_sent) = (default.sent(), risk.sent());
+        let mut ctx = test_store.context(config, default);
+        ctx.workspace_notifiers.insert("risk".to_string(), Box::new(risk));
+        pulse_monitor(&ctx, &ctx.config.endpoints[0]).await.unwrap();
 
-/*
-async fn get_alchemy_block(client: &reqwest::Client) -> Result<U256, Box<dyn std::error::Error>> {
-    let api_key = env::var("ALCHEMY_API_KEY")
-        .map_err(|_| "ALCHEMY_API_KEY environment variable not set")?;
-    
-    let url = format!("https://eth-mainnet.g.alchemy.com/v2/{}", api_key);
-    
-    let body = serde_json::json!({
-        "jsonrpc": "2.0",
-        "method": "eth_blockNumber",
-        "params": [],
-        "id": 1
-    });
-    
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
-        .await?;
-    
-    let json: serde_json::Value = response.json().await?;
-    
-    if let Some(result) = json.get("result") {
-        if let Some(block_hex) = result.as_str() {
-            // Parse hex string to U256
-            let block_number = U256::from_str_radix(block_hex, 16)?;
-            return Ok(block_number);
-        }
-    }
-    
-    Err("Failed to parse block number from Alchemy response".into())
-}
-
-fn parse_cursor_response(response: &str) -> Result<U256, Box<dyn std::error::Error>> {
-    let json: serde_json::Value = serde_json::from_str(response)?;
-    
-    // Navigate to data.cursors array
-    if let Some(data) = json.get("data") {
-        if let Some(cursors) = data.get("cursors") {
-            if let Some(cursors_array) = cursors.as_array() {
-                // Find the cursor with the highest block_num
-                let mut max_block = U256::zero();
-                for cursor in cursors_array {
-                    if let Some(block_num) = cursor.get("block_num") {
-                        let block_val = if let Some(num) = block_num.as_u64() {
-                            U256::from(num)
-                        } else if let Some(str_val) = block_num.as_str() {
-                            U256::from_dec_str(str_val)?
-                        } else {
-                            continue;
-                        };
-                        
-                        if block_val > max_block {
-                            max_block = block_val;
-                        }
-                    }
-                }
-                if max_block > U256::zero() {
-                    return Ok(max_block);
+        // Both bids are late, so their batch goes to the severity's workspace and not the endpoint's
+        assert!(default_sent.lock().unwrap().is_empty());
+        let risk_sent = risk_sent.lock().unwrap();
+        assert_eq!(risk_sent.len(), 1);
+        assert!(risk_sent[0].starts_with("📦 2 Overdue Loan Alert(s) on Chain 137"));
+        assert!(ctx.store().get(&make_bid_key(137, "1")).is_some() && ctx.store().get(&make_bid_key(137, "2")).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_pulse_sends_to_configured_slack_workspace() {
+        let addr = serve_overdue_bids().await;
+        let config = |extra: &str| -> EndpointConfig {
+            ron::from_str(&format!(
+                r#"(endpoints: [
+                    (name: "polygon", url: "http://{0}/subgraph", chain_id: 137, auth_key: None, slack_workspace: Some("ops")),
+                    (name: "mainnet", url: "http://{0}/subgraph", chain_id: 1, auth_key: None),
+                ], slack_workspaces: {{ "ops": (token_env: "SLACK_OPS_TOKEN"), "risk": (token_env: "SLACK_RISK_TOKEN") }}, {1})"#,
+                addr, extra
+            ))
+            .unwrap()
+        };
+        let test_store = TestStore::new("pulse_workspace_test");
+        let default = RecordingNotifier::default();
+        let ops = RecordingNotifier::default();
+        let (default_sent, ops_sent) = (default.sent(), ops.sent());
+        let mut ctx = test_store.context(config(""), default);
+        ctx.workspace_notifiers.insert("ops".to_string(), Box::new(ops));
+        pulse_monitor(&ctx, &ctx.config.endpoints[0]).await.unwrap();
+        pulse_monitor(&ctx, &ctx.config.endpoints[1]).await.unwrap();
+        assert!(ops_sent.lock().unwrap().iter().all(|message| message.contains("Chain: Polygon (137)")));
+        assert_eq!(ops_sent.lock().unwrap().len(), 2);
+        assert!(default_sent.lock().unwrap().iter().all(|message| message.contains("Chain: Ethereum (1)")));
+        assert_eq!(default_sent.lock().unwrap().len(), 2);
+
+        // A severity's workspace wins over the endpoint's
+        ctx.store().flush();
+        let _ = fs::remove_file(&test_store.path);
+        let risk = RecordingNotifier::default();
+        let risk_sent = risk.sent();
+        let thresholds = r#"severity_thresholds: [(name: "late", min_overdue_secs: 1, emoji: "🔥", slack_workspace: Some("risk"))]"#;
+        let mut ctx = test_store.context(config(thresholds), RecordingNotifier::default());
+        ctx.workspace_notifiers.insert("risk".to_string(), Box::new(risk));
+        pulse_monitor(&ctx, &ctx.config.endpoints[0]).await.unwrap();
+        assert_eq!(risk_sent.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_validate_config_rejects_unknown_slack_workspace() {
+        let config = config_with_endpoints(
+            r#"(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: None, slack_workspace: Some("ops"))"#,
+        );
+        assert_eq!(validate_config(&config), Err("Endpoint 'a' uses unknown slack_workspace 'ops'".to_string()));
+
+        let config: EndpointConfig = ron::from_str(
+            r#"(endpoints: [], severity_thresholds: [(name: "late", min_overdue_secs: 1, emoji: "🔥", slack_workspace: Some("ops"))])"#,
+        )
+        .unwrap();
+        assert_eq!(validate_config(&config), Err("Severity 'late' uses unknown slack_workspace 'ops'".to_string()));
+    }
+
+    #[test]
+    fn test_format_preflight_table() {
+        let checks = [
+            PreflightCheck {
+                endpoint: "polygon".to_string(),
+                url: "https://example.com/polygon".to_string(),
+                result: Ok(Duration::from_millis(120)),
+            },
+            PreflightCheck {
+                endpoint: "base".to_string(),
+                url: "https://example.com/b".to_string(),
+                result: Err("HTTP 401 Unauthorized: invalid api key".to_string()),
+            },
+        ];
+
+        assert_eq!(
+            format_preflight_table(&checks),
+            "PASS  polygon  https://example.com/polygon  120ms\n\
+             FAIL  base     https://example.com/b        HTTP 401 Unauthorized: invalid api key"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_preflight_checks_every_url() {
+        let addr = serve_overdue_bids().await;
+        let config = config_with_endpoints(&format!(
+            r#"(name: "polygon", url: "http://{0}/subgraph", chain_id: 137, auth_key: None,
+                fallback_urls: [(url: "http://{0}/missing")])"#,
+            addr
+        ));
+        let test_store = TestStore::new("preflight_test");
+        let ctx = test_store.context(config, RecordingNotifier::default());
+
+        let checks = preflight(&ctx).await;
+        assert_eq!(checks.len(), 2);
+        assert!(checks[0].result.is_ok());
+        assert!(checks[1].result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pulse_holds_back_alerts_over_cap() {
+        let addr = serve_overdue_bids().await;
+        let mut config = config_with_endpoints(&format!(r#"(name: "polygon", url: "http://{}/subgraph", chain_id: 137, auth_key: None)"#, addr));
+        config.max_alerts_per_cycle = Some(1);
+        let test_store = TestStore::new("pulse_cap_test");
+
+        let notifier = RecordingNotifier::default();
+        let sent = notifier.sent();
+        let ctx = test_store.context(config, notifier);
+        let endpoint = &ctx.config.endpoints[0];
+
+        let outcome = pulse_monitor(&ctx, endpoint).await.unwrap();
+        assert_eq!(outcome.alerts_sent, 1);
+        {
+            let sent = sent.lock().unwrap();
+            assert_eq!(sent.len(), 2);
+            assert!(sent[0].contains("Bid ID: 1"));
+            assert!(sent[1].starts_with("🚧 Alert Cap Reached!"));
+            assert!(sent[1].contains("2 overdue loans detected, exceeding the alert cap of 1"));
+        }
+        assert!(ctx.store().get(&make_bid_key(137, "2")).is_none());
+
+        // The held back bid goes out on the next poll
+        let outcome = pulse_monitor(&ctx, endpoint).await.unwrap();
+        assert_eq!(outcome.alerts_sent, 1);
+        assert!(sent.lock().unwrap()[2].contains("Bid ID: 2"));
+    }
+
+    #[tokio::test]
+    async fn test_pulse_posts_all_clear_once_per_interval() {
+        let addr = serve_bids(Vec::new(), Vec::new()).await;
+        let mut config = config_with_endpoints(&format!(
+            r#"(name: "polygon", url: "http://{0}/subgraph", chain_id: 137, auth_key: None),
+               (name: "polygon backup", url: "http://{0}/subgraph", chain_id: 137, auth_key: None)"#,
+            addr
+        ));
+        config.post_all_clear = true;
+        config.all_clear_channel = Some("#loans-fyi".to_string());
+        let test_store = TestStore::new("pulse_all_clear_test");
+
+        let notifier = RecordingNotifier::default();
+        let sent = notifier.sent();
+        let ctx = test_store.context(config, notifier);
+
+        pulse_monitor(&ctx, &ctx.config.endpoints[0]).await.unwrap();
+        pulse_monitor(&ctx, &ctx.config.endpoints[1]).await.unwrap();
+        pulse_monitor(&ctx, &ctx.config.endpoints[0]).await.unwrap();
+        {
+            let sent = sent.lock().unwrap();
+            assert_eq!(sent.len(), 1);
+            assert!(sent[0].starts_with("✅ No overdue loans on Polygon (137)"));
+        }
+
+        // A poll interval later it is posted again
+        lock_or_recover(&ctx.all_clear_sent, "all clear times").insert(137, Utc::now().timestamp() - 60);
+        pulse_monitor(&ctx, &ctx.config.endpoints[0]).await.unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_pulse_escalates_after_consecutive_failures() {
+        let down = Arc::new(AtomicBool::new(true));
+        let subgraph_down = down.clone();
+        let app = axum::Router::new().route(
+            "/subgraph",
+            axum::routing::post(move || async move {
+                if subgraph_down.load(Ordering::SeqCst) {
+                    axum::Json(serde_json::json!({ "errors": [{ "message": "indexer unavailable" }] }))
+                } else {
+                    axum::Json(serde_json::json!({ "data": { "bids": [] } }))
                 }
-            }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut config = config_with_endpoints(&format!(r#"(name: "polygon", url: "http://{}/subgraph", chain_id: 137, auth_key: None)"#, addr));
+        config.escalate_after = Some(2);
+        config.critical_channel = Some("#critical".to_string());
+        let test_store = TestStore::new("pulse_escalation_test");
+
+        let notifier = RecordingNotifier::default();
+        let sent = notifier.sent();
+        let ctx = test_store.context(config, notifier);
+        let endpoint = &ctx.config.endpoints[0];
+
+        // The first failure goes to the normal channel, the second escalates, the third is quiet
+        for _ in 0..3 {
+            assert!(pulse_monitor(&ctx, endpoint).await.is_err());
+        }
+        {
+            let sent = sent.lock().unwrap();
+            assert_eq!(sent.len(), 2);
+            assert!(sent[0].starts_with("⚠️ GraphQL Endpoint Failed!"));
+            assert!(sent[1].starts_with("🚨 GraphQL Endpoint Down!"));
+            assert!(sent[1].contains("Consecutive Failures: 2\nError: GraphQL errors:\n• indexer unavailable"));
         }
+
+        down.store(false, Ordering::SeqCst);
+        pulse_monitor(&ctx, endpoint).await.unwrap();
+        {
+            let sent = sent.lock().unwrap();
+            assert_eq!(sent.len(), 4);
+            assert!(sent[2].starts_with("✅ GraphQL Endpoint Recovered!"));
+            assert!(sent[3].starts_with("✅ GraphQL Endpoint Back Up!"));
+        }
+        assert_eq!(ctx.health().endpoint_stats()["polygon"].consecutive_failures, 0);
     }
-    
-    Err("No cursors found or invalid response format".into())
-}
-*/
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[tokio::test]
+    async fn test_pulse_skips_bids_already_in_store() {
+        let addr = serve_overdue_bids().await;
+        let config = config_with_endpoints(&format!(r#"(name: "polygon", url: "http://{}/subgraph", chain_id: 137, auth_key: None)"#, addr));
+        let test_store = TestStore::new("pulse_skip_test");
+
+        let mut store = AlertStore::load(&test_store.path);
+        store.record(137, "1", "1000000", Utc::now().timestamp());
+        store.record(137, "2", "1000000", Utc::now().timestamp());
+        store.flush();
+
+        let notifier = RecordingNotifier::default();
+        let sent = notifier.sent();
+        let ctx = test_store.context(config, notifier);
+
+        let outcome = pulse_monitor(&ctx, &ctx.config.endpoints[0]).await.unwrap();
+        assert_eq!(outcome.alerts_sent, 0);
+        assert!(sent.lock().unwrap().is_empty());
+    }
 
     #[test]
-    fn test_format_bid_alert_with_usdc() {
-        let bid = serde_json::json!({
-            "bidId": "12345",
-            "borrowerAddress": "0xabc123def456",
-            "principal": "1000000",
-            "lendingToken": {
-                "id": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
-                "symbol": "USDC",
-                "decimals": 6
-            },
-            "nextDueDate": "1704067200",
-            "status": "Accepted"
-        });
+    fn test_panic_message() {
+        let payload = std::panic::catch_unwind(|| panic!("Failed to open {}", "alerted bids file")).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "Failed to open alerted bids file");
+        let payload = std::panic::catch_unwind(|| std::panic::panic_any(42)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "Box<dyn Any>");
+        assert_eq!(panic_message(&"boom"), "boom");
+    }
+
+    #[tokio::test]
+    async fn test_pulse_warns_on_unchanged_results() {
+        let addr = serve_overdue_bids().await;
+        let mut config = config_with_endpoints(&format!(r#"(name: "polygon", url: "http://{}/subgraph", chain_id: 137, auth_key: None)"#, addr));
+        config.stale_cycles = Some(1);
+        let test_store = TestStore::new("pulse_unchanged_test");
+
+        let notifier = RecordingNotifier::default();
+        let sent = notifier.sent();
+        let ctx = test_store.context(config, notifier);
+        let stalled_warnings = || sent.lock().unwrap().iter().filter(|msg| msg.starts_with("🧊 Subgraph May Be Stalled!")).count();
 
-        let message = format_bid_alert(&bid, 1, "2024-01-01 12:00:00 EST");
+        // The first repeat is still within stale_cycles
+        pulse_monitor(&ctx, &ctx.config.endpoints[0]).await.unwrap();
+        pulse_monitor(&ctx, &ctx.config.endpoints[0]).await.unwrap();
+        assert_eq!(stalled_warnings(), 0);
 
-        assert!(message.contains("🚨 Overdue Loan Alert!"));
-        assert!(message.contains("Chain ID: 1"));
-        assert!(message.contains("Bid ID: 12345"));
-        assert!(message.contains("Borrower: 0xabc123def456"));
-        assert!(message.contains("Principal Token: USDC"));
-        assert!(message.contains("Principal Amount: 1.00"));
-        assert!(message.contains("Status: Accepted"));
+        pulse_monitor(&ctx, &ctx.config.endpoints[0]).await.unwrap();
+        assert_eq!(stalled_warnings(), 1);
+        assert!(ctx.store().is_endpoint_failing(&unchanged_results_key("polygon")));
+
+        // A different set of bids resets the count
+        assert_eq!(ctx.unchanged_polls("polygon", &[test_bid("9", "ACCEPTED")]), 0);
     }
 
     #[test]
-    fn test_format_bid_alert_with_18_decimals() {
-        let bid = serde_json::json!({
-            "bidId": "99999",
-            "borrowerAddress": "0xdeadbeef",
-            "principal": "5000000000000000000",
-            "lendingToken": {
-                "id": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
-                "symbol": "WETH",
-                "decimals": 18
-            },
-            "nextDueDate": "1704153600",
-            "status": "Accepted"
-        });
+    fn test_poll_jitter_stays_within_bounds() {
+        let scheduled = time::Instant::now() + Duration::from_secs(3600);
+        let offset_ms = |jitter: &mut PollJitter| {
+            let at = jitter.apply(scheduled);
+            if at >= scheduled { (at - scheduled).as_millis() as i64 } else { -((scheduled - at).as_millis() as i64) }
+        };
+
+        let mut jitter = PollJitter::new(30, StdRng::seed_from_u64(7));
+        let offsets: Vec<i64> = (0..1_000).map(|_| offset_ms(&mut jitter)).collect();
+        assert!(offsets.iter().all(|offset| offset.abs() <= 30_000));
+        // Polls move both ways, not just later
+        assert!(offsets.iter().any(|offset| *offset < -15_000) && offsets.iter().any(|offset| *offset > 15_000));
+
+        // The same seed gives the same offsets
+        let mut reseeded = PollJitter::new(30, StdRng::seed_from_u64(7));
+        assert_eq!((0..3).map(|_| offset_ms(&mut reseeded)).collect::<Vec<_>>(), offsets[..3]);
+
+        assert_eq!(PollJitter::new(0, StdRng::seed_from_u64(7)).apply(scheduled), scheduled);
+    }
+
+    #[tokio::test]
+    async fn test_pulse_queues_undelivered_alerts_until_sent() {
+        let addr = serve_overdue_bids().await;
+        let config: EndpointConfig = ron::from_str(&format!(
+            r#"(endpoints: [(name: "polygon", url: "http://{}/subgraph", chain_id: 137, auth_key: None)], batch_alerts: true)"#,
+            addr
+        ))
+        .unwrap();
+        let test_store = TestStore::new("pulse_queue_test");
+
+        let notifier = RecordingNotifier::default();
+        let (sent, failing) = (notifier.sent(), notifier.failing());
+        failing.store(true, std::sync::atomic::Ordering::SeqCst);
+        let ctx = test_store.context(config, notifier);
+        let endpoint = &ctx.config.endpoints[0];
+
+        // Nothing is counted or recorded as sent while the notifier is down, and queued bids aren't alerted twice
+        let outcome = pulse_monitor(&ctx, endpoint).await.unwrap();
+        assert_eq!(outcome, PulseOutcome { bids_found: 2, alerts_sent: 0, alerts_queued: 2, errors: vec![] });
+        let outcome = pulse_monitor(&ctx, endpoint).await.unwrap();
+        assert_eq!(outcome, PulseOutcome { bids_found: 2, alerts_sent: 0, alerts_queued: 0, errors: vec![] });
+        assert!(ctx.store().get(&make_bid_key(137, "1")).is_none());
+        assert!(ctx.retry_queue().is_queued(137, "1") && ctx.retry_queue().is_queued(137, "2"));
+        assert!(sent.lock().unwrap().is_empty());
+
+        // The queue survives a restart
+        assert!(RetryQueue::load(test_store.path.with_extension("queue.jsonl"), 10).is_queued(137, "2"));
+
+        failing.store(false, std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(pulse_monitor(&ctx, endpoint).await.unwrap().alerts_sent, 2);
+        {
+            let sent = sent.lock().unwrap();
+            assert_eq!(sent.len(), 1);
+            assert!(sent[0].starts_with("📦 2 Overdue Loan Alert(s) on Chain 137"));
+        }
+        assert!(ctx.retry_queue().is_empty());
+        assert!(ctx.store().get(&make_bid_key(137, "1")).is_some() && ctx.store().get(&make_bid_key(137, "2")).is_some());
+
+        pulse_monitor(&ctx, endpoint).await.unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_queued_alert_is_resent_only_to_failed_backends() {
+        let addr = serve_overdue_bids().await;
+        let config = config_with_endpoints(&format!(r#"(name: "polygon", url: "http://{}/subgraph", chain_id: 137, auth_key: None)"#, addr));
+        let test_store = TestStore::new("pulse_fan_out_retry_test");
+
+        let (healthy, failing) = (RecordingNotifier::default(), RecordingNotifier::default());
+        let (healthy_sent, failing_sent, down) = (healthy.sent(), failing.sent(), failing.failing());
+        down.store(true, std::sync::atomic::Ordering::SeqCst);
+        let mut ctx = test_store.context(config, RecordingNotifier::default());
+        ctx.notifier = Box::new(MultiNotifier::new(vec![Box::new(healthy), Box::new(failing)]));
+        let endpoint = &ctx.config.endpoints[0];
+
+        let outcome = pulse_monitor(&ctx, endpoint).await.unwrap();
+        assert_eq!((outcome.alerts_sent, outcome.alerts_queued), (0, 2));
+        assert_eq!(healthy_sent.lock().unwrap().len(), 2);
+        assert!(ctx.retry_queue().is_queued(137, "1") && ctx.retry_queue().is_queued(137, "2"));
+
+        // The healthy backend already has both alerts, so only the one that was down gets them
+        down.store(false, std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(pulse_monitor(&ctx, endpoint).await.unwrap().alerts_sent, 2);
+        assert_eq!(healthy_sent.lock().unwrap().len(), 2);
+        assert_eq!(failing_sent.lock().unwrap().len(), 2);
+        assert!(ctx.retry_queue().is_empty());
+        assert!(ctx.store().get(&make_bid_key(137, "1")).is_some() && ctx.store().get(&make_bid_key(137, "2")).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_recovery_notice_is_resent_until_delivered() {
+        let addr = serve_bids(Vec::new(), vec![test_bid("1", "Repaid")]).await;
+        let config = config_with_endpoints(&format!(r#"(name: "polygon", url: "http://{}/subgraph", chain_id: 137, auth_key: None)"#, addr));
+        let test_store = TestStore::new("pulse_recovery_test");
+        AlertStore::load(&test_store.path).record(137, "1", "1000000", 1_000);
+
+        let notifier = RecordingNotifier::default();
+        let (sent, failing) = (notifier.sent(), notifier.failing());
+        failing.store(true, std::sync::atomic::Ordering::SeqCst);
+        let ctx = test_store.context(config, notifier);
+        let endpoint = &ctx.config.endpoints[0];
+
+        pulse_monitor(&ctx, endpoint).await.unwrap();
+        assert!(ctx.store().get(&make_bid_key(137, "1")).is_some());
+
+        failing.store(false, std::sync::atomic::Ordering::SeqCst);
+        pulse_monitor(&ctx, endpoint).await.unwrap();
+        assert!(ctx.store().get(&make_bid_key(137, "1")).is_none());
+        assert_eq!(sent.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_defaulted_bid_is_dropped_without_recovery_notice() {
+        let addr = serve_bids(Vec::new(), vec![test_bid("1", "Defaulted")]).await;
+        let config = config_with_endpoints(&format!(r#"(name: "polygon", url: "http://{}/subgraph", chain_id: 137, auth_key: None)"#, addr));
+        let test_store = TestStore::new("pulse_defaulted_test");
+        AlertStore::load(&test_store.path).record(137, "1", "1000000", 1_000);
+
+        let notifier = RecordingNotifier::default();
+        let sent = notifier.sent();
+        let ctx = test_store.context(config, notifier);
+        let endpoint = &ctx.config.endpoints[0];
+
+        pulse_monitor(&ctx, endpoint).await.unwrap();
+        assert!(ctx.store().get(&make_bid_key(137, "1")).is_none());
+        assert!(sent.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_list_and_clear_alerts() {
+        let test_store = TestStore::new("clear_alerts_test");
+        let mut store = AlertStore::load(&test_store.path);
+        store.record(137, "42", "1000000", 1_704_067_200);
+        store.record_key(make_due_soon_key(137, "43", 1_704_153_600), "5", 1_704_067_200);
+        store.record(1, "7", "2000000", 1_704_067_200);
+
+        assert_eq!(
+            list_alerts(&store, chrono_tz::UTC),
+            "137:42  alerted 2024-01-01 00:00:00 UTC  principal 1000000\n\
+             137:43:due-1704153600  alerted 2024-01-01 00:00:00 UTC  principal 5\n\
+             1:7  alerted 2024-01-01 00:00:00 UTC  principal 2000000\n"
+        );
+
+        assert_eq!(alerts_to_clear(&store, Some(137), Some("42"), false), Ok(HashSet::from(["137:42".to_string()])));
+        assert!(alerts_to_clear(&store, Some(137), Some("99"), false).is_err());
+        assert_eq!(alerts_to_clear(&store, Some(137), None, false).unwrap().len(), 2);
+        assert!(alerts_to_clear(&store, None, None, false).unwrap_err().contains("--yes"));
+
+        let everything = alerts_to_clear(&store, None, None, true).unwrap();
+        assert_eq!(everything.len(), 3);
+        store.remove(&everything);
+        store.flush();
+        assert_eq!(list_alerts(&AlertStore::load(&test_store.path), chrono_tz::UTC), "");
+    }
+
+    #[test]
+    fn test_validation_report() {
+        let config = config_with_endpoints(
+            r#"(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: Some("VALIDATION_REPORT_TEST_KEY"))"#,
+        );
+        let (report, valid) = validation_report(Ok(config), Some("Europe/London"), false);
+        assert!(valid);
+        assert_eq!(
+            report,
+            "✓ Config parsed, 1 endpoints (1 enabled)\n\
+             ✓ Config checks passed\n\
+             ✓ Query templates loaded\n\
+             ⚠ Endpoint 'a' auth_key VALIDATION_REPORT_TEST_KEY is not set\n\
+             ✓ ALERT_TIMEZONE Europe/London is valid\n"
+        );
+
+        let config = config_with_endpoints(
+            r#"(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: None),
+               (name: "a", url: "https://example.com/b", chain_id: 137, auth_key: None)"#,
+        );
+        let (report, valid) = validation_report(Ok(config), Some("Mars/Olympus"), false);
+        assert!(!valid);
+        assert!(report.contains("✗ Duplicate endpoint name 'a'\n"));
+        assert!(report.contains("✗ ALERT_TIMEZONE 'Mars/Olympus' is not a valid timezone\n"));
+
+        let (report, valid) = validation_report(Err("Failed to parse config file x.ron".to_string()), None, false);
+        assert!(!valid);
+        assert_eq!(report, "✗ Failed to parse config file x.ron\n");
+    }
 
-        let message = format_bid_alert(&bid, 137, "2024-01-02 12:00:00 EST");
+    #[test]
+    fn test_validation_report_strict_fails_on_unset_secrets() {
+        let config = || {
+            Ok(ron::from_str(
+                r#"(endpoints: [(name: "a", url: "https://example.com/a", chain_id: 1, auth_key: Some("VALIDATION_STRICT_TEST_KEY"))],
+                    notifiers: [(backend: Teams)],
+                    slack_workspaces: { "risk": (token_env: "VALIDATION_STRICT_TEST_SLACK_TOKEN") })"#,
+            )
+            .unwrap())
+        };
+
+        let (report, valid) = validation_report(config(), None, false);
+        assert!(valid);
+        assert!(report.contains("⚠ Endpoint 'a' auth_key VALIDATION_STRICT_TEST_KEY is not set\n"));
+        assert!(report.contains("⚠ notifiers lists Teams but TEAMS_WEBHOOK_URL is not set\n"));
 
-        assert!(message.contains("Chain ID: 137"));
-        assert!(message.contains("Principal Token: WETH"));
-        assert!(message.contains("Principal Amount: 5.00"));
+        let (report, valid) = validation_report(config(), None, true);
+        assert!(!valid);
+        assert!(report.contains("✗ Endpoint 'a' auth_key VALIDATION_STRICT_TEST_KEY is not set\n"));
+        assert!(report.contains("✗ notifiers lists Teams but TEAMS_WEBHOOK_URL is not set\n"));
+
+        let config = || Ok(ron::from_str(r#"(endpoints: [], slack_workspaces: { "risk": (token_env: "VALIDATION_STRICT_TEST_SLACK_TOKEN") })"#).unwrap());
+        let (report, valid) = validation_report(config(), None, true);
+        assert!(!valid);
+        assert!(report.contains(
+            "✗ Slack workspace 'risk': slack_workspaces entry needs VALIDATION_STRICT_TEST_SLACK_TOKEN to be set\n"
+        ));
     }
 
     #[test]
-    fn test_format_bid_alert_with_missing_fields() {
-        let bid = serde_json::json!({});
+    fn test_weight_divides_the_poll_interval() {
+        let config = config_with_endpoints(
+            r#"(name: "mainnet", url: "https://example.com/a", chain_id: 1, auth_key: None, weight: Some(4)),
+               (name: "polygon", url: "https://example.com/b", chain_id: 137, auth_key: None, poll_interval_secs: Some(600), weight: Some(3)),
+               (name: "sepolia", url: "https://example.com/c", chain_id: 11155111, auth_key: None),
+               (name: "fast", url: "https://example.com/d", chain_id: 10, auth_key: None, poll_interval_secs: Some(2), weight: Some(5))"#,
+        );
+        let intervals: Vec<u64> = config.endpoints.iter().map(|endpoint| endpoint.interval_secs(3600)).collect();
 
-        let message = format_bid_alert(&bid, 1, "2024-01-01 12:00:00 EST");
+        assert_eq!(intervals, [900, 200, 3600, 1]);
 
-        assert!(message.contains("Bid ID: unknown"));
-        assert!(message.contains("Borrower: unknown"));
-        assert!(message.contains("Principal Token: unknown"));
-        assert!(message.contains("Principal Amount: 0.00"));
+        let config = config_with_endpoints(r#"(name: "mainnet", url: "https://example.com/a", chain_id: 1, auth_key: None, weight: Some(0))"#);
+        assert_eq!(validate_config(&config), Err("Endpoint 'mainnet' has weight 0, expected at least 1".to_string()));
     }
-}
\ No newline at end of file
+}
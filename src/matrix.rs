@@ -0,0 +1,162 @@
+use crate::notifier::{Notifier, split_message};
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use serde_json::json;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Homeservers reject events over 64KiB; this leaves room for the HTML copy of the text and
+// multi-byte characters
+const MAX_MESSAGE_CHARS: usize = 12_000;
+
+/// Sends alerts to a Matrix room through the client-server API, as `m.text` messages with an
+/// HTML copy so the header shows in bold.
+pub struct MatrixNotifier {
+    client: Client,
+    homeserver_url: String,
+    access_token: String,
+    room_id: String,
+    // Transaction IDs are the start time plus a counter, so they stay unique across restarts
+    // and the homeserver never drops a message as a repeat of an earlier one
+    txn_prefix: u128,
+    txn_counter: AtomicU64,
+}
+
+impl MatrixNotifier {
+    pub(crate) fn new(client: Client, homeserver_url: String, access_token: String, room_id: String) -> Self {
+        let txn_prefix = SystemTime::now().duration_since(UNIX_EPOCH).map(|since| since.as_millis()).unwrap_or_default();
+        Self {
+            client,
+            homeserver_url,
+            access_token,
+            room_id,
+            txn_prefix,
+            txn_counter: AtomicU64::new(0),
+        }
+    }
+
+    fn next_txn_id(&self) -> String {
+        format!("health-bot-{}-{}", self.txn_prefix, self.txn_counter.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// `PUT /_matrix/client/v3/rooms/{roomId}/send/m.room.message/{txnId}`, with the room ID
+    /// percent-encoded since it contains `!` and `:`.
+    fn send_url(&self, txn_id: &str) -> anyhow::Result<Url> {
+        let mut url = Url::parse(&self.homeserver_url).map_err(|e| anyhow::anyhow!("Invalid MATRIX_HOMESERVER_URL: {}", e))?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("Invalid MATRIX_HOMESERVER_URL: cannot have a path"))?
+            .pop_if_empty()
+            .extend(["_matrix", "client", "v3", "rooms", &self.room_id, "send", "m.room.message", txn_id]);
+        Ok(url)
+    }
+
+    pub async fn send_message(&self, text: &str) -> anyhow::Result<()> {
+        let response = self
+            .client
+            .put(self.send_url(&self.next_txn_id())?)
+            .bearer_auth(&self.access_token)
+            .json(&message_content(text))
+            .send()
+            .await
+            .map_err(|e| e.without_url())?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Matrix homeserver returned HTTP {}: {}", status, body);
+        }
+
+        Ok(())
+    }
+}
+
+/// The `m.room.message` event content for `text`, with the first line in bold in the HTML copy.
+pub fn message_content(text: &str) -> serde_json::Value {
+    let mut lines = text.lines().map(escape_html);
+    let header = lines.next().unwrap_or_default();
+    let rest: Vec<String> = lines.collect();
+    let formatted_body = if rest.is_empty() {
+        format!("<strong>{}</strong>", header)
+    } else {
+        format!("<strong>{}</strong><br>{}", header, rest.join("<br>"))
+    };
+
+    json!({
+        "msgtype": "m.text",
+        "body": text,
+        "format": "org.matrix.custom.html",
+        "formatted_body": formatted_body,
+    })
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[async_trait]
+impl Notifier for MatrixNotifier {
+    async fn notify(&self, _channel: Option<&str>, msg: &str) -> anyhow::Result<()> {
+        for chunk in split_message(msg, MAX_MESSAGE_CHARS) {
+            self.send_message(&chunk).await?;
+        }
+        Ok(())
+    }
+
+    fn max_message_chars(&self) -> usize {
+        MAX_MESSAGE_CHARS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_message_content() {
+        let content = message_content("🚨 Overdue Loan Alert! [🟡 <1d]\nBid ID: 42\nBorrower: <unknown> & co");
+        assert_eq!(content["msgtype"], "m.text");
+        assert_eq!(content["body"], "🚨 Overdue Loan Alert! [🟡 <1d]\nBid ID: 42\nBorrower: <unknown> & co");
+        assert_eq!(
+            content["formatted_body"],
+            "<strong>🚨 Overdue Loan Alert! [🟡 &lt;1d]</strong><br>Bid ID: 42<br>Borrower: &lt;unknown&gt; &amp; co"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_message_uses_a_new_txn_id_each_time() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        let app = axum::Router::new().route(
+            "/_matrix/client/v3/rooms/{room}/send/m.room.message/{txn}",
+            axum::routing::put(
+                move |axum::extract::Path((room, txn)): axum::extract::Path<(String, String)>,
+                      headers: axum::http::HeaderMap,
+                      axum::Json(content): axum::Json<serde_json::Value>| async move {
+                    let auth = headers.get("authorization").and_then(|value| value.to_str().ok()).unwrap_or_default().to_string();
+                    recorded.lock().unwrap().push((room, txn, auth, content));
+                    axum::Json(json!({ "event_id": "$event" }))
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let matrix = MatrixNotifier::new(
+            Client::new(),
+            format!("http://{}/", addr),
+            "secret".to_string(),
+            "!ops:example.org".to_string(),
+        );
+        matrix.notify(None, "first").await.unwrap();
+        matrix.notify(None, "second").await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].0, "!ops:example.org");
+        assert_eq!(requests[0].2, "Bearer secret");
+        assert_eq!(requests[1].3["body"], "second");
+        assert_ne!(requests[0].1, requests[1].1);
+    }
+}
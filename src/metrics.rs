@@ -0,0 +1,128 @@
+use prometheus::{Encoder, GaugeVec, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder};
+use std::sync::LazyLock;
+
+/// Prometheus metrics about the monitor itself, served on `/metrics`.
+pub struct Metrics {
+    registry: Registry,
+    /// Overdue bids the last poll found, per chain
+    pub overdue_loans: IntGaugeVec,
+    pub alerts_sent: IntCounter,
+    /// Alerts that failed to send and were queued for retry
+    pub alerts_queued: IntCounter,
+    /// Failed polls and follow-up checks, per endpoint
+    pub endpoint_errors: IntCounterVec,
+    /// Time taken by GraphQL queries, retries included
+    pub graphql_request_duration: Histogram,
+    /// Time taken by the last overdue query, per endpoint
+    pub endpoint_latency: GaugeVec,
+    /// Polls per endpoint, by how the overdue query went
+    pub endpoint_polls: IntCounterVec,
+    /// Failed polls since the endpoint's last success
+    pub endpoint_consecutive_failures: IntGaugeVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let overdue_loans = IntGaugeVec::new(Opts::new("overdue_loans", "Overdue loans found by the last poll"), &["chain_id"])
+            .expect("valid overdue_loans metric");
+        let alerts_sent = IntCounter::new("alerts_sent_total", "Bid alerts and escalations sent").expect("valid alerts_sent metric");
+        let alerts_queued =
+            IntCounter::new("alerts_queued_total", "Bid alerts and escalations queued for retry").expect("valid alerts_queued metric");
+        let endpoint_errors = IntCounterVec::new(
+            Opts::new("endpoint_errors_total", "Failed endpoint polls and follow-up checks"),
+            &["endpoint"],
+        )
+        .expect("valid endpoint_errors metric");
+        let graphql_request_duration = Histogram::with_opts(HistogramOpts::new(
+            "graphql_request_duration_seconds",
+            "GraphQL query latency, including retries",
+        ))
+        .expect("valid graphql_request_duration metric");
+        let endpoint_latency = GaugeVec::new(
+            Opts::new("endpoint_latency_seconds", "Duration of the last overdue bids query"),
+            &["endpoint"],
+        )
+        .expect("valid endpoint_latency metric");
+        let endpoint_polls = IntCounterVec::new(
+            Opts::new("endpoint_polls_total", "Endpoint polls, by result of the overdue bids query"),
+            &["endpoint", "result"],
+        )
+        .expect("valid endpoint_polls metric");
+        let endpoint_consecutive_failures = IntGaugeVec::new(
+            Opts::new("endpoint_consecutive_failures", "Failed polls since the endpoint last succeeded"),
+            &["endpoint"],
+        )
+        .expect("valid endpoint_consecutive_failures metric");
+
+        let registry = Registry::new();
+        registry.register(Box::new(overdue_loans.clone())).expect("overdue_loans registered once");
+        registry.register(Box::new(alerts_sent.clone())).expect("alerts_sent registered once");
+        registry.register(Box::new(alerts_queued.clone())).expect("alerts_queued registered once");
+        registry.register(Box::new(endpoint_errors.clone())).expect("endpoint_errors registered once");
+        registry
+            .register(Box::new(graphql_request_duration.clone()))
+            .expect("graphql_request_duration registered once");
+        registry.register(Box::new(endpoint_latency.clone())).expect("endpoint_latency registered once");
+        registry.register(Box::new(endpoint_polls.clone())).expect("endpoint_polls registered once");
+        registry
+            .register(Box::new(endpoint_consecutive_failures.clone()))
+            .expect("endpoint_consecutive_failures registered once");
+
+        Self {
+            registry,
+            overdue_loans,
+            alerts_sent,
+            alerts_queued,
+            endpoint_errors,
+            graphql_request_duration,
+            endpoint_latency,
+            endpoint_polls,
+            endpoint_consecutive_failures,
+        }
+    }
+
+    /// Renders every metric in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!(error = %e, "Failed to encode metrics");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// The process-wide metrics, shared by every endpoint task.
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_includes_labelled_metrics() {
+        let metrics = Metrics::new();
+        metrics.overdue_loans.with_label_values(&["137"]).set(3);
+        metrics.alerts_sent.inc_by(2);
+        metrics.alerts_queued.inc();
+        metrics.endpoint_errors.with_label_values(&["polygon"]).inc();
+        metrics.graphql_request_duration.observe(0.25);
+        metrics.endpoint_latency.with_label_values(&["polygon"]).set(1.5);
+        metrics.endpoint_polls.with_label_values(&["polygon", "graphql_error"]).inc();
+        metrics.endpoint_consecutive_failures.with_label_values(&["polygon"]).set(1);
+
+        let rendered = metrics.render();
+
+        assert!(rendered.contains("overdue_loans{chain_id=\"137\"} 3"));
+        assert!(rendered.contains("alerts_sent_total 2"));
+        assert!(rendered.contains("alerts_queued_total 1"));
+        assert!(rendered.contains("endpoint_errors_total{endpoint=\"polygon\"} 1"));
+        assert!(rendered.contains("graphql_request_duration_seconds_count 1"));
+        assert!(rendered.contains("endpoint_latency_seconds{endpoint=\"polygon\"} 1.5"));
+        assert!(rendered.contains("endpoint_polls_total{endpoint=\"polygon\",result=\"graphql_error\"} 1"));
+        assert!(rendered.contains("endpoint_consecutive_failures{endpoint=\"polygon\"} 1"));
+    }
+}
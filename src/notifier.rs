@@ -0,0 +1,361 @@
+use crate::format::LinkStyle;
+use async_trait::async_trait;
+use futures::future::join_all;
+use std::fmt;
+use tracing::info;
+
+/// What an alert is about, for backends that send structured payloads.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AlertSubject<'a> {
+    pub chain_id: Option<i32>,
+    pub bid_id: Option<&'a str>,
+    /// Severity of a bid alert, if it has one
+    pub severity: Option<&'a str>,
+}
+
+impl<'a> AlertSubject<'a> {
+    pub fn chain(chain_id: i32) -> Self {
+        Self { chain_id: Some(chain_id), ..Self::default() }
+    }
+
+    pub fn bid(chain_id: i32, bid_id: &'a str, severity: Option<&'a str>) -> Self {
+        Self { chain_id: Some(chain_id), bid_id: Some(bid_id), severity }
+    }
+}
+
+/// A destination for alert messages.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Sends `msg`, to `channel` if given and the backend has channels, otherwise to its default destination.
+    async fn notify(&self, channel: Option<&str>, msg: &str) -> anyhow::Result<()>;
+
+    /// Sends `msg` as an alert about `subject`. Backends that only send text ignore the subject.
+    async fn notify_about(&self, channel: Option<&str>, msg: &str, _subject: AlertSubject<'_>) -> anyhow::Result<()> {
+        self.notify(channel, msg).await
+    }
+
+    /// Sends a Slack Block Kit layout along with its plain text version. Backends without
+    /// blocks just send the text.
+    async fn notify_blocks(
+        &self,
+        channel: Option<&str>,
+        msg: &str,
+        _blocks: &serde_json::Value,
+        subject: AlertSubject<'_>,
+    ) -> anyhow::Result<()> {
+        self.notify_about(channel, msg, subject).await
+    }
+
+    /// Sends an alert that failed before again, only through `backends` when given, the ones
+    /// a `FanOutError` reported as failed. Backends that don't fan out have nothing to skip.
+    async fn resend_about(
+        &self,
+        _backends: Option<&[usize]>,
+        channel: Option<&str>,
+        msg: &str,
+        subject: AlertSubject<'_>,
+    ) -> anyhow::Result<()> {
+        self.notify_about(channel, msg, subject).await
+    }
+
+    /// How links should be written in messages sent through this notifier.
+    fn link_style(&self) -> LinkStyle {
+        LinkStyle::Plain
+    }
+
+    /// Longest message the backend accepts in one post, used to split batched alerts between bids.
+    fn max_message_chars(&self) -> usize {
+        usize::MAX
+    }
+}
+
+/// Logs messages instead of delivering them, used for `--dry-run`.
+pub struct DryRunNotifier;
+
+#[async_trait]
+impl Notifier for DryRunNotifier {
+    async fn notify(&self, channel: Option<&str>, msg: &str) -> anyhow::Result<()> {
+        // `alert` rather than `message`, which is the field the event's own text is logged under
+        info!(channel, alert = %msg, "Dry run: would send alert");
+        Ok(())
+    }
+}
+
+/// Sends every message to all of its notifiers at once. A failing backend doesn't stop the
+/// others; the failures are reported together afterwards.
+pub struct MultiNotifier {
+    notifiers: Vec<Box<dyn Notifier>>,
+}
+
+impl MultiNotifier {
+    pub fn new(notifiers: Vec<Box<dyn Notifier>>) -> Self {
+        Self { notifiers }
+    }
+}
+
+/// A `MultiNotifier` send that some of its backends failed, so a retry can skip the others.
+#[derive(Debug)]
+pub struct FanOutError {
+    /// Positions of the failed backends in the `MultiNotifier`
+    pub failed: Vec<usize>,
+    errors: Vec<String>,
+}
+
+impl fmt::Display for FanOutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.errors.join("; "))
+    }
+}
+
+impl std::error::Error for FanOutError {}
+
+/// The backends a failed send still has to reach, `None` when that's all of them.
+pub fn failed_backends(error: &anyhow::Error) -> Option<Vec<usize>> {
+    error.downcast_ref::<FanOutError>().map(|e| e.failed.clone())
+}
+
+/// Pairs each backend position with its result, failing with the positions that didn't send.
+fn combine_results(backends: impl IntoIterator<Item = usize>, results: Vec<anyhow::Result<()>>) -> anyhow::Result<()> {
+    let mut failed = Vec::new();
+    let mut errors = Vec::new();
+    for (backend, result) in backends.into_iter().zip(results) {
+        if let Err(e) = result {
+            failed.push(backend);
+            errors.push(format!("{:#}", e));
+        }
+    }
+    if failed.is_empty() { Ok(()) } else { Err(FanOutError { failed, errors }.into()) }
+}
+
+#[async_trait]
+impl Notifier for MultiNotifier {
+    async fn notify(&self, channel: Option<&str>, msg: &str) -> anyhow::Result<()> {
+        combine_results(0.., join_all(self.notifiers.iter().map(|notifier| notifier.notify(channel, msg))).await)
+    }
+
+    async fn notify_about(&self, channel: Option<&str>, msg: &str, subject: AlertSubject<'_>) -> anyhow::Result<()> {
+        combine_results(0.., join_all(self.notifiers.iter().map(|notifier| notifier.notify_about(channel, msg, subject))).await)
+    }
+
+    async fn notify_blocks(
+        &self,
+        channel: Option<&str>,
+        msg: &str,
+        blocks: &serde_json::Value,
+        subject: AlertSubject<'_>,
+    ) -> anyhow::Result<()> {
+        let sends = self.notifiers.iter().map(|notifier| notifier.notify_blocks(channel, msg, blocks, subject));
+        combine_results(0.., join_all(sends).await)
+    }
+
+    /// Positions past the end, left over from a longer `notifiers` list before a restart, are skipped.
+    async fn resend_about(
+        &self,
+        backends: Option<&[usize]>,
+        channel: Option<&str>,
+        msg: &str,
+        subject: AlertSubject<'_>,
+    ) -> anyhow::Result<()> {
+        let targets: Vec<usize> = match backends {
+            Some(backends) => backends.iter().copied().filter(|&backend| backend < self.notifiers.len()).collect(),
+            None => (0..self.notifiers.len()).collect(),
+        };
+        let sends = targets.iter().map(|&backend| self.notifiers[backend].notify_about(channel, msg, subject));
+        combine_results(targets.iter().copied(), join_all(sends).await)
+    }
+
+    /// Messages are formatted once for every backend, so Slack links are only used when all of them are Slack.
+    fn link_style(&self) -> LinkStyle {
+        let all_slack = !self.notifiers.is_empty() && self.notifiers.iter().all(|notifier| notifier.link_style() == LinkStyle::Slack);
+        if all_slack { LinkStyle::Slack } else { LinkStyle::Plain }
+    }
+
+    fn max_message_chars(&self) -> usize {
+        self.notifiers.iter().map(|notifier| notifier.max_message_chars()).min().unwrap_or(usize::MAX)
+    }
+}
+
+/// Passes on only bid alerts at one of `severities`, for a backend that should just hear about
+/// the worst loans. Everything else sent to it is dropped.
+pub struct SeverityFilter {
+    severities: Vec<String>,
+    inner: Box<dyn Notifier>,
+}
+
+impl SeverityFilter {
+    pub fn new(severities: Vec<String>, inner: Box<dyn Notifier>) -> Self {
+        Self { severities, inner }
+    }
+
+    fn passes(&self, subject: AlertSubject) -> bool {
+        subject.severity.is_some_and(|severity| self.severities.iter().any(|allowed| allowed == severity))
+    }
+}
+
+#[async_trait]
+impl Notifier for SeverityFilter {
+    async fn notify(&self, _channel: Option<&str>, _msg: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn notify_about(&self, channel: Option<&str>, msg: &str, subject: AlertSubject<'_>) -> anyhow::Result<()> {
+        if !self.passes(subject) {
+            return Ok(());
+        }
+        self.inner.notify_about(channel, msg, subject).await
+    }
+
+    async fn notify_blocks(
+        &self,
+        channel: Option<&str>,
+        msg: &str,
+        blocks: &serde_json::Value,
+        subject: AlertSubject<'_>,
+    ) -> anyhow::Result<()> {
+        if !self.passes(subject) {
+            return Ok(());
+        }
+        self.inner.notify_blocks(channel, msg, blocks, subject).await
+    }
+
+    fn link_style(&self) -> LinkStyle {
+        self.inner.link_style()
+    }
+
+    fn max_message_chars(&self) -> usize {
+        self.inner.max_message_chars()
+    }
+}
+
+/// Test double that keeps every message it is sent, failing each send while `failing` is set.
+#[cfg(test)]
+#[derive(Default)]
+pub struct RecordingNotifier {
+    sent: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    failing: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+#[cfg(test)]
+impl RecordingNotifier {
+    /// Handle on the sent messages that stays usable once the notifier is boxed.
+    pub fn sent(&self) -> std::sync::Arc<std::sync::Mutex<Vec<String>>> {
+        self.sent.clone()
+    }
+
+    /// Switch for failing sends, also usable once the notifier is boxed.
+    pub fn failing(&self) -> std::sync::Arc<std::sync::atomic::AtomicBool> {
+        self.failing.clone()
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl Notifier for RecordingNotifier {
+    async fn notify(&self, _channel: Option<&str>, msg: &str) -> anyhow::Result<()> {
+        if self.failing.load(std::sync::atomic::Ordering::SeqCst) {
+            anyhow::bail!("backend down");
+        }
+        self.sent.lock().unwrap().push(msg.to_string());
+        Ok(())
+    }
+}
+
+/// Splits `msg` into chunks of at most `max_chars` characters for backends with a message size
+/// limit, breaking on line boundaries where possible.
+pub fn split_message(msg: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_chars = 0;
+
+    for line in msg.lines() {
+        let line_chars = line.chars().count();
+        let separator = usize::from(!current.is_empty());
+
+        if current_chars + separator + line_chars <= max_chars {
+            if separator == 1 {
+                current.push('\n');
+            }
+            current.push_str(line);
+            current_chars += separator + line_chars;
+            continue;
+        }
+
+        if !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+            current_chars = 0;
+        }
+
+        // A single line longer than the limit has to be cut mid-line
+        let mut chars = line.chars().peekable();
+        while chars.peek().is_some() {
+            let piece: String = chars.by_ref().take(max_chars).collect();
+            current_chars = piece.chars().count();
+            current = piece;
+            if chars.peek().is_some() {
+                chunks.push(std::mem::take(&mut current));
+                current_chars = 0;
+            }
+        }
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_multi_notifier_sends_to_every_backend() {
+        let (failing, healthy, filtered) = (RecordingNotifier::default(), RecordingNotifier::default(), RecordingNotifier::default());
+        let (down, failing_sent) = (failing.failing(), failing.sent());
+        down.store(true, std::sync::atomic::Ordering::SeqCst);
+        let (healthy_sent, filtered_sent) = (healthy.sent(), filtered.sent());
+        let notifier = MultiNotifier::new(vec![
+            Box::new(failing),
+            Box::new(healthy),
+            Box::new(SeverityFilter::new(vec!["critical".to_string()], Box::new(filtered))),
+        ]);
+
+        let critical = AlertSubject::bid(1, "42", Some("critical"));
+        let error = notifier.notify_about(None, "bid 42", critical).await.unwrap_err();
+        assert_eq!(error.to_string(), "backend down");
+        assert_eq!(failed_backends(&error), Some(vec![0]));
+        notifier.notify(None, "endpoint down").await.unwrap_err();
+
+        assert_eq!(*healthy_sent.lock().unwrap(), ["bid 42", "endpoint down"]);
+        assert_eq!(*filtered_sent.lock().unwrap(), ["bid 42"]);
+
+        // A resend only goes to the backend that missed it
+        down.store(false, std::sync::atomic::Ordering::SeqCst);
+        notifier.resend_about(failed_backends(&error).as_deref(), None, "bid 42", critical).await.unwrap();
+        assert_eq!(*failing_sent.lock().unwrap(), ["bid 42"]);
+        assert_eq!(healthy_sent.lock().unwrap().len(), 2);
+        assert_eq!(filtered_sent.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_split_message_short_message_is_unchanged() {
+        assert_eq!(split_message("line one\nline two", 100), vec!["line one\nline two"]);
+    }
+
+    #[test]
+    fn test_split_message_breaks_on_lines() {
+        let chunks = split_message("aaaa\nbbbb\ncccc", 9);
+
+        assert_eq!(chunks, vec!["aaaa\nbbbb", "cccc"]);
+    }
+
+    #[test]
+    fn test_split_message_cuts_long_lines() {
+        let chunks = split_message(&"x".repeat(25), 10);
+
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 10));
+        assert_eq!(chunks.concat(), "x".repeat(25));
+    }
+}
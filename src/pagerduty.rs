@@ -0,0 +1,91 @@
+use crate::{RetryPolicy, make_post_request_with_retry};
+use reqwest::Client;
+use reqwest::header::HeaderMap;
+use serde_json::json;
+
+/// PagerDuty Events API v2 endpoint.
+pub const EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+/// Opens and resolves PagerDuty incidents for escalated endpoint outages. Events for the same
+/// `dedup_key` land on one incident, so a resolve closes the incident its trigger opened.
+pub struct PagerDuty {
+    client: Client,
+    url: String,
+    routing_key: String,
+    retry: RetryPolicy,
+}
+
+impl PagerDuty {
+    pub(crate) fn new(client: Client, url: String, routing_key: String, retry: RetryPolicy) -> Self {
+        Self { client, url, routing_key, retry }
+    }
+
+    /// Opens (or adds to) the incident for `dedup_key`, titled with `summary`.
+    pub async fn trigger(&self, dedup_key: &str, summary: &str, source: &str) -> anyhow::Result<()> {
+        self.send(json!({
+            "routing_key": self.routing_key,
+            "event_action": "trigger",
+            "dedup_key": dedup_key,
+            "payload": {
+                // PagerDuty rejects summaries over 1024 characters
+                "summary": summary.chars().take(1024).collect::<String>(),
+                "source": source,
+                "severity": "critical",
+            },
+        }))
+        .await
+    }
+
+    pub async fn resolve(&self, dedup_key: &str) -> anyhow::Result<()> {
+        self.send(json!({
+            "routing_key": self.routing_key,
+            "event_action": "resolve",
+            "dedup_key": dedup_key,
+        }))
+        .await
+    }
+
+    async fn send(&self, event: serde_json::Value) -> anyhow::Result<()> {
+        let response = make_post_request_with_retry(&self.client, &self.url, event, None, &HeaderMap::new(), self.retry)
+            .await
+            .map_err(|e| e.without_url())?;
+
+        if !response.status.is_success() {
+            anyhow::bail!("PagerDuty returned HTTP {}: {}", response.status, response.body);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn test_trigger_and_resolve_share_dedup_key() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let app = axum::Router::new().route(
+            "/enqueue",
+            axum::routing::post(move |axum::Json(event): axum::Json<serde_json::Value>| async move {
+                recorded.lock().unwrap().push(event);
+                (axum::http::StatusCode::ACCEPTED, r#"{"status": "success"}"#)
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let retry = RetryPolicy { max_retries: 0, base_backoff_ms: 1 };
+        let pagerduty = PagerDuty::new(Client::new(), format!("http://{}/enqueue", addr), "key".to_string(), retry);
+        pagerduty.trigger("health-bot:endpoint:polygon", "polygon is down", "health_bot").await.unwrap();
+        pagerduty.resolve("health-bot:endpoint:polygon").await.unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events[0]["event_action"], "trigger");
+        assert_eq!(events[0]["payload"], json!({ "summary": "polygon is down", "source": "health_bot", "severity": "critical" }));
+        assert_eq!(events[1], json!({ "routing_key": "key", "event_action": "resolve", "dedup_key": "health-bot:endpoint:polygon" }));
+    }
+}
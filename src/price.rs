@@ -0,0 +1,208 @@
+use crate::lock_or_recover;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+// How long a fetched price is reused before asking the source again
+pub const DEFAULT_PRICE_TTL_SECS: u64 = 300;
+
+// Fiat currency prices are quoted in unless configured otherwise
+pub const DEFAULT_FIAT_CURRENCY: &str = "usd";
+
+const COINGECKO_API_URL: &str = "https://api.coingecko.com/api/v3";
+
+// CoinGecko asset platform ids for the chains we monitor
+const COINGECKO_PLATFORMS: &[(i32, &str)] = &[
+    (1, "ethereum"),
+    (137, "polygon-pos"),
+    (999, "hyperevm"),
+    (8453, "base"),
+    (42161, "arbitrum-one"),
+    (747474, "katana"),
+];
+
+/// A provider of token prices in a fiat currency.
+#[async_trait]
+pub trait PriceSource: Send + Sync {
+    /// Price of one whole token at `token_address` on `chain_id`, in `currency`.
+    async fn token_price(&self, chain_id: i32, token_address: &str, currency: &str) -> anyhow::Result<f64>;
+}
+
+/// Which price provider to use.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+pub enum PriceSourceKind {
+    #[default]
+    CoinGecko,
+}
+
+/// Settings for showing principal amounts in fiat.
+#[derive(Debug, Deserialize)]
+pub struct PriceFeedConfig {
+    #[serde(default)]
+    pub source: PriceSourceKind,
+    /// Currency code understood by the source, defaults to `DEFAULT_FIAT_CURRENCY`
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// How long prices are cached, defaults to `DEFAULT_PRICE_TTL_SECS`
+    #[serde(default)]
+    pub cache_ttl_secs: Option<u64>,
+}
+
+/// CoinGecko's token price API. Uses the demo API key from `COINGECKO_API_KEY` when set.
+pub struct CoinGecko {
+    client: Client,
+    api_key: Option<String>,
+}
+
+impl CoinGecko {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            api_key: env::var("COINGECKO_API_KEY").ok().filter(|key| !key.is_empty()),
+        }
+    }
+}
+
+fn coingecko_platform(chain_id: i32) -> Option<&'static str> {
+    COINGECKO_PLATFORMS
+        .iter()
+        .find(|(id, _)| *id == chain_id)
+        .map(|(_, platform)| *platform)
+}
+
+/// Reads the price out of a `simple/token_price` response, which is keyed by lowercase address.
+fn price_from_coingecko_response(body: &serde_json::Value, token_address: &str, currency: &str) -> Option<f64> {
+    body.get(token_address.to_lowercase())
+        .and_then(|prices| prices.get(currency.to_lowercase()))
+        .and_then(|price| price.as_f64())
+}
+
+#[async_trait]
+impl PriceSource for CoinGecko {
+    async fn token_price(&self, chain_id: i32, token_address: &str, currency: &str) -> anyhow::Result<f64> {
+        let platform = coingecko_platform(chain_id)
+            .ok_or_else(|| anyhow::anyhow!("CoinGecko has no platform for chain {}", chain_id))?;
+
+        let mut request = self
+            .client
+            .get(format!("{}/simple/token_price/{}", COINGECKO_API_URL, platform))
+            .query(&[("contract_addresses", token_address), ("vs_currencies", currency)]);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("x-cg-demo-api-key", api_key);
+        }
+
+        let body: serde_json::Value = request.send().await?.error_for_status()?.json().await?;
+        price_from_coingecko_response(&body, token_address, currency)
+            .ok_or_else(|| anyhow::anyhow!("CoinGecko has no {} price for {}", currency, token_address))
+    }
+}
+
+/// Caches prices from a `PriceSource` per (chain, token) for `ttl`.
+pub struct PriceCache {
+    source: Box<dyn PriceSource>,
+    currency: String,
+    ttl: Duration,
+    entries: Mutex<HashMap<(i32, String), (f64, Instant)>>,
+}
+
+impl PriceCache {
+    pub fn new(source: Box<dyn PriceSource>, currency: impl Into<String>, ttl: Duration) -> Self {
+        Self {
+            source,
+            currency: currency.into(),
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_config(config: &PriceFeedConfig, client: Client) -> Self {
+        let source: Box<dyn PriceSource> = match config.source {
+            PriceSourceKind::CoinGecko => Box::new(CoinGecko::new(client)),
+        };
+        Self::new(
+            source,
+            config.currency.as_deref().unwrap_or(DEFAULT_FIAT_CURRENCY),
+            Duration::from_secs(config.cache_ttl_secs.unwrap_or(DEFAULT_PRICE_TTL_SECS)),
+        )
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    /// Price of `token_address` on `chain_id`, from the cache while fresh. Failures are logged
+    /// and give `None`, so alerts go out without a fiat value rather than not at all.
+    pub async fn price(&self, chain_id: i32, token_address: &str) -> Option<f64> {
+        let key = (chain_id, token_address.to_lowercase());
+        let cached = lock_or_recover(&self.entries, "price cache").get(&key).copied();
+        if let Some((price, fetched_at)) = cached
+            && fetched_at.elapsed() < self.ttl
+        {
+            return Some(price);
+        }
+
+        match self.source.token_price(chain_id, token_address, &self.currency).await {
+            Ok(price) => {
+                lock_or_recover(&self.entries, "price cache").insert(key, (price, Instant::now()));
+                Some(price)
+            }
+            Err(e) => {
+                warn!(token = token_address, chain_id, error = %e, "Failed to fetch price");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingSource {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl PriceSource for CountingSource {
+        async fn token_price(&self, _chain_id: i32, token_address: &str, _currency: &str) -> anyhow::Result<f64> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if token_address == "0xbad" {
+                anyhow::bail!("no price");
+            }
+            Ok(1.5)
+        }
+    }
+
+    #[test]
+    fn test_price_from_coingecko_response() {
+        let body = serde_json::json!({ "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48": { "usd": 0.9998 } });
+
+        assert_eq!(
+            price_from_coingecko_response(&body, "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", "USD"),
+            Some(0.9998)
+        );
+        assert_eq!(price_from_coingecko_response(&body, "0xdead", "usd"), None);
+        assert_eq!(price_from_coingecko_response(&serde_json::json!({}), "0xa0b8", "usd"), None);
+    }
+
+    #[tokio::test]
+    async fn test_price_cache_reuses_fresh_prices_and_skips_failures() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let source = Box::new(CountingSource { calls: calls.clone() });
+        let cache = PriceCache::new(source, "usd", Duration::from_secs(60));
+
+        assert_eq!(cache.price(1, "0xA0b8").await, Some(1.5));
+        assert_eq!(cache.price(1, "0xa0b8").await, Some(1.5));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.price(1, "0xbad").await, None);
+        assert_eq!(cache.price(1, "0xbad").await, None);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::audit::AuditKind;
+use crate::store::write_atomic;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use tracing::{error, warn};
+
+/// A bid covered by a queued alert, recorded as alerted once the alert goes out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedBid {
+    pub bid_id: String,
+    pub principal: String,
+    pub kind: AuditKind,
+    #[serde(default)]
+    pub severity: Option<String>,
+}
+
+/// An alert the notifier failed to deliver, kept until the next poll of its endpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedAlert {
+    pub endpoint: String,
+    pub chain_id: i32,
+    /// The `slack_workspaces` entry to send to, the default workspace when unset
+    #[serde(default)]
+    pub workspace: Option<String>,
+    #[serde(default)]
+    pub channel: Option<String>,
+    pub message: String,
+    pub bids: Vec<QueuedBid>,
+    /// Positions of the `notifiers` backends that didn't get the alert, every backend when unset
+    #[serde(default)]
+    pub backends: Option<Vec<usize>>,
+    pub queued_at: i64,
+}
+
+/// Alerts waiting to be sent again, mirrored to a JSONL file so they survive a restart.
+///
+/// Bids stay out of the dedup store while their alert is queued, and the queue holds at most
+/// `max_len` alerts, dropping the oldest first.
+pub struct RetryQueue {
+    path: PathBuf,
+    alerts: VecDeque<QueuedAlert>,
+    max_len: usize,
+}
+
+impl RetryQueue {
+    pub fn load(path: impl Into<PathBuf>, max_len: usize) -> Self {
+        let path = path.into();
+        let alerts = match fs::read_to_string(&path) {
+            Ok(content) => content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .filter_map(|line| match serde_json::from_str(line) {
+                    Ok(alert) => Some(alert),
+                    Err(e) => {
+                        warn!(path = %path.display(), error = %e, "Skipping unreadable queued alert");
+                        None
+                    }
+                })
+                .collect(),
+            Err(_) => VecDeque::new(),
+        };
+
+        let mut queue = Self { path, alerts, max_len: max_len.max(1) };
+        queue.enforce_limit();
+        queue
+    }
+
+    pub fn len(&self) -> usize {
+        self.alerts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.alerts.is_empty()
+    }
+
+    /// Whether an alert for the bid is still waiting to be sent.
+    pub fn is_queued(&self, chain_id: i32, bid_id: &str) -> bool {
+        self.alerts
+            .iter()
+            .any(|alert| alert.chain_id == chain_id && alert.bids.iter().any(|bid| bid.bid_id == bid_id))
+    }
+
+    pub fn push(&mut self, alert: QueuedAlert) {
+        self.alerts.push_back(alert);
+        self.enforce_limit();
+        self.save();
+    }
+
+    /// Removes and returns the endpoint's queued alerts, oldest first. The file is only
+    /// rewritten by the `requeue` that follows, so they aren't lost if the process dies while
+    /// they're being resent.
+    pub fn take_for_endpoint(&mut self, endpoint: &str) -> Vec<QueuedAlert> {
+        let (taken, kept): (VecDeque<_>, VecDeque<_>) =
+            std::mem::take(&mut self.alerts).into_iter().partition(|alert| alert.endpoint == endpoint);
+        self.alerts = kept;
+        taken.into()
+    }
+
+    /// Puts back the alerts that still couldn't be sent, ahead of anything queued since.
+    pub fn requeue(&mut self, alerts: Vec<QueuedAlert>) {
+        for alert in alerts.into_iter().rev() {
+            self.alerts.push_front(alert);
+        }
+        self.enforce_limit();
+        self.save();
+    }
+
+    fn enforce_limit(&mut self) {
+        while self.alerts.len() > self.max_len {
+            if let Some(dropped) = self.alerts.pop_front() {
+                let bids: Vec<&str> = dropped.bids.iter().map(|bid| bid.bid_id.as_str()).collect();
+                warn!(
+                    endpoint = %dropped.endpoint,
+                    chain_id = dropped.chain_id,
+                    bids = ?bids,
+                    max = self.max_len,
+                    "Retry queue is full, dropping the oldest alert"
+                );
+            }
+        }
+    }
+
+    fn save(&self) {
+        if self.alerts.is_empty() && !self.path.exists() {
+            return;
+        }
+
+        let mut content = String::new();
+        for alert in &self.alerts {
+            match serde_json::to_string(alert) {
+                Ok(line) => {
+                    content.push_str(&line);
+                    content.push('\n');
+                }
+                Err(e) => error!(error = %e, "Failed to serialize queued alert"),
+            }
+        }
+        if let Err(e) = write_atomic(&self.path, &content) {
+            error!(path = %self.path.display(), error = %e, "Failed to write retry queue file");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(endpoint: &str, bid_id: &str) -> QueuedAlert {
+        QueuedAlert {
+            endpoint: endpoint.to_string(),
+            chain_id: 137,
+            workspace: None,
+            channel: None,
+            message: format!("bid {}", bid_id),
+            bids: vec![QueuedBid {
+                bid_id: bid_id.to_string(),
+                principal: "1000000".to_string(),
+                kind: AuditKind::Alert,
+                severity: Some("critical".to_string()),
+            }],
+            backends: None,
+            queued_at: 1_000,
+        }
+    }
+
+    #[test]
+    fn test_retry_queue_persists_and_drops_oldest() {
+        let dir = std::env::temp_dir().join(format!("retry_queue_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("pending_alerts.jsonl");
+        let _ = fs::remove_file(&path);
+
+        let mut queue = RetryQueue::load(&path, 2);
+        assert!(queue.is_empty());
+        queue.push(alert("polygon", "1"));
+        queue.push(alert("mainnet", "2"));
+        queue.push(alert("polygon", "3"));
+
+        // The oldest alert made room for the newest
+        assert!(!queue.is_queued(137, "1"));
+        assert!(queue.is_queued(137, "2") && queue.is_queued(137, "3"));
+
+        let reloaded = RetryQueue::load(&path, 2);
+        assert_eq!(reloaded.alerts, [alert("mainnet", "2"), alert("polygon", "3")]);
+
+        let taken = queue.take_for_endpoint("polygon");
+        assert_eq!(taken, [alert("polygon", "3")]);
+        queue.requeue(Vec::new());
+        assert_eq!(RetryQueue::load(&path, 2).alerts, [alert("mainnet", "2")]);
+
+        queue.requeue(taken);
+        assert_eq!(queue.alerts, [alert("polygon", "3"), alert("mainnet", "2")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
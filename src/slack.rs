@@ -1,8 +1,41 @@
 
 
-use reqwest::Client;
+use crate::format::LinkStyle;
+use crate::notifier::{AlertSubject, Notifier, split_message};
+use async_trait::async_trait;
+use crate::lock_or_recover;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Client, StatusCode};
+use std::collections::HashMap;
 use std::error::Error;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde::Deserialize;
 use serde_json::json;
+use sha2::Sha256;
+use tracing::{debug, warn};
+
+const DEFAULT_CHANNEL: &str = "#webserver-alerts";
+
+// Slack recommends keeping message text under this many characters
+const MAX_TEXT_CHARS: usize = 4000;
+
+const SLACK_API_URL: &str = "https://slack.com/api";
+
+// Channels fetched per `conversations.list` page
+const CHANNEL_PAGE_LIMIT: u32 = 200;
+
+// Slack allows about one message per second per channel, with short bursts
+const CHANNEL_BURST: f64 = 3.0;
+const CHANNEL_MESSAGES_PER_SEC: f64 = 1.0;
+
+// How many times a rate limited post is retried, and the longest `Retry-After` honored
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+const MAX_RETRY_AFTER_SECS: u64 = 60;
+
+// Signed requests older than this are rejected, so a captured request can't be replayed
+const MAX_SIGNATURE_AGE_SECS: i64 = 300;
 
  
  /*
@@ -11,10 +44,10 @@ use serde_json::json;
         .expect("SLACK_OAUTH_TOKEN environment variable must be set");
 
     // Create the bot instance
-    let bot = SlackBot::new(token);
+    let bot = SlackBot::new(Client::new(), token);
 
     // Send a simple message
-    bot.send_message("#general", "Hello from my Rust bot! 🦀").await?;
+    bot.send_message("#general", "Hello from my Rust bot! 🦀", None).await?;
 
     // Send a rich message with attachment
     let attachment = json!([
@@ -47,48 +80,249 @@ use serde_json::json;
 
 
 
+/// Checks the `X-Slack-Signature` of a request from Slack (a slash command or interaction)
+/// against the app's signing secret, rejecting requests signed more than
+/// `MAX_SIGNATURE_AGE_SECS` from `now`.
+pub fn verify_signature(signing_secret: &str, headers: &HeaderMap, body: &[u8], now: i64) -> bool {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let (Some(timestamp), Some(signature)) = (header("x-slack-request-timestamp"), header("x-slack-signature")) else {
+        return false;
+    };
+    if timestamp.parse::<i64>().map_or(true, |signed_at| (now - signed_at).abs() > MAX_SIGNATURE_AGE_SECS) {
+        return false;
+    }
+    let Some(signature) = signature.strip_prefix("v0=").and_then(|hex_digest| hex::decode(hex_digest).ok()) else {
+        return false;
+    };
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(signing_secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(format!("v0:{}:", timestamp).as_bytes());
+    mac.update(body);
+    // Constant time, so the signature can't be guessed byte by byte
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// The form fields of a slash command that we use.
+#[derive(Debug, Deserialize)]
+pub struct SlashCommand {
+    pub command: String,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub user_name: String,
+    /// Where replies go once the 3 second acknowledgement deadline has passed
+    pub response_url: String,
+}
+
+/// A `block_actions` interaction, sent when someone clicks a button on one of our messages.
+/// Slack posts it as JSON in the `payload` form field.
+#[derive(Debug, Deserialize)]
+pub struct BlockActions {
+    pub user: InteractionUser,
+    pub actions: Vec<BlockAction>,
+    pub response_url: String,
+    /// The message the button is on, with its `text` and `blocks`
+    #[serde(default)]
+    pub message: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InteractionUser {
+    pub id: String,
+    #[serde(default)]
+    pub username: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BlockAction {
+    pub action_id: String,
+    #[serde(default)]
+    pub value: String,
+}
+
+/// Parses the form body of an interaction request.
+pub fn parse_block_actions(body: &[u8]) -> anyhow::Result<BlockActions> {
+    #[derive(Deserialize)]
+    struct Form {
+        payload: String,
+    }
+    let form: Form = serde_urlencoded::from_bytes(body)?;
+    Ok(serde_json::from_str(&form.payload)?)
+}
+
+/// Replaces the message an interaction came from with `text` and `blocks`.
+pub async fn replace_message(client: &Client, response_url: &str, text: &str, blocks: &serde_json::Value) -> anyhow::Result<()> {
+    client
+        .post(response_url)
+        .json(&json!({ "replace_original": true, "text": text, "blocks": blocks }))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.without_url())?;
+    Ok(())
+}
+
+/// Posts `text` to a slash command's `response_url`, visible only to the user who ran it.
+pub async fn respond(client: &Client, response_url: &str, text: &str) -> anyhow::Result<()> {
+    for chunk in split_message(text, MAX_TEXT_CHARS) {
+        client
+            .post(response_url)
+            .json(&json!({ "response_type": "ephemeral", "text": chunk }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.without_url())?;
+    }
+    Ok(())
+}
+
+/// Paces sends to one channel: up to `CHANNEL_BURST` at once, then `CHANNEL_MESSAGES_PER_SEC`.
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(now: Instant) -> Self {
+        Self { tokens: CHANNEL_BURST, updated_at: now }
+    }
+
+    /// Takes a token and returns how long to wait before using it. Tokens are reserved up
+    /// front, so concurrent senders queue up behind each other instead of all waking at once.
+    fn reserve(&mut self, now: Instant) -> Duration {
+        let refilled = now.saturating_duration_since(self.updated_at).as_secs_f64() * CHANNEL_MESSAGES_PER_SEC;
+        self.tokens = (self.tokens + refilled).min(CHANNEL_BURST) - 1.0;
+        self.updated_at = now;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / CHANNEL_MESSAGES_PER_SEC)
+        }
+    }
+}
+
+/// How long Slack asked us to back off for, from the `Retry-After` seconds of a 429.
+fn retry_after(headers: &HeaderMap) -> Duration {
+    let secs = headers
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .unwrap_or(1);
+    Duration::from_secs(secs.min(MAX_RETRY_AFTER_SECS))
+}
+
 pub struct SlackBot {
     client: Client,
     token: String,
+    channel: String,
+    api_url: String,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+    /// Channel name without `#` to ID, filled from `conversations.list`
+    channel_ids: Mutex<HashMap<String, String>>,
 }
 
 impl SlackBot {
-    pub fn new(token: String) -> Self {
+    pub fn new(client: Client, token: String) -> Self {
         Self {
-            client: Client::new(),
+            client,
             token,
+            channel: DEFAULT_CHANNEL.to_string(),
+            api_url: SLACK_API_URL.to_string(),
+            buckets: Mutex::new(HashMap::new()),
+            channel_ids: Mutex::new(HashMap::new()),
         }
     }
 
-    pub async fn send_message(&self, channel: &str, text: &str) -> Result<(), Box<dyn Error>> {
-        let url = "https://slack.com/api/chat.postMessage";
-        
-        let payload = json!({
-            "channel": channel,
-            "text": text
-        });
+    /// Sets the channel used when sending through the `Notifier` trait without a channel.
+    pub fn with_channel(mut self, channel: impl Into<String>) -> Self {
+        self.channel = channel.into();
+        self
+    }
 
-        let response = self
-            .client
-            .post(url)
-            .header("Authorization", format!("Bearer {}", self.token))
-            .header("Content-Type", "application/json")
-            .json(&payload)
-            .send()
-            .await?;
+    /// The ID of `channel`, given as a `#name`, bare name or ID. Names are looked up with
+    /// `conversations.list` and cached, and the cache is refreshed when a name isn't in it.
+    pub async fn resolve_channel(&self, channel: &str) -> Result<String, Box<dyn Error>> {
+        if is_channel_id(channel) {
+            return Ok(channel.to_string());
+        }
+        let name = channel.trim_start_matches('#');
+
+        if let Some(id) = lock_or_recover(&self.channel_ids, "slack channel ids").get(name) {
+            return Ok(id.clone());
+        }
 
-        if response.status().is_success() {
+        let channels = self.list_channels().await?;
+        let mut channel_ids = lock_or_recover(&self.channel_ids, "slack channel ids");
+        *channel_ids = channels;
+        channel_ids
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("Slack channel #{} not found", name).into())
+    }
+
+    /// Every channel the bot can see, by name.
+    async fn list_channels(&self) -> Result<HashMap<String, String>, Box<dyn Error>> {
+        let mut channels = HashMap::new();
+        let mut cursor = String::new();
+
+        loop {
+            let response = self
+                .client
+                .get(format!("{}/conversations.list", self.api_url))
+                .bearer_auth(&self.token)
+                .query(&[
+                    ("types", "public_channel,private_channel"),
+                    ("exclude_archived", "true"),
+                    ("limit", &CHANNEL_PAGE_LIMIT.to_string()),
+                    ("cursor", &cursor),
+                ])
+                .send()
+                .await?
+                .error_for_status()?;
             let response_body: serde_json::Value = response.json().await?;
-            
-            if response_body["ok"].as_bool().unwrap_or(false) {
-                println!("Message sent successfully!");
-            } else {
-                let error = response_body["error"].as_str().unwrap_or("Unknown error");
-                eprintln!("Slack API error: {}", error);
+            check_slack_response(&response_body)?;
+
+            let (page, next_cursor) = channels_from_list(&response_body);
+            channels.extend(page);
+            match next_cursor {
+                Some(next_cursor) => cursor = next_cursor,
+                None => return Ok(channels),
+            }
+        }
+    }
+
+    /// The channel to put in a post: its ID when it resolves, otherwise the name as given, so
+    /// a bot without the `channels:read` scope keeps posting by name.
+    async fn post_channel(&self, channel: &str) -> String {
+        match self.resolve_channel(channel).await {
+            Ok(id) => id,
+            Err(e) => {
+                warn!(channel, error = %e, "Failed to resolve Slack channel, posting by name");
+                channel.to_string()
             }
-        } else {
-            eprintln!("HTTP error: {}", response.status());
         }
+    }
+
+    /// Posts `text` to `channel`, a `#name` or a channel ID. With `blocks`, Slack renders the Block Kit layout and
+    /// uses `text` only for notifications and clients that can't show blocks.
+    pub async fn send_message(
+        &self,
+        channel: &str,
+        text: &str,
+        blocks: Option<&serde_json::Value>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut payload = json!({
+            "channel": self.post_channel(channel).await,
+            "text": text
+        });
+
+        if let Some(blocks) = blocks {
+            payload["blocks"] = blocks.clone();
+        }
+
+        self.post_message(&payload).await?;
+        debug!("Message sent successfully");
 
         Ok(())
     }
@@ -99,10 +333,8 @@ impl SlackBot {
         text: &str,
         attachments: Option<serde_json::Value>,
     ) -> Result<(), Box<dyn Error>> {
-        let url = "https://slack.com/api/chat.postMessage";
-        
         let mut payload = json!({
-            "channel": channel,
+            "channel": self.post_channel(channel).await,
             "text": text
         });
 
@@ -110,28 +342,327 @@ impl SlackBot {
             payload["attachments"] = attachments;
         }
 
-        let response = self
-            .client
-            .post(url)
-            .header("Authorization", format!("Bearer {}", self.token))
-            .header("Content-Type", "application/json")
-            .json(&payload)
-            .send()
-            .await?;
+        self.post_message(&payload).await?;
+        debug!("Rich message sent successfully");
 
-        if response.status().is_success() {
-            let response_body: serde_json::Value = response.json().await?;
-            
-            if response_body["ok"].as_bool().unwrap_or(false) {
-                println!("Rich message sent successfully!");
-            } else {
-                let error = response_body["error"].as_str().unwrap_or("Unknown error");
-                eprintln!("Slack API error: {}", error);
+        Ok(())
+    }
+
+    /// How long to wait before the next post to `channel` keeps it under Slack's limit.
+    fn reserve_send(&self, channel: &str) -> Duration {
+        let now = Instant::now();
+        lock_or_recover(&self.buckets, "slack rate limit")
+            .entry(channel.to_string())
+            .or_insert_with(|| TokenBucket::new(now))
+            .reserve(now)
+    }
+
+    /// Posts the payload, paced per channel, retrying after the requested delay when Slack
+    /// answers 429.
+    async fn post_message(&self, payload: &serde_json::Value) -> Result<serde_json::Value, Box<dyn Error>> {
+        let channel = payload["channel"].as_str().unwrap_or(&self.channel);
+        let mut attempt = 0;
+
+        let response = loop {
+            let wait = self.reserve_send(channel);
+            if !wait.is_zero() {
+                debug!(channel, ?wait, "Pacing Slack message");
+                tokio::time::sleep(wait).await;
             }
-        } else {
-            eprintln!("HTTP error: {}", response.status());
+
+            let response = self
+                .client
+                .post(format!("{}/chat.postMessage", self.api_url))
+                .header("Authorization", format!("Bearer {}", self.token))
+                .header("Content-Type", "application/json")
+                .json(payload)
+                .send()
+                .await?;
+
+            if response.status() == StatusCode::TOO_MANY_REQUESTS && attempt < MAX_RATE_LIMIT_RETRIES {
+                let delay = retry_after(response.headers());
+                warn!(channel, ?delay, attempt, "Rate limited by Slack, retrying");
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+                continue;
+            }
+            break response;
+        };
+
+        if !response.status().is_success() {
+            return Err(format!("HTTP error: {}", response.status()).into());
         }
 
+        let response_body: serde_json::Value = response.json().await?;
+        check_slack_response(&response_body)?;
+
+        Ok(response_body)
+    }
+}
+
+/// Whether `channel` is already a conversation ID (`C…` public, `G…` private, `D…` direct)
+/// rather than a name, which Slack keeps lowercase.
+fn is_channel_id(channel: &str) -> bool {
+    channel.len() >= 9
+        && channel.starts_with(['C', 'G', 'D'])
+        && channel.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+/// Name to ID pairs from a `conversations.list` page, and the cursor of the next page if any.
+fn channels_from_list(response_body: &serde_json::Value) -> (Vec<(String, String)>, Option<String>) {
+    let channels = response_body["channels"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|channel| Some((channel["name"].as_str()?.to_string(), channel["id"].as_str()?.to_string())))
+        .collect();
+    let next_cursor = response_body["response_metadata"]["next_cursor"]
+        .as_str()
+        .filter(|cursor| !cursor.is_empty())
+        .map(str::to_string);
+    (channels, next_cursor)
+}
+
+/// Slack reports logical failures (bad channel, missing scope, ...) as HTTP 200 with `ok: false`.
+fn check_slack_response(response_body: &serde_json::Value) -> Result<(), Box<dyn Error>> {
+    if response_body["ok"].as_bool().unwrap_or(false) {
+        return Ok(());
+    }
+
+    let error = response_body["error"].as_str().unwrap_or("Unknown error");
+    Err(format!("Slack API error: {}", error).into())
+}
+
+#[async_trait]
+impl Notifier for SlackBot {
+    async fn notify(&self, channel: Option<&str>, msg: &str) -> anyhow::Result<()> {
+        let channel = channel.unwrap_or(&self.channel);
+        for chunk in split_message(msg, MAX_TEXT_CHARS) {
+            self.send_message(channel, &chunk, None)
+                .await
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+        }
         Ok(())
     }
+
+    async fn notify_blocks(
+        &self,
+        channel: Option<&str>,
+        msg: &str,
+        blocks: &serde_json::Value,
+        _subject: AlertSubject<'_>,
+    ) -> anyhow::Result<()> {
+        // Slack rejects the whole post on a malformed block, so retry as plain text rather than lose the alert
+        let result = self
+            .send_message(channel.unwrap_or(&self.channel), msg, Some(blocks))
+            .await
+            .map_err(|e| e.to_string());
+        if let Err(e) = result {
+            warn!(error = %e, "Failed to send Block Kit message, falling back to plain text");
+            return self.notify(channel, msg).await;
+        }
+        Ok(())
+    }
+
+    fn link_style(&self) -> LinkStyle {
+        LinkStyle::Slack
+    }
+
+    fn max_message_chars(&self) -> usize {
+        MAX_TEXT_CHARS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::Router;
+    use axum::response::IntoResponse;
+    use axum::routing::{get, post};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_token_bucket_allows_burst_then_paces() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(start);
+
+        for _ in 0..3 {
+            assert_eq!(bucket.reserve(start), Duration::ZERO);
+        }
+        assert_eq!(bucket.reserve(start), Duration::from_secs(1));
+        assert_eq!(bucket.reserve(start), Duration::from_secs(2));
+        // Refills over time, up to the burst size
+        assert_eq!(bucket.reserve(start + Duration::from_secs(10)), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), Duration::from_secs(1));
+
+        headers.insert(RETRY_AFTER, "30".parse().unwrap());
+        assert_eq!(retry_after(&headers), Duration::from_secs(30));
+
+        headers.insert(RETRY_AFTER, "3600".parse().unwrap());
+        assert_eq!(retry_after(&headers), Duration::from_secs(MAX_RETRY_AFTER_SECS));
+    }
+
+    #[tokio::test]
+    async fn test_send_message_retries_after_rate_limit() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler_calls = calls.clone();
+        let app = Router::new().route(
+            "/chat.postMessage",
+            post(move || {
+                let calls = handler_calls.clone();
+                async move {
+                    if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                        (StatusCode::TOO_MANY_REQUESTS, [(RETRY_AFTER, "0")], "").into_response()
+                    } else {
+                        axum::Json(json!({ "ok": true })).into_response()
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut bot = SlackBot::new(Client::new(), "token".to_string());
+        bot.api_url = format!("http://{}", addr);
+
+        bot.send_message("C0123456789", "hello", None).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    fn signed_headers(secret: &str, timestamp: i64, body: &[u8]) -> HeaderMap {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("v0:{}:", timestamp).as_bytes());
+        mac.update(body);
+        let signature = format!("v0={}", hex::encode(mac.finalize().into_bytes()));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-slack-request-timestamp", timestamp.to_string().parse().unwrap());
+        headers.insert("x-slack-signature", signature.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let body = b"command=%2Foverdue&text=137&response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2F1";
+        let headers = signed_headers("secret", 1_700_000_000, body);
+
+        assert!(verify_signature("secret", &headers, body, 1_700_000_060));
+        assert!(!verify_signature("other secret", &headers, body, 1_700_000_060));
+        assert!(!verify_signature("secret", &headers, b"command=%2Foverdue&text=1", 1_700_000_060));
+        // Replayed after the signature has expired
+        assert!(!verify_signature("secret", &headers, body, 1_700_000_000 + MAX_SIGNATURE_AGE_SECS + 1));
+        assert!(!verify_signature("secret", &HeaderMap::new(), body, 1_700_000_000));
+    }
+
+    #[test]
+    fn test_parse_block_actions() {
+        let payload = json!({
+            "type": "block_actions",
+            "user": { "id": "U123", "username": "oncall" },
+            "actions": [{ "action_id": "snooze_bid", "value": "{\"chain_id\":137,\"bid_id\":\"42\"}" }],
+            "response_url": "https://hooks.slack.com/actions/1",
+            "message": { "text": "alert", "blocks": [] }
+        });
+        let body = serde_urlencoded::to_string([("payload", payload.to_string())]).unwrap();
+
+        let interaction = parse_block_actions(body.as_bytes()).unwrap();
+
+        assert_eq!(interaction.user.id, "U123");
+        assert_eq!(interaction.actions[0].action_id, "snooze_bid");
+        assert_eq!(interaction.actions[0].value, r#"{"chain_id":137,"bid_id":"42"}"#);
+        assert_eq!(interaction.message["text"], "alert");
+        assert!(parse_block_actions(b"payload=not-json").is_err());
+    }
+
+    #[test]
+    fn test_is_channel_id() {
+        assert!(is_channel_id("C0123456789"));
+        assert!(is_channel_id("G01ABCDEF23"));
+        assert!(!is_channel_id("#webserver-alerts"));
+        assert!(!is_channel_id("general"));
+        assert!(!is_channel_id("CHANNEL"));
+    }
+
+    #[test]
+    fn test_channels_from_list() {
+        let body = json!({
+            "ok": true,
+            "channels": [{ "id": "C01", "name": "general" }, { "id": "C02", "name": "webserver-alerts" }, { "id": "C03" }],
+            "response_metadata": { "next_cursor": "dGVhbTpDMDI=" }
+        });
+
+        let (channels, cursor) = channels_from_list(&body);
+        assert_eq!(
+            channels,
+            [("general".to_string(), "C01".to_string()), ("webserver-alerts".to_string(), "C02".to_string())]
+        );
+        assert_eq!(cursor.as_deref(), Some("dGVhbTpDMDI="));
+
+        let last_page = json!({ "ok": true, "channels": [], "response_metadata": { "next_cursor": "" } });
+        assert_eq!(channels_from_list(&last_page).1, None);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_channel_caches_and_refreshes_on_miss() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler_calls = calls.clone();
+        let app = Router::new().route(
+            "/conversations.list",
+            get(move || {
+                let calls = handler_calls.clone();
+                async move {
+                    // The second listing includes a channel created since the first
+                    let mut channels = vec![json!({ "id": "C0000000001", "name": "general" })];
+                    if calls.fetch_add(1, Ordering::SeqCst) > 0 {
+                        channels.push(json!({ "id": "C0000000002", "name": "new-alerts" }));
+                    }
+                    axum::Json(json!({ "ok": true, "channels": channels }))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut bot = SlackBot::new(Client::new(), "token".to_string());
+        bot.api_url = format!("http://{}", addr);
+
+        assert_eq!(bot.resolve_channel("#general").await.unwrap(), "C0000000001");
+        assert_eq!(bot.resolve_channel("general").await.unwrap(), "C0000000001");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(bot.resolve_channel("#new-alerts").await.unwrap(), "C0000000002");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(bot.resolve_channel("#missing").await.is_err());
+        assert_eq!(bot.resolve_channel("C0123456789").await.unwrap(), "C0123456789");
+    }
+
+    #[test]
+    fn test_check_slack_response_ok() {
+        let body = json!({ "ok": true, "channel": "C0123456", "ts": "1704067200.000100" });
+
+        assert!(check_slack_response(&body).is_ok());
+    }
+
+    #[test]
+    fn test_check_slack_response_surfaces_error() {
+        let body = json!({ "ok": false, "error": "channel_not_found" });
+
+        let err = check_slack_response(&body).unwrap_err();
+        assert_eq!(err.to_string(), "Slack API error: channel_not_found");
+    }
+
+    #[test]
+    fn test_check_slack_response_missing_ok_is_error() {
+        let err = check_slack_response(&json!({})).unwrap_err();
+
+        assert_eq!(err.to_string(), "Slack API error: Unknown error");
+    }
 }
@@ -0,0 +1,561 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use tracing::error;
+
+/// What we last told Slack about a bid.
+///
+/// Lines are `chain_id:bid_id alerted_at principal`, followed by `snoozed_until` for a
+/// snoozed bid. Older files only contain the key, in which case all fields are `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlertRecord {
+    pub alerted_at: Option<i64>,
+    pub principal: Option<String>,
+    /// Re-alerts are held back until this time, set from the Slack snooze button
+    pub snoozed_until: Option<i64>,
+}
+
+impl AlertRecord {
+    pub fn is_snoozed(&self, now: i64) -> bool {
+        self.snoozed_until.is_some_and(|until| now < until)
+    }
+}
+
+pub fn make_bid_key(chain_id: i32, bid_id: &str) -> String {
+    format!("{}:{}", chain_id, bid_id)
+}
+
+const DUE_SOON_MARKER: &str = ":due-";
+
+/// Key for a "due soon" warning, kept apart from the bid's overdue key and tied to the due
+/// date so every installment gets its own warning.
+pub fn make_due_soon_key(chain_id: i32, bid_id: &str, due: i64) -> String {
+    format!("{}{}{}", make_bid_key(chain_id, bid_id), DUE_SOON_MARKER, due)
+}
+
+const LIQUIDATION_MARKER: &str = ":liquidation";
+
+/// Key for a liquidation opportunity alert, kept apart from the bid's overdue key so a bid
+/// can be alerted on as both.
+pub fn make_liquidation_key(chain_id: i32, bid_id: &str) -> String {
+    format!("{}{}", make_bid_key(chain_id, bid_id), LIQUIDATION_MARKER)
+}
+
+fn parse_alerted_line(line: &str) -> Option<(String, AlertRecord)> {
+    let mut parts = line.split_whitespace();
+    let key = parts.next()?.to_string();
+    let alerted_at = parts.next().and_then(|t| t.parse().ok());
+    let principal = parts.next().map(|p| p.to_string());
+    let snoozed_until = parts.next().and_then(|t| t.parse().ok());
+    Some((key, AlertRecord { alerted_at, principal, snoozed_until }))
+}
+
+fn format_alerted_line(key: &str, record: &AlertRecord) -> String {
+    match (record.alerted_at, &record.principal) {
+        (Some(alerted_at), Some(principal)) => match record.snoozed_until {
+            Some(snoozed_until) => format!("{} {} {} {}\n", key, alerted_at, principal, snoozed_until),
+            None => format!("{} {} {}\n", key, alerted_at, principal),
+        },
+        (Some(alerted_at), None) => format!("{} {}\n", key, alerted_at),
+        _ => format!("{}\n", key),
+    }
+}
+
+/// Takes an advisory lock on a `.lock` file next to `path`, held until the returned file is
+/// dropped. The data file itself can't carry the lock since rewrites rename a new file over it.
+fn lock_store(path: &Path, exclusive: bool) -> std::io::Result<fs::File> {
+    let mut lock_name = path.file_name().unwrap_or_default().to_os_string();
+    lock_name.push(".lock");
+
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_file_name(lock_name))?;
+    if exclusive {
+        file.lock()?;
+    } else {
+        file.lock_shared()?;
+    }
+    Ok(file)
+}
+
+/// Replaces the file at `path` with `content` by writing a sibling temp file and renaming it
+/// over the original, so a crash mid-write can't leave a truncated file behind.
+pub(crate) fn write_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)
+}
+
+/// A change to the alerted bids file, applied by the store's writer thread in the order sent.
+enum FileWrite {
+    Append { path: PathBuf, line: String },
+    /// Rewrites the file without the lines for `keys`
+    Remove { path: PathBuf, keys: HashSet<String> },
+    /// Answers once every earlier write has been applied and synced to disk
+    Sync { path: PathBuf, done: mpsc::Sender<()> },
+}
+
+impl FileWrite {
+    fn apply(self) {
+        match self {
+            FileWrite::Append { path, line } => {
+                let appended = lock_store(&path, true).and_then(|_lock| {
+                    fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&path)
+                        .and_then(|mut file| file.write_all(line.as_bytes()))
+                });
+                if let Err(e) = appended {
+                    error!(path = %path.display(), error = %e, "Failed to append to alerted bids file");
+                }
+            }
+            FileWrite::Remove { path, keys } => {
+                let rewritten = lock_store(&path, true).and_then(|_lock| match fs::read_to_string(&path) {
+                    Ok(content) => write_atomic(&path, &without_keys(&content, &keys)),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                    Err(e) => Err(e),
+                });
+                if let Err(e) = rewritten {
+                    error!(path = %path.display(), error = %e, "Failed to rewrite alerted bids file");
+                }
+            }
+            FileWrite::Sync { path, done } => {
+                match fs::File::open(&path) {
+                    Ok(file) => {
+                        if let Err(e) = file.sync_all() {
+                            error!(error = %e, "Failed to sync alerted bids file");
+                        }
+                    }
+                    // Nothing has been alerted yet, so there is nothing to sync
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => error!(error = %e, "Failed to open alerted bids file for sync"),
+                }
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+/// The file `content` without the lines for `keys`, keeping only the last line of every other
+/// key. It's read back under the lock rather than rebuilt from memory, so lines another process
+/// appended since this one loaded the file aren't lost.
+fn without_keys(content: &str, keys: &HashSet<String>) -> String {
+    let mut seen = HashSet::new();
+    let mut lines: Vec<String> = content
+        .lines()
+        .rev()
+        .filter_map(parse_alerted_line)
+        .filter(|(key, _)| !keys.contains(key) && seen.insert(key.clone()))
+        .map(|(key, record)| format_alerted_line(&key, &record))
+        .collect();
+    lines.reverse();
+    lines.concat()
+}
+
+/// Applies file writes on a thread of its own, so the async tasks recording alerts never wait
+/// on the disk (or on another process holding the file lock). Dropping it waits for the
+/// writes still pending.
+struct StoreWriter {
+    sender: Option<mpsc::Sender<FileWrite>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl StoreWriter {
+    fn spawn() -> Self {
+        let (sender, receiver) = mpsc::channel::<FileWrite>();
+        let thread = thread::Builder::new()
+            .name("alert-store-writer".to_string())
+            .spawn(move || receiver.into_iter().for_each(FileWrite::apply))
+            .expect("failed to spawn alerted bids writer thread");
+        Self { sender: Some(sender), thread: Some(thread) }
+    }
+
+    fn send(&self, write: FileWrite) {
+        // The thread only stops once the sender is dropped, so this only fails if it panicked
+        if let Some(Err(e)) = self.sender.as_ref().map(|sender| sender.send(write)) {
+            error!(error = %e, "Alerted bids writer has stopped, write lost");
+        }
+    }
+}
+
+impl Drop for StoreWriter {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// An endpoint that is currently failing to answer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointFailure {
+    pub since: i64,
+    pub alerted_at: i64,
+}
+
+/// The set of alerted bids, kept in memory and mirrored to an append-only file.
+///
+/// Endpoint tasks share one store behind a mutex so they see each other's writes. File
+/// access also takes an advisory lock, so separate processes pointed at the same file
+/// don't interleave lines, read a rewrite halfway through or drop each other's lines when
+/// removing keys. The file is only read by `load`, though, so one process doesn't see what
+/// another records after that: each file should have one monitor, with the `clear-alerts`
+/// subcommand's changes taking effect on the monitor's next start.
+/// Endpoint failures are kept in memory only; after a restart an ongoing outage is
+/// simply alerted once more.
+///
+/// Reads are served from memory and writes are handed to a writer thread, so the runtime's
+/// workers never block on file I/O while holding the store; only `load`, done once at
+/// startup, reads the file on the calling thread. A SQLite store, if adopted, should
+/// likewise run its queries on a blocking pool rather than on the runtime.
+pub struct AlertStore {
+    path: PathBuf,
+    records: HashMap<String, AlertRecord>,
+    endpoint_failures: HashMap<String, EndpointFailure>,
+    writer: StoreWriter,
+}
+
+impl AlertStore {
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let mut records = HashMap::new();
+
+        // Without a lock file (e.g. a read-only directory) the file is still read, unguarded
+        let _lock = lock_store(&path, false);
+        if let Ok(file) = fs::File::open(&path) {
+            let reader = BufReader::new(file);
+            // The file is append-only, so a later line for the same key supersedes earlier ones
+            for line in reader.lines().map_while(Result::ok) {
+                if let Some((key, record)) = parse_alerted_line(&line) {
+                    records.insert(key, record);
+                }
+            }
+        }
+
+        Self { path, records, endpoint_failures: HashMap::new(), writer: StoreWriter::spawn() }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&AlertRecord> {
+        self.records.get(key)
+    }
+
+    /// Every recorded key with its record, in no particular order.
+    pub fn records(&self) -> impl Iterator<Item = (&String, &AlertRecord)> {
+        self.records.iter()
+    }
+
+    /// Every key recorded on the given chain, overdue and due soon alike.
+    pub fn keys_for_chain(&self, chain_id: i32) -> HashSet<String> {
+        let key_prefix = format!("{}:", chain_id);
+
+        self.records.keys().filter(|key| key.starts_with(&key_prefix)).cloned().collect()
+    }
+
+    /// Bid ids previously alerted as overdue on the given chain.
+    pub fn bid_ids_for_chain(&self, chain_id: i32) -> Vec<String> {
+        let key_prefix = format!("{}:", chain_id);
+
+        self.records
+            .keys()
+            .filter_map(|key| key.strip_prefix(&key_prefix))
+            .filter(|id| !id.contains(DUE_SOON_MARKER) && !id.ends_with(LIQUIDATION_MARKER))
+            .map(|id| id.to_string())
+            .collect()
+    }
+
+    /// Liquidation opportunity keys recorded on the given chain.
+    pub fn liquidation_keys_for_chain(&self, chain_id: i32) -> HashSet<String> {
+        let key_prefix = format!("{}:", chain_id);
+
+        self.records
+            .keys()
+            .filter(|key| key.starts_with(&key_prefix) && key.ends_with(LIQUIDATION_MARKER))
+            .cloned()
+            .collect()
+    }
+
+    /// Due soon keys on the given chain whose due date has passed.
+    pub fn expired_due_soon_keys(&self, chain_id: i32, now: i64) -> HashSet<String> {
+        let key_prefix = format!("{}:", chain_id);
+
+        self.records
+            .keys()
+            .filter(|key| key.starts_with(&key_prefix))
+            .filter(|key| {
+                key.split_once(DUE_SOON_MARKER)
+                    .and_then(|(_, due)| due.parse::<i64>().ok())
+                    .is_some_and(|due| due <= now)
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn record(&mut self, chain_id: i32, bid_id: &str, principal: &str, alerted_at: i64) {
+        self.record_key(make_bid_key(chain_id, bid_id), principal, alerted_at);
+    }
+
+    pub fn record_key(&mut self, key: String, principal: &str, alerted_at: i64) {
+        let record = AlertRecord {
+            alerted_at: Some(alerted_at),
+            principal: Some(principal.to_string()),
+            snoozed_until: None,
+        };
+        self.append(key, record);
+    }
+
+    /// Holds back re-alerts of the bid at `key` until `until`. A bid with no record yet is
+    /// recorded as alerted now, so it isn't alerted as new while snoozed either.
+    pub fn snooze(&mut self, key: &str, until: i64, now: i64) {
+        let mut record = self.records.get(key).cloned().unwrap_or_default();
+        record.alerted_at.get_or_insert(now);
+        record.principal.get_or_insert_with(|| "0".to_string());
+        record.snoozed_until = Some(until);
+        self.append(key.to_string(), record);
+    }
+
+    fn append(&mut self, key: String, record: AlertRecord) {
+        // The record is kept in memory either way, so a failed write only loses it across a restart
+        self.writer.send(FileWrite::Append { path: self.path.clone(), line: format_alerted_line(&key, &record) });
+        self.records.insert(key, record);
+    }
+
+    /// Notes a failed poll of the endpoint and returns the failure state if it should be
+    /// alerted on: the first failure, then at most once per `realert_after_secs`.
+    pub fn record_endpoint_failure(&mut self, endpoint: &str, now: i64, realert_after_secs: u64) -> Option<EndpointFailure> {
+        match self.endpoint_failures.get_mut(endpoint) {
+            Some(failure) if now - failure.alerted_at < realert_after_secs as i64 => None,
+            Some(failure) => {
+                failure.alerted_at = now;
+                Some(failure.clone())
+            }
+            None => {
+                let failure = EndpointFailure { since: now, alerted_at: now };
+                self.endpoint_failures.insert(endpoint.to_string(), failure.clone());
+                Some(failure)
+            }
+        }
+    }
+
+    pub fn is_endpoint_failing(&self, endpoint: &str) -> bool {
+        self.endpoint_failures.contains_key(endpoint)
+    }
+
+    /// Notes a successful poll, returning the failure it ends if the endpoint was failing.
+    pub fn record_endpoint_success(&mut self, endpoint: &str) -> Option<EndpointFailure> {
+        self.endpoint_failures.remove(endpoint)
+    }
+
+    /// Makes sure everything recorded so far has reached disk, called before exiting. Blocks
+    /// until the pending writes are done.
+    pub fn flush(&self) {
+        let (done, finished) = mpsc::channel();
+        self.writer.send(FileWrite::Sync { path: self.path.clone(), done });
+        let _ = finished.recv();
+    }
+
+    /// Drops the given keys and rewrites the file without them.
+    pub fn remove(&mut self, keys: &HashSet<String>) {
+        self.records.retain(|key, _| !keys.contains(key));
+        self.writer.send(FileWrite::Remove { path: self.path.clone(), keys: keys.clone() });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_alerted_line_legacy_format() {
+        let (key, record) = parse_alerted_line("1:12345").unwrap();
+
+        assert_eq!(key, "1:12345");
+        assert_eq!(record, AlertRecord::default());
+    }
+
+    #[test]
+    fn test_parse_alerted_line_with_timestamp_and_principal() {
+        let (key, record) = parse_alerted_line("137:99999 1704067200 5000000000000000000").unwrap();
+
+        assert_eq!(key, "137:99999");
+        assert_eq!(record.alerted_at, Some(1704067200));
+        assert_eq!(record.principal.as_deref(), Some("5000000000000000000"));
+    }
+
+    #[test]
+    fn test_format_alerted_line_round_trips() {
+        let record = AlertRecord { alerted_at: Some(1704067200), principal: Some("1000000".to_string()), snoozed_until: None };
+        let line = format_alerted_line("1:12345", &record);
+
+        assert_eq!(parse_alerted_line(line.trim_end()), Some(("1:12345".to_string(), record.clone())));
+
+        let snoozed = AlertRecord { snoozed_until: Some(1704081600), ..record };
+        let line = format_alerted_line("1:12345", &snoozed);
+        assert_eq!(line, "1:12345 1704067200 1000000 1704081600\n");
+        assert_eq!(parse_alerted_line(line.trim_end()), Some(("1:12345".to_string(), snoozed)));
+        assert_eq!(format_alerted_line("1:12345", &AlertRecord::default()), "1:12345\n");
+    }
+
+    #[test]
+    fn test_endpoint_failure_cooldown_and_recovery() {
+        let dir = std::env::temp_dir().join(format!("alert_store_test_{}", std::process::id()));
+        let mut store = AlertStore::load(dir.join("alerted_bids.txt"));
+
+        assert_eq!(store.record_endpoint_failure("mainnet", 1_000, 600), Some(EndpointFailure { since: 1_000, alerted_at: 1_000 }));
+        assert_eq!(store.record_endpoint_failure("mainnet", 1_599, 600), None);
+        assert_eq!(store.record_endpoint_failure("mainnet", 1_600, 600), Some(EndpointFailure { since: 1_000, alerted_at: 1_600 }));
+
+        assert_eq!(store.record_endpoint_success("mainnet"), Some(EndpointFailure { since: 1_000, alerted_at: 1_600 }));
+        assert_eq!(store.record_endpoint_success("mainnet"), None);
+    }
+
+    #[test]
+    fn test_due_soon_keys_are_kept_apart_from_overdue_keys() {
+        let dir = std::env::temp_dir().join(format!("alert_store_due_soon_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("alerted_bids.txt");
+        let _ = fs::remove_file(&path);
+        let mut store = AlertStore::load(&path);
+
+        store.record(1, "12345", "1000000", 1_000);
+        store.record_key(make_due_soon_key(1, "12345", 2_000), "1000000", 1_000);
+        store.record_key(make_due_soon_key(1, "67890", 5_000), "1000000", 1_000);
+        store.record_key(make_liquidation_key(1, "67890"), "1000000", 1_000);
+
+        assert_eq!(store.bid_ids_for_chain(1), vec!["12345".to_string()]);
+        assert_eq!(store.liquidation_keys_for_chain(1), HashSet::from(["1:67890:liquidation".to_string()]));
+        assert_eq!(store.expired_due_soon_keys(1, 3_000), HashSet::from(["1:12345:due-2000".to_string()]));
+        assert!(store.expired_due_soon_keys(137, 3_000).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_remove_rewrites_file_atomically() {
+        let dir = std::env::temp_dir().join(format!("alert_store_remove_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("alerted_bids.txt");
+        let _ = fs::remove_file(&path);
+        let mut store = AlertStore::load(&path);
+
+        store.record(1, "12345", "1000000", 1_000);
+        store.record(1, "67890", "2000000", 1_000);
+        store.remove(&HashSet::from(["1:12345".to_string()]));
+        store.flush();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1:67890 1000 2000000\n");
+        assert!(!dir.join("alerted_bids.txt.tmp").exists());
+        assert_eq!(AlertStore::load(&path).bid_ids_for_chain(1), vec!["67890".to_string()]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_remove_keeps_lines_other_stores_appended() {
+        let dir = std::env::temp_dir().join(format!("alert_store_shared_remove_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("alerted_bids.txt");
+        let _ = fs::remove_file(&path);
+
+        // Two stores on one file stand in for the monitor and a `clear-alerts` run
+        let mut first = AlertStore::load(&path);
+        first.record(1, "12345", "1000000", 1_000);
+        first.flush();
+        let mut second = AlertStore::load(&path);
+        second.record(1, "67890", "2000000", 2_000);
+        second.record(1, "67890", "2500000", 3_000);
+        second.flush();
+
+        first.remove(&HashSet::from(["1:12345".to_string()]));
+        first.flush();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1:67890 3000 2500000\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_snooze_survives_reload_until_realerted() {
+        let dir = std::env::temp_dir().join(format!("alert_store_snooze_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("alerted_bids.txt");
+        let _ = fs::remove_file(&path);
+        let mut store = AlertStore::load(&path);
+
+        store.record(137, "42", "1000000", 1_000);
+        store.snooze("137:42", 5_000, 2_000);
+        store.flush();
+
+        let reloaded = AlertStore::load(&path);
+        let record = reloaded.get("137:42").unwrap();
+        assert_eq!(record.alerted_at, Some(1_000));
+        assert!(record.is_snoozed(4_999));
+        assert!(!record.is_snoozed(5_000));
+
+        // A later alert starts from a clean record
+        store.record(137, "42", "1000000", 6_000);
+        store.flush();
+        assert!(!AlertStore::load(&path).get("137:42").unwrap().is_snoozed(0));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_record_survives_unwritable_file() {
+        let path = std::env::temp_dir()
+            .join(format!("alert_store_missing_dir_{}", std::process::id()))
+            .join("alerted_bids.txt");
+        let mut store = AlertStore::load(&path);
+
+        store.record(1, "12345", "1000000", 1_000);
+        store.flush();
+
+        assert!(store.get("1:12345").is_some());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_concurrent_records_write_whole_lines() {
+        let dir = std::env::temp_dir().join(format!("alert_store_concurrent_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("alerted_bids.txt");
+        let _ = fs::remove_file(&path);
+
+        // Separate stores on one file stand in for separate bot processes
+        let writers: Vec<_> = (0..8)
+            .map(|writer| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let mut store = AlertStore::load(&path);
+                    for bid in 0..50 {
+                        store.record(writer, &bid.to_string(), "5000000000000000000", 1_704_067_200);
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 8 * 50);
+        for line in content.lines() {
+            let (key, record) = parse_alerted_line(line).unwrap();
+            assert_eq!(format_alerted_line(&key, &record), format!("{}\n", line));
+            assert_eq!(record.principal.as_deref(), Some("5000000000000000000"));
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
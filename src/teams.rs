@@ -0,0 +1,139 @@
+use crate::notifier::{Notifier, split_message};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::json;
+
+// Teams rejects webhook payloads over about 28KB; this leaves room for the card's JSON and
+// multi-byte characters
+const MAX_MESSAGE_CHARS: usize = 12_000;
+
+/// Posts alerts to a Microsoft Teams incoming webhook as MessageCards.
+pub struct TeamsNotifier {
+    client: Client,
+    webhook_url: String,
+}
+
+impl TeamsNotifier {
+    pub fn new(client: Client, webhook_url: String) -> Self {
+        Self {
+            client,
+            webhook_url,
+        }
+    }
+
+    pub async fn send_card(&self, card: &serde_json::Value) -> anyhow::Result<()> {
+        let response = self
+            .client
+            .post(&self.webhook_url)
+            .header("Content-Type", "application/json")
+            .json(card)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Teams webhook returned HTTP {}: {}", status, body);
+        }
+
+        Ok(())
+    }
+}
+
+/// Turns an alert message into a MessageCard. The first line becomes the title and each
+/// blank-line separated block a section, with `Label: value` lines as its facts, so batched
+/// alerts get one section per bid.
+pub fn message_card(msg: &str) -> serde_json::Value {
+    let mut lines = msg.lines();
+    let title = lines.next().unwrap_or("").to_string();
+    let rest: Vec<&str> = lines.collect();
+
+    let sections: Vec<serde_json::Value> = rest
+        .split(|line| line.trim().is_empty())
+        .filter(|block| !block.is_empty())
+        .map(card_section)
+        .collect();
+
+    json!({
+        "@type": "MessageCard",
+        "@context": "https://schema.org/extensions",
+        "summary": title,
+        "title": title,
+        "sections": sections,
+    })
+}
+
+/// One card section. In a batch each block starts with the alert's own header line, which
+/// becomes the section title.
+fn card_section(block: &[&str]) -> serde_json::Value {
+    let (title, lines) = match block.split_first() {
+        Some((first, rest)) if as_fact(first).is_none() => (Some(*first), rest),
+        _ => (None, block),
+    };
+
+    let mut facts = Vec::new();
+    let mut text = Vec::new();
+    for line in lines {
+        match as_fact(line) {
+            Some((name, value)) => facts.push(json!({ "name": name, "value": value })),
+            None => text.push(*line),
+        }
+    }
+
+    let mut section = json!({ "facts": facts });
+    if let Some(title) = title {
+        section["activityTitle"] = json!(title);
+    }
+    if !text.is_empty() {
+        // Teams markdown needs a blank line for a line break
+        section["text"] = json!(text.join("\n\n"));
+    }
+    section
+}
+
+fn as_fact(line: &str) -> Option<(&str, &str)> {
+    let (name, value) = line.split_once(": ")?;
+    let is_label = !name.is_empty() && name.len() <= 40 && name.chars().all(|c| c.is_alphanumeric() || c == ' ');
+    is_label.then_some((name, value))
+}
+
+#[async_trait]
+impl Notifier for TeamsNotifier {
+    async fn notify(&self, _channel: Option<&str>, msg: &str) -> anyhow::Result<()> {
+        for chunk in split_message(msg, MAX_MESSAGE_CHARS) {
+            self.send_card(&message_card(&chunk)).await?;
+        }
+        Ok(())
+    }
+
+    fn max_message_chars(&self) -> usize {
+        MAX_MESSAGE_CHARS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_card_sections() {
+        let alert = "Timestamp: 2024-01-01 12:00:00 EST\nChain: Polygon (137)\nBid ID: 42\n\
+                     Loan Tx: 0xabc (https://polygonscan.com/tx/0xabc)";
+        let batch = format!("📦 2 Overdue Loan Alert(s) on Chain 137\n\n🚨 Overdue Loan Alert\n{alert}\n\n🚨 Overdue Loan Alert\n{alert}");
+        let card = message_card(&batch);
+
+        assert_eq!(card["title"], "📦 2 Overdue Loan Alert(s) on Chain 137");
+        let sections = card["sections"].as_array().unwrap();
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0]["activityTitle"], "🚨 Overdue Loan Alert");
+        assert_eq!(sections[1]["activityTitle"], "🚨 Overdue Loan Alert");
+        assert_eq!(sections[1]["facts"][0], json!({ "name": "Timestamp", "value": "2024-01-01 12:00:00 EST" }));
+        assert_eq!(sections[1]["facts"][3], json!({ "name": "Loan Tx", "value": "0xabc (https://polygonscan.com/tx/0xabc)" }));
+
+        let card = message_card("🚨 Overdue Loan Alert\nBid ID: 42\nSee https://example.com/bids/42");
+        assert_eq!(card["title"], "🚨 Overdue Loan Alert");
+        assert_eq!(card["sections"][0]["facts"], json!([{ "name": "Bid ID", "value": "42" }]));
+        assert_eq!(card["sections"][0]["text"], "See https://example.com/bids/42");
+        assert!(card["sections"][0].get("activityTitle").is_none());
+    }
+}
@@ -0,0 +1,109 @@
+use crate::notifier::{Notifier, split_message};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::json;
+
+// Telegram rejects message text longer than this
+const MAX_TEXT_CHARS: usize = 4096;
+
+// Characters that must be backslash-escaped in MarkdownV2 text
+const MARKDOWN_V2_SPECIAL: &[char] = &[
+    '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!', '\\',
+];
+
+pub fn escape_markdown_v2(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if MARKDOWN_V2_SPECIAL.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+pub struct TelegramNotifier {
+    client: Client,
+    token: String,
+    chat_id: String,
+    markdown_v2: bool,
+}
+
+impl TelegramNotifier {
+    pub fn new(client: Client, token: String, chat_id: String) -> Self {
+        Self {
+            client,
+            token,
+            chat_id,
+            markdown_v2: false,
+        }
+    }
+
+    /// Sends messages with `parse_mode: MarkdownV2`, escaping them so plain alerts still render.
+    pub fn with_markdown_v2(mut self, enabled: bool) -> Self {
+        self.markdown_v2 = enabled;
+        self
+    }
+
+    pub async fn send_message(&self, text: &str) -> anyhow::Result<()> {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.token);
+
+        let mut payload = json!({
+            "chat_id": self.chat_id,
+            "text": text
+        });
+
+        if self.markdown_v2 {
+            payload["parse_mode"] = json!("MarkdownV2");
+        }
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .json(&payload)
+            .send()
+            .await?;
+
+        let response_body: serde_json::Value = response.json().await?;
+        if !response_body["ok"].as_bool().unwrap_or(false) {
+            let description = response_body["description"].as_str().unwrap_or("Unknown error");
+            anyhow::bail!("Telegram API error: {}", description);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    async fn notify(&self, _channel: Option<&str>, msg: &str) -> anyhow::Result<()> {
+        if self.markdown_v2 {
+            // Escaping at most doubles the length, so split with headroom before escaping
+            for chunk in split_message(msg, MAX_TEXT_CHARS / 2) {
+                self.send_message(&escape_markdown_v2(&chunk)).await?;
+            }
+        } else {
+            for chunk in split_message(msg, MAX_TEXT_CHARS) {
+                self.send_message(&chunk).await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn max_message_chars(&self) -> usize {
+        if self.markdown_v2 { MAX_TEXT_CHARS / 2 } else { MAX_TEXT_CHARS }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_markdown_v2() {
+        assert_eq!(escape_markdown_v2("Principal Amount: 1.00"), "Principal Amount: 1\\.00");
+        assert_eq!(escape_markdown_v2("a_b*c[d](e)!"), "a\\_b\\*c\\[d\\]\\(e\\)\\!");
+        assert_eq!(escape_markdown_v2("🚨 Overdue Loan Alert"), "🚨 Overdue Loan Alert");
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::{HashMap, HashSet};
+
+/// Token decimals as returned by subgraphs, either a number or a string.
+pub fn parse_decimals(value: &serde_json::Value) -> Option<u32> {
+    value
+        .as_u64()
+        .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+        .and_then(|decimals| u32::try_from(decimals).ok())
+}
+
+/// Symbol and decimals of a lending token, as first reported by a bid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenInfo {
+    pub symbol: Option<String>,
+    pub decimals: Option<u32>,
+}
+
+/// A bid reporting different decimals than were first seen for its token.
+#[derive(Debug, PartialEq)]
+pub struct DecimalsMismatch {
+    pub address: String,
+    pub known: u32,
+    pub reported: u32,
+}
+
+/// Lending token metadata per (chain id, lowercase token address), populated as bids are
+/// seen. The first value seen for a token is kept as canonical.
+#[derive(Debug, Default)]
+pub struct TokenCache {
+    tokens: HashMap<(i32, String), TokenInfo>,
+    // Mismatches already reported, so each is surfaced once rather than every poll
+    reported: HashSet<(i32, String, u32)>,
+}
+
+fn lending_token_address(bid: &serde_json::Value) -> Option<String> {
+    bid.get("lendingToken")?.get("id")?.as_str().map(|id| id.to_lowercase())
+}
+
+impl TokenCache {
+    pub fn get(&self, chain_id: i32, address: &str) -> Option<&TokenInfo> {
+        self.tokens.get(&(chain_id, address.to_lowercase()))
+    }
+
+    /// Records the bid's token metadata, filling in fields the cache doesn't have yet. Returns
+    /// a mismatch the first time a token is seen with decimals that disagree with the cache.
+    pub fn observe(&mut self, chain_id: i32, bid: &serde_json::Value) -> Option<DecimalsMismatch> {
+        let address = lending_token_address(bid)?;
+        let token = bid.get("lendingToken")?;
+        let symbol = token.get("symbol").and_then(|v| v.as_str()).map(str::to_string);
+        let decimals = token.get("decimals").and_then(parse_decimals);
+
+        let info = self
+            .tokens
+            .entry((chain_id, address.clone()))
+            .or_insert(TokenInfo { symbol: None, decimals: None });
+        if info.symbol.is_none() {
+            info.symbol = symbol;
+        }
+
+        match (info.decimals, decimals) {
+            (None, reported) => {
+                info.decimals = reported;
+                None
+            }
+            (Some(known), Some(reported)) if known != reported => self
+                .reported
+                .insert((chain_id, address.clone(), reported))
+                .then_some(DecimalsMismatch { address, known, reported }),
+            _ => None,
+        }
+    }
+
+    /// Fills in a missing `symbol` or `decimals` on the bid's lending token from the cache.
+    pub fn enrich(&self, chain_id: i32, bid: &mut serde_json::Value) {
+        let Some(info) = lending_token_address(bid).and_then(|address| self.get(chain_id, &address)) else {
+            return;
+        };
+        let Some(token) = bid.get_mut("lendingToken").and_then(|v| v.as_object_mut()) else {
+            return;
+        };
+
+        if let Some(symbol) = &info.symbol {
+            token.entry("symbol").or_insert_with(|| serde_json::json!(symbol));
+        }
+        if let Some(decimals) = info.decimals {
+            token.entry("decimals").or_insert_with(|| serde_json::json!(decimals));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bid(token: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({ "lendingToken": token })
+    }
+
+    #[test]
+    fn test_parse_decimals() {
+        assert_eq!(parse_decimals(&serde_json::json!(6)), Some(6));
+        assert_eq!(parse_decimals(&serde_json::json!("18")), Some(18));
+        assert_eq!(parse_decimals(&serde_json::json!("n/a")), None);
+        assert_eq!(parse_decimals(&serde_json::Value::Null), None);
+    }
+
+    #[test]
+    fn test_observe_reports_inconsistent_decimals_once() {
+        let mut cache = TokenCache::default();
+
+        assert_eq!(cache.observe(1, &bid(serde_json::json!({ "id": "0xA0b8", "symbol": "USDC", "decimals": 6 }))), None);
+        assert_eq!(cache.observe(1, &bid(serde_json::json!({ "id": "0xa0b8", "decimals": "6" }))), None);
+
+        let odd = bid(serde_json::json!({ "id": "0xa0b8", "decimals": 18 }));
+        assert_eq!(
+            cache.observe(1, &odd),
+            Some(DecimalsMismatch { address: "0xa0b8".to_string(), known: 6, reported: 18 })
+        );
+        assert_eq!(cache.observe(1, &odd), None);
+        // The first value seen stays canonical
+        assert_eq!(cache.get(1, "0xA0B8").and_then(|info| info.decimals), Some(6));
+        // Tokens are tracked per chain
+        assert_eq!(cache.observe(137, &odd), None);
+    }
+
+    #[test]
+    fn test_enrich_fills_missing_metadata() {
+        let mut cache = TokenCache::default();
+        cache.observe(1, &bid(serde_json::json!({ "id": "0xa0b8", "symbol": "USDC", "decimals": 6 })));
+
+        let mut sparse = bid(serde_json::json!({ "id": "0xA0b8" }));
+        cache.enrich(1, &mut sparse);
+        assert_eq!(sparse["lendingToken"]["symbol"], "USDC");
+        assert_eq!(sparse["lendingToken"]["decimals"], 6);
+
+        // Reported values are left alone
+        let mut full = bid(serde_json::json!({ "id": "0xa0b8", "symbol": "USDC.e", "decimals": 6 }));
+        cache.enrich(1, &mut full);
+        assert_eq!(full["lendingToken"]["symbol"], "USDC.e");
+    }
+}
@@ -0,0 +1,125 @@
+use crate::notifier::{AlertSubject, Notifier};
+use crate::{RetryPolicy, make_post_request_with_retry};
+use async_trait::async_trait;
+use reqwest::Client;
+use reqwest::header::HeaderMap;
+
+/// Payload sent when no template is configured.
+pub const DEFAULT_TEMPLATE: &str = r#"{"text": "{message}"}"#;
+
+/// Posts alerts as JSON to an arbitrary webhook, built from a template whose string values may
+/// contain `{message}`, `{chain_id}` and `{bid_id}` placeholders.
+pub struct WebhookNotifier {
+    client: Client,
+    url: String,
+    template: serde_json::Value,
+    retry: RetryPolicy,
+}
+
+impl WebhookNotifier {
+    /// Fails if `template` isn't valid JSON.
+    pub(crate) fn new(client: Client, url: String, template: &str, retry: RetryPolicy) -> Result<Self, String> {
+        let template = serde_json::from_str(template).map_err(|e| format!("WEBHOOK_TEMPLATE is not valid JSON: {}", e))?;
+        Ok(Self { client, url, template, retry })
+    }
+
+    /// The template with its placeholders filled in. Substitution happens inside string values,
+    /// so the message never needs escaping; placeholders with nothing to fill become empty.
+    fn render(&self, msg: &str, subject: AlertSubject) -> serde_json::Value {
+        let chain_id = subject.chain_id.map(|chain_id| chain_id.to_string()).unwrap_or_default();
+        let bid_id = subject.bid_id.unwrap_or("");
+        let mut payload = self.template.clone();
+        // The message goes in last so placeholder-like text inside it is left alone
+        fill_placeholders(&mut payload, &[("{chain_id}", &chain_id), ("{bid_id}", bid_id), ("{message}", msg)]);
+        payload
+    }
+
+    async fn send(&self, payload: serde_json::Value) -> anyhow::Result<()> {
+        let response = make_post_request_with_retry(&self.client, &self.url, payload, None, &HeaderMap::new(), self.retry)
+            .await
+            .map_err(|e| e.without_url())?;
+
+        if !response.status.is_success() {
+            anyhow::bail!("Webhook returned HTTP {}: {}", response.status, response.body);
+        }
+
+        Ok(())
+    }
+}
+
+fn fill_placeholders(value: &mut serde_json::Value, replacements: &[(&str, &str)]) {
+    match value {
+        serde_json::Value::String(text) => {
+            for (placeholder, replacement) in replacements {
+                *text = text.replace(placeholder, replacement);
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|item| fill_placeholders(item, replacements)),
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(|field| fill_placeholders(field, replacements)),
+        _ => {}
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, channel: Option<&str>, msg: &str) -> anyhow::Result<()> {
+        self.notify_about(channel, msg, AlertSubject::default()).await
+    }
+
+    async fn notify_about(&self, _channel: Option<&str>, msg: &str, subject: AlertSubject<'_>) -> anyhow::Result<()> {
+        self.send(self.render(msg, subject)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notifier(url: String, template: &str) -> WebhookNotifier {
+        WebhookNotifier::new(Client::new(), url, template, RetryPolicy { max_retries: 1, base_backoff_ms: 1 }).unwrap()
+    }
+
+    #[test]
+    fn test_render_fills_placeholders() {
+        let webhook = notifier(
+            String::new(),
+            r#"{"summary": "{message}", "tags": ["chain:{chain_id}", "bid:{bid_id}"], "severity": 3}"#,
+        );
+
+        let payload = webhook.render("🚨 \"Overdue\"\nbid 42", AlertSubject::bid(137, "42", None));
+        assert_eq!(
+            payload,
+            serde_json::json!({ "summary": "🚨 \"Overdue\"\nbid 42", "tags": ["chain:137", "bid:42"], "severity": 3 })
+        );
+
+        let payload = webhook.render("RPC down", AlertSubject::chain(1));
+        assert_eq!(payload["tags"], serde_json::json!(["chain:1", "bid:"]));
+
+        let retry = RetryPolicy { max_retries: 0, base_backoff_ms: 1 };
+        assert!(WebhookNotifier::new(Client::new(), String::new(), "{message}", retry).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_notify_reports_non_2xx() {
+        let app = axum::Router::new()
+            .route(
+                "/ok",
+                axum::routing::post(|axum::Json(payload): axum::Json<serde_json::Value>| async move {
+                    assert_eq!(payload, serde_json::json!({ "text": "hello" }));
+                    "ok"
+                }),
+            )
+            .route("/rejected", axum::routing::post(|| async { (axum::http::StatusCode::BAD_REQUEST, "bad payload") }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        notifier(format!("http://{}/ok", addr), DEFAULT_TEMPLATE).notify(None, "hello").await.unwrap();
+
+        let error = notifier(format!("http://{}/rejected", addr), DEFAULT_TEMPLATE)
+            .notify(None, "hello")
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Webhook returned HTTP 400 Bad Request: bad payload");
+    }
+}